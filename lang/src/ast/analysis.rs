@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::lexical_analysis::Token;
+use crate::symbol::Symbol;
+
+use super::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map,
+    Set, Super, This, Unary, Variable,
+};
+use super::parser::ITERATOR;
+use super::statement::{
+    self, Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt,
+    Throw, Try, Var, While,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Method,
+    Module,
+}
+
+pub struct Declaration {
+    pub name: Token,
+    // source of the whole declaration, i.e. a function from `fun` to its closing brace
+    pub span: Range<usize>,
+    pub kind: DeclarationKind,
+    // declared at the top level of the script
+    pub global: bool,
+    // the declaration as it's shown to users, i.e. `fun add(a, b)`
+    pub detail: String,
+    // function or class which the declaration is nested in
+    pub parent: Option<usize>,
+    // declaration of an enclosing scope which has the same name
+    pub shadows: Option<usize>,
+}
+
+pub struct Reference {
+    pub name: Token,
+    // none for globals which the script doesn't declare, i.e. native functions
+    pub declaration: Option<usize>,
+    // the variable is assigned, or a global is declared again
+    pub write: bool,
+}
+
+// Declarations of a script and the variables referring to them, for tools working with names
+// rather than values. Scopes are the ones of the resolver; globals can be used before their
+// declaration, like in functions called after it.
+#[derive(Default)]
+pub struct Analysis {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
+}
+
+impl Analysis {
+    pub fn new(statements: &[Stmt]) -> Self {
+        let mut analyzer = Analyzer::default();
+        for statement in statements {
+            statement.accept(&mut analyzer);
+        }
+
+        analyzer.resolve_globals();
+        analyzer.analysis
+    }
+
+    // name of a declaration or a reference at the character offset, the end of the name included,
+    // with the declaration it stands for
+    pub fn name_at(&self, offset: usize) -> Option<(&Token, usize)> {
+        let contains = |name: &Token| {
+            let span = name.span();
+            span.start <= offset && offset <= span.end
+        };
+
+        let declaration = self
+            .declarations
+            .iter()
+            .enumerate()
+            .find(|(_, declaration)| contains(&declaration.name))
+            .map(|(index, declaration)| (&declaration.name, index));
+
+        declaration.or_else(|| {
+            self.references
+                .iter()
+                .filter(|reference| contains(&reference.name))
+                .find_map(|reference| Some((&reference.name, reference.declaration?)))
+        })
+    }
+
+    pub fn references_to(&self, declaration: usize) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.declaration == Some(declaration))
+    }
+}
+
+#[derive(Default)]
+struct Analyzer {
+    analysis: Analysis,
+    scopes: Vec<HashMap<Symbol, usize>>,
+    globals: HashMap<Symbol, usize>,
+    // references which aren't resolved until every global is declared
+    unresolved: Vec<usize>,
+    parent: Option<usize>,
+}
+
+impl Analyzer {
+    // declarations without a statement of their own, like parameters, span only their name
+    fn declare(
+        &mut self,
+        name: &Token,
+        span: Option<Range<usize>>,
+        kind: DeclarationKind,
+        detail: String,
+    ) -> Option<usize> {
+        // variables of desugared code aren't written by users
+        if &*name.lexeme == ITERATOR {
+            return None;
+        }
+
+        let global = self.scopes.is_empty() && kind != DeclarationKind::Method;
+        if global {
+            if let Some(&declaration) = self.globals.get(&name.lexeme) {
+                self.refer(name, Some(declaration), true);
+                return Some(declaration);
+            }
+        }
+
+        // methods aren't variables, they're looked up on instances
+        let shadows = match kind {
+            DeclarationKind::Method => None,
+            _ => self.lookup(&name.lexeme),
+        };
+        let declaration = self.analysis.declarations.len();
+        self.analysis.declarations.push(Declaration {
+            name: name.clone(),
+            span: span.unwrap_or_else(|| name.span()),
+            kind,
+            global,
+            detail,
+            parent: self.parent,
+            shadows,
+        });
+
+        match self.scopes.last_mut() {
+            _ if kind == DeclarationKind::Method => {}
+            Some(scope) => {
+                scope.insert(name.lexeme.clone(), declaration);
+            }
+            None => {
+                self.globals.insert(name.lexeme.clone(), declaration);
+            }
+        }
+        Some(declaration)
+    }
+
+    fn lookup(&self, name: &Symbol) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+    }
+
+    fn resolve(&mut self, name: &Token, write: bool) {
+        if &*name.lexeme == ITERATOR {
+            return;
+        }
+
+        let declaration = self.lookup(&name.lexeme);
+        if declaration.is_none() {
+            self.unresolved.push(self.analysis.references.len());
+        }
+        self.refer(name, declaration, write);
+    }
+
+    fn refer(&mut self, name: &Token, declaration: Option<usize>, write: bool) {
+        self.analysis.references.push(Reference {
+            name: name.clone(),
+            declaration,
+            write,
+        });
+    }
+
+    fn resolve_globals(&mut self) {
+        for reference in std::mem::take(&mut self.unresolved) {
+            let reference = &mut self.analysis.references[reference];
+            reference.declaration = self.globals.get(&reference.name.lexeme).copied();
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn scoped(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.statements(statements);
+        self.scopes.pop();
+    }
+
+    fn function(&mut self, function: &Function, declaration: Option<usize>) {
+        let parent = std::mem::replace(&mut self.parent, declaration);
+        self.scopes.push(HashMap::new());
+
+        for param in &function.params {
+            let detail = format!("(parameter) {}", param.lexeme);
+            self.declare(param, None, DeclarationKind::Parameter, detail);
+        }
+        self.statements(&function.body);
+
+        self.scopes.pop();
+        self.parent = parent;
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+}
+
+fn params(function: &Function) -> String {
+    let params: Vec<&str> = function.params.iter().map(|param| &*param.lexeme).collect();
+    params.join(", ")
+}
+
+impl statement::Visitor<()> for Analyzer {
+    fn visit_block_stmt(&mut self, stmt: &Block) {
+        self.scoped(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &Class) {
+        let mut detail = format!("class {}", stmt.name.lexeme);
+        if let Some(superclass) = &stmt.superclass {
+            detail += &format!(" < {}", superclass.name.lexeme);
+        }
+
+        let class = self.declare(&stmt.name, stmt.span(), DeclarationKind::Class, detail);
+        if let Some(superclass) = &stmt.superclass {
+            self.resolve(&superclass.name, false);
+        }
+
+        let parent = std::mem::replace(&mut self.parent, class);
+        for method in &stmt.methods {
+            let detail = format!(
+                "(method) {}.{}({})",
+                stmt.name.lexeme,
+                method.name.lexeme,
+                params(method)
+            );
+            let declaration =
+                self.declare(&method.name, method.span(), DeclarationKind::Method, detail);
+            self.function(method, declaration);
+        }
+        self.parent = parent;
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) {
+        self.expression(&stmt.expression);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Function) {
+        let detail = format!("fun {}({})", stmt.name.lexeme, params(stmt));
+        let declaration = self.declare(&stmt.name, stmt.span(), DeclarationKind::Function, detail);
+        self.function(stmt, declaration);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) {
+        self.expression(&stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) {
+        if let Some(name) = &stmt.name {
+            let detail = format!("import {} as {}", stmt.path.lexeme, name.lexeme);
+            self.declare(name, stmt.span(), DeclarationKind::Module, detail);
+        }
+        for name in &stmt.names {
+            let detail = format!("from {} import {}", stmt.path.lexeme, name.lexeme);
+            self.declare(name, stmt.span(), DeclarationKind::Variable, detail);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) {
+        self.expression(&stmt.expression);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) {
+        if let Some(value) = &stmt.value {
+            self.expression(value);
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) {
+        self.expression(&stmt.value);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) {
+        self.scoped(&stmt.body);
+
+        if let Some(name) = &stmt.catch_name {
+            self.scopes.push(HashMap::new());
+            let detail = format!("catch ({})", name.lexeme);
+            self.declare(name, None, DeclarationKind::Variable, detail);
+            self.statements(&stmt.catch_body);
+            self.scopes.pop();
+        }
+
+        if let Some(finally_body) = &stmt.finally_body {
+            self.scoped(finally_body);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        if let Some(initializer) = &stmt.initializer {
+            self.expression(initializer);
+        }
+
+        let detail = format!("var {}", stmt.name.lexeme);
+        self.declare(&stmt.name, stmt.span(), DeclarationKind::Variable, detail);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) {
+        self.expression(&stmt.condition);
+        stmt.body.accept(self);
+        if let Some(increment) = &stmt.increment {
+            self.expression(increment);
+        }
+    }
+}
+
+impl expression::Visitor<()> for Analyzer {
+    fn visit_assign_expr(&mut self, expr: &Assign) {
+        self.expression(&expr.value);
+        self.resolve(&expr.name, true);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) {
+        self.expression(&expr.left);
+        self.expression(&expr.right);
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) {
+        self.expression(&expr.callee);
+        for argument in &expr.arguments {
+            self.expression(argument);
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) {
+        self.expression(&expr.object);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) {
+        self.expression(&expr.expression);
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) {
+        self.expression(&expr.object);
+        self.expression(&expr.index);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) {
+        self.expression(&expr.object);
+        self.expression(&expr.index);
+        self.expression(&expr.value);
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) {
+        for element in &expr.elements {
+            self.expression(element);
+        }
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &Logical) {
+        self.expression(&expr.left);
+        self.expression(&expr.right);
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) {
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            self.expression(key);
+            self.expression(value);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) {
+        self.expression(&expr.object);
+        self.expression(&expr.value);
+    }
+
+    fn visit_super_expr(&mut self, _expr: &Super) {}
+
+    fn visit_this_expr(&mut self, _expr: &This) {}
+
+    fn visit_unary_expr(&mut self, expr: &Unary) {
+        self.expression(&expr.right);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) {
+        self.resolve(&expr.name, false);
+    }
+}
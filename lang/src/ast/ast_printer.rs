@@ -1,44 +1,110 @@
-use super::expression::{Binary, Expr, Grouping, Literal, Unary, Visitor};
+use super::expression::{
+    Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map, Set,
+    Super, This, Unary, Variable, Visitor,
+};
+use super::literal_value::LiteralValue;
 
 pub struct AstPrinter {}
 
-impl Visitor for AstPrinter {
-    fn visit_binary_expr(&self, expr: &Binary) -> String {
+impl Visitor<String> for AstPrinter {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        self.parenthesize(format!("= {}", expr.name.lexeme), vec![&expr.value])
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> String {
         self.parenthesize(
             expr.operator.lexeme.to_string(),
             vec![&expr.left, &expr.right],
         )
     }
 
-    fn visit_grouping_expr(&self, expr: &Grouping) -> String {
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+        self.parenthesize("call".to_string(), exprs)
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) -> String {
+        self.parenthesize(format!(". {}", expr.name.lexeme), vec![&expr.object])
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> String {
         self.parenthesize("group".to_string(), vec![&expr.expression])
     }
 
-    fn visit_literal_expr(&self, expr: &Literal) -> String {
-        if expr.value == "" {
-            return "nil".to_string();
+    fn visit_index_expr(&mut self, expr: &Index) -> String {
+        self.parenthesize("[]".to_string(), vec![&expr.object, &expr.index])
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> String {
+        self.parenthesize(
+            "= []".to_string(),
+            vec![&expr.object, &expr.index, &expr.value],
+        )
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> String {
+        self.parenthesize("list".to_string(), expr.elements.iter().collect())
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        match &expr.value {
+            LiteralValue::String(value) => format!("\"{value}\""),
+            value => value.to_string(),
         }
+    }
 
-        expr.value.to_string()
+    fn visit_logical_expr(&mut self, expr: &Logical) -> String {
+        self.parenthesize(
+            expr.operator.lexeme.to_string(),
+            vec![&expr.left, &expr.right],
+        )
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) -> String {
+        let entries = expr
+            .keys
+            .iter()
+            .zip(&expr.values)
+            .flat_map(|(key, value)| [key, value])
+            .collect();
+        self.parenthesize("map".to_string(), entries)
     }
 
-    fn visit_unary_expr(&self, expr: &Unary) -> String {
+    fn visit_set_expr(&mut self, expr: &Set) -> String {
+        self.parenthesize(
+            format!("= . {}", expr.name.lexeme),
+            vec![&expr.object, &expr.value],
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) -> String {
+        format!("(super {})", expr.method.lexeme)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &This) -> String {
+        "this".to_string()
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> String {
         self.parenthesize(expr.operator.lexeme.to_string(), vec![&expr.right])
     }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
 }
 
 impl AstPrinter {
-    pub fn print(&self, expr: Box<dyn Expr>) -> String {
-        return expr.accept(self);
+    pub fn print(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
     }
 
-    fn parenthesize(&self, name: String, exprs: Vec<&Box<dyn Expr>>) -> String {
-        //TODO
+    fn parenthesize(&mut self, name: String, exprs: Vec<&Expr>) -> String {
         let mut parenthesized = "(".to_string() + &name;
         for expr in &exprs {
-            parenthesized = parenthesized + " " + &expr.accept(self).to_string();
+            parenthesized = parenthesized + " " + &expr.accept(self);
         }
-        parenthesized = parenthesized + &")".to_string();
-        return parenthesized;
+        parenthesized + ")"
     }
 }
@@ -0,0 +1,347 @@
+use super::ast_printer::AstPrinter;
+use super::export::escape_label;
+use super::expression::Expr;
+use super::statement::{Function, Stmt};
+
+const ENTRY: usize = 0;
+const EXIT: usize = 1;
+
+// Control-flow graphs of the top level of a script and of each function and method, as a Graphviz
+// DOT graph with a cluster per function. Basic blocks list their statements with expressions
+// printed by `AstPrinter`; edges are labelled with the branch of an `if` or a loop condition which
+// takes them, back edges of loops are bold and edges of exceptions dashed. Statements after a
+// `return`, `throw`, `break` or `continue` start a block which nothing leads to.
+pub fn dot(statements: &[Stmt]) -> String {
+    let mut graphs = Vec::new();
+    let mut functions = vec![("script".to_string(), statements)];
+
+    while let Some((name, body)) = functions.pop() {
+        let mut builder = Builder::new();
+        builder.statements(body);
+        builder.finish();
+
+        functions.extend(builder.functions.drain(..).rev());
+        graphs.push((name, builder.blocks));
+    }
+
+    let mut output = "digraph cfg {\n  node [shape=box, fontname=\"monospace\"];\n".to_string();
+    for (index, (name, blocks)) in graphs.iter().enumerate() {
+        output += &format!("  subgraph cluster_{index} {{\n");
+        output += &format!("    label=\"{}\";\n", escape_label(name));
+
+        for (block, contents) in blocks.iter().enumerate() {
+            let label = match block {
+                ENTRY => "entry".to_string(),
+                EXIT => "exit".to_string(),
+                _ => contents
+                    .statements
+                    .iter()
+                    .map(|statement| escape_label(statement) + "\\l")
+                    .collect(),
+            };
+            output += &format!("    f{index}_b{block} [label=\"{label}\"];\n");
+        }
+
+        for (block, contents) in blocks.iter().enumerate() {
+            for edge in &contents.edges {
+                let mut attributes = Vec::new();
+                if !edge.label.is_empty() {
+                    attributes.push(format!("label=\"{}\"", edge.label));
+                }
+                match edge.kind {
+                    EdgeKind::Normal => {}
+                    EdgeKind::Back => attributes.push("style=bold".to_string()),
+                    EdgeKind::Exception => attributes.push("style=dashed".to_string()),
+                }
+
+                output += &format!("    f{index}_b{block} -> f{index}_b{}", edge.target);
+                if !attributes.is_empty() {
+                    output += &format!(" [{}]", attributes.join(", "));
+                }
+                output += ";\n";
+            }
+        }
+        output += "  }\n";
+    }
+    output + "}\n"
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EdgeKind {
+    Normal,
+    // from the end of a loop body back to its condition
+    Back,
+    Exception,
+}
+
+struct Edge {
+    target: usize,
+    label: &'static str,
+    kind: EdgeKind,
+}
+
+#[derive(Default)]
+struct BasicBlock {
+    statements: Vec<String>,
+    edges: Vec<Edge>,
+}
+
+struct Loop {
+    // where `continue` goes, the increment or the condition
+    next: usize,
+    exit: usize,
+    condition: usize,
+}
+
+impl Loop {
+    // jumps to the condition go back, jumps to the increment reach it first
+    fn kind(&self) -> EdgeKind {
+        match self.next == self.condition {
+            true => EdgeKind::Back,
+            false => EdgeKind::Normal,
+        }
+    }
+}
+
+struct Builder<'a> {
+    blocks: Vec<BasicBlock>,
+    // block which the next statement is added to, none after a jump
+    current: Option<usize>,
+    loops: Vec<Loop>,
+    // catch or finally blocks of the enclosing `try` statements
+    handlers: Vec<usize>,
+    // functions declared in the code, which get graphs of their own
+    functions: Vec<(String, &'a [Stmt])>,
+}
+
+impl<'a> Builder<'a> {
+    fn new() -> Self {
+        Self {
+            blocks: vec![BasicBlock::default(), BasicBlock::default()],
+            current: Some(ENTRY),
+            loops: Vec::new(),
+            handlers: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    fn block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: Option<usize>, target: usize, label: &'static str, kind: EdgeKind) {
+        if let Some(from) = from {
+            self.blocks[from].edges.push(Edge {
+                target,
+                label,
+                kind,
+            });
+        }
+    }
+
+    // starts a block which the current one falls through to, an empty one is used as it is
+    fn start(&mut self) -> usize {
+        if let Some(current) = self.current {
+            if current != ENTRY && self.blocks[current].statements.is_empty() {
+                return current;
+            }
+        }
+
+        let block = self.block();
+        self.edge(self.current, block, "", EdgeKind::Normal);
+        self.current = Some(block);
+        block
+    }
+
+    fn emit(&mut self, statement: String) {
+        // the entry block only marks where the function starts
+        let current = match self.current {
+            Some(ENTRY) | None => {
+                let block = self.block();
+                self.edge(self.current, block, "", EdgeKind::Normal);
+                block
+            }
+            Some(block) => block,
+        };
+        self.blocks[current].statements.push(statement);
+        self.current = Some(current);
+    }
+
+    // ends the current block with a jump
+    fn jump(&mut self, statement: String, target: usize, label: &'static str, kind: EdgeKind) {
+        self.emit(statement);
+        self.edge(self.current, target, label, kind);
+        self.current = None;
+    }
+
+    fn finish(&mut self) {
+        self.edge(self.current, EXIT, "", EdgeKind::Normal);
+    }
+
+    fn statements(&mut self, statements: &'a [Stmt]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn function(&mut self, name: String, function: &'a Function) {
+        self.functions.push((name, &function.body));
+    }
+
+    fn statement(&mut self, statement: &'a Stmt) {
+        match statement {
+            Stmt::Block(stmt) => self.statements(&stmt.statements),
+            Stmt::Break(_) => {
+                let exit = self.loops.last().map_or(EXIT, |enclosing| enclosing.exit);
+                self.jump("break".to_string(), exit, "break", EdgeKind::Normal);
+            }
+            Stmt::Class(stmt) => {
+                self.emit(format!("class {}", stmt.name.lexeme));
+                for method in &stmt.methods {
+                    let name = format!("{}.{}", stmt.name.lexeme, method.name.lexeme);
+                    self.function(name, method);
+                }
+            }
+            Stmt::Continue(_) => {
+                let (next, kind) = match self.loops.last() {
+                    Some(enclosing) => (enclosing.next, enclosing.kind()),
+                    None => (EXIT, EdgeKind::Normal),
+                };
+                self.jump("continue".to_string(), next, "continue", kind);
+            }
+            Stmt::Expression(stmt) => self.emit(print(&stmt.expression)),
+            Stmt::Function(stmt) => {
+                self.emit(format!("fun {}", stmt.name.lexeme));
+                self.function(format!("fun {}", stmt.name.lexeme), stmt);
+            }
+            Stmt::If(stmt) => {
+                self.emit(format!("if {}", print(&stmt.condition)));
+                let branch = self.current;
+
+                let then_branch = self.block();
+                self.edge(branch, then_branch, "true", EdgeKind::Normal);
+                self.current = Some(then_branch);
+                self.statement(&stmt.then_branch);
+                let then_end = self.current;
+
+                let else_end = match &stmt.else_branch {
+                    Some(else_branch) => {
+                        let block = self.block();
+                        self.edge(branch, block, "false", EdgeKind::Normal);
+                        self.current = Some(block);
+                        self.statement(else_branch);
+                        self.current
+                    }
+                    None => None,
+                };
+
+                let join = self.block();
+                self.edge(then_end, join, "", EdgeKind::Normal);
+                match &stmt.else_branch {
+                    Some(_) => self.edge(else_end, join, "", EdgeKind::Normal),
+                    None => self.edge(branch, join, "false", EdgeKind::Normal),
+                }
+                self.current = Some(join);
+            }
+            Stmt::Import(stmt) => self.emit(format!("import {}", stmt.path.lexeme)),
+            Stmt::Print(stmt) => self.emit(format!("print {}", print(&stmt.expression))),
+            Stmt::Return(stmt) => {
+                let statement = match &stmt.value {
+                    Some(value) => format!("return {}", print(value)),
+                    None => "return".to_string(),
+                };
+                self.jump(statement, EXIT, "return", EdgeKind::Normal);
+            }
+            Stmt::Throw(stmt) => {
+                let handler = self.handlers.last().copied().unwrap_or(EXIT);
+                let statement = format!("throw {}", print(&stmt.value));
+                self.jump(statement, handler, "throw", EdgeKind::Exception);
+            }
+            Stmt::Try(stmt) => {
+                let finally = self.block();
+                let catch = match &stmt.catch_name {
+                    Some(name) => {
+                        let catch = self.block();
+                        self.blocks[catch]
+                            .statements
+                            .push(format!("catch ({})", name.lexeme));
+                        catch
+                    }
+                    None => finally,
+                };
+
+                // any statement of the body can throw
+                self.emit("try".to_string());
+                self.edge(self.current, catch, "throw", EdgeKind::Exception);
+                self.handlers.push(catch);
+                self.start();
+                self.statements(&stmt.body);
+                self.handlers.pop();
+                self.edge(self.current, finally, "", EdgeKind::Normal);
+
+                if catch != finally {
+                    self.current = Some(catch);
+                    self.statements(&stmt.catch_body);
+                    self.edge(self.current, finally, "", EdgeKind::Normal);
+                }
+
+                self.current = Some(finally);
+                if let Some(finally_body) = &stmt.finally_body {
+                    self.blocks[finally].statements.push("finally".to_string());
+                    self.statements(finally_body);
+                }
+            }
+            Stmt::Var(stmt) => {
+                let statement = match &stmt.initializer {
+                    Some(initializer) => {
+                        format!("var {} = {}", stmt.name.lexeme, print(initializer))
+                    }
+                    None => format!("var {}", stmt.name.lexeme),
+                };
+                self.emit(statement);
+            }
+            Stmt::While(stmt) => {
+                let condition = self.start();
+                self.blocks[condition].statements.push(format!(
+                    "{} {}",
+                    stmt.keyword.lexeme,
+                    print(&stmt.condition)
+                ));
+
+                let body = self.block();
+                self.edge(Some(condition), body, "true", EdgeKind::Normal);
+                let exit = self.block();
+                self.edge(Some(condition), exit, "false", EdgeKind::Normal);
+
+                let next = match &stmt.increment {
+                    Some(increment) => {
+                        let block = self.block();
+                        self.blocks[block].statements.push(print(increment));
+                        self.edge(Some(block), condition, "", EdgeKind::Back);
+                        block
+                    }
+                    None => condition,
+                };
+
+                let enclosing = Loop {
+                    next,
+                    exit,
+                    condition,
+                };
+                let kind = enclosing.kind();
+                self.loops.push(enclosing);
+                self.current = Some(body);
+                self.statement(&stmt.body);
+                self.loops.pop();
+
+                self.edge(self.current, next, "", kind);
+                self.current = Some(exit);
+            }
+        }
+    }
+}
+
+fn print(expr: &Expr) -> String {
+    AstPrinter {}.print(expr)
+}
@@ -0,0 +1,596 @@
+use std::ops::Range;
+
+use crate::json::Json;
+use crate::lexical_analysis::Token;
+
+use super::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map,
+    Set, Super, This, Unary, Variable,
+};
+use super::literal_value::LiteralValue;
+use super::node_id::NodeId;
+use super::resolver::Depths;
+use super::statement::{
+    self, Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt,
+    Throw, Try, Var, While,
+};
+use super::Script;
+
+// bumped when a node or a field changes, so tools can tell which trees they understand
+pub const SCHEMA_VERSION: usize = 3;
+
+// Parse tree of a script as JSON:
+// `{"version": 3, "statements": [node, ...]}`. A node is an object with its `type`, the name of
+// its struct in `expression.rs` or `statement.rs`, its `span` and a key for every field of the
+// struct under the same name, starting with its `id`. Nodes are nested as objects, tokens are
+// `{"lexeme", "line", "start", "end"}`, literal values are JSON values, resolved `depth`s are
+// numbers, and missing optional fields are null. A span is `{"line", "start", "end"}` in characters
+// of the source and covers the tokens which the node and its children keep, which run from its
+// first token to its last, keywords, closing brackets and semicolons included. Loops are exported
+// as the parser desugars them, with tokens of the desugared code placed at the loop.
+pub fn json(script: &Script) -> Json {
+    let mut exporter = Exporter::new(script);
+    let statements: Vec<Json> = script
+        .statements
+        .iter()
+        .map(|statement| statement.accept(&mut exporter).to_json())
+        .collect();
+
+    Json::object([
+        ("version", SCHEMA_VERSION.into()),
+        ("statements", statements.into()),
+    ])
+}
+
+// Parse tree of a script as S-expressions, one line per statement: a node is
+// `(Type (span line start end) (field value) ...)` with the fields of the JSON export, tokens are
+// their lexemes as strings and missing fields are `nil`.
+pub fn sexp(script: &Script) -> String {
+    let mut exporter = Exporter::new(script);
+    script
+        .statements
+        .iter()
+        .map(|statement| statement.accept(&mut exporter).to_sexp() + "\n")
+        .collect()
+}
+
+// Parse tree as a Graphviz DOT graph: a box per node labelled with its type, tokens and values, and
+// an edge to each child labelled with the field which holds it.
+pub fn dot(script: &Script) -> String {
+    let mut output = "digraph ast {\n  node [shape=box, fontname=\"monospace\"];\n".to_string();
+    output += "  n0 [label=\"Script\"];\n";
+
+    let mut exporter = Exporter::new(script);
+    let mut next = 1;
+    for (index, statement) in script.statements.iter().enumerate() {
+        let node = statement
+            .accept(&mut exporter)
+            .to_dot(&mut output, &mut next);
+        output += &format!("  n0 -> n{node} [label=\"statements[{index}]\"];\n");
+    }
+    output + "}\n"
+}
+
+// text of a label of a DOT graph, in which lines are separated by `\n`
+pub(crate) fn escape_label(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+enum Field {
+    Node(Node),
+    Nodes(Vec<Node>),
+    Token(Token),
+    Tokens(Vec<Token>),
+    Value(Json),
+}
+
+struct Node {
+    kind: &'static str,
+    // characters of the source and the line where they start
+    span: Option<(Range<usize>, usize)>,
+    fields: Vec<(&'static str, Field)>,
+}
+
+impl Node {
+    fn new(kind: &'static str, id: NodeId) -> Self {
+        Self {
+            kind,
+            span: None,
+            fields: vec![("id", Field::Value(id.index().into()))],
+        }
+    }
+
+    fn cover(&mut self, range: Range<usize>, line: usize) {
+        self.span = Some(match self.span.take() {
+            None => (range, line),
+            Some((span, span_line)) => {
+                let line = if range.start < span.start {
+                    line
+                } else {
+                    span_line
+                };
+                (span.start.min(range.start)..span.end.max(range.end), line)
+            }
+        });
+    }
+
+    fn token(mut self, name: &'static str, token: &Token) -> Self {
+        self.cover(token.span(), token.line);
+        self.fields.push((name, Field::Token(token.clone())));
+        self
+    }
+
+    fn optional_token(self, name: &'static str, token: Option<&Token>) -> Self {
+        match token {
+            Some(token) => self.token(name, token),
+            None => self.value(name, Json::Null),
+        }
+    }
+
+    fn tokens(mut self, name: &'static str, tokens: &[Token]) -> Self {
+        for token in tokens {
+            self.cover(token.span(), token.line);
+        }
+        self.fields.push((name, Field::Tokens(tokens.to_vec())));
+        self
+    }
+
+    fn node(mut self, name: &'static str, node: Node) -> Self {
+        if let Some((range, line)) = node.span.clone() {
+            self.cover(range, line);
+        }
+        self.fields.push((name, Field::Node(node)));
+        self
+    }
+
+    fn optional_node(self, name: &'static str, node: Option<Node>) -> Self {
+        match node {
+            Some(node) => self.node(name, node),
+            None => self.value(name, Json::Null),
+        }
+    }
+
+    fn nodes(mut self, name: &'static str, nodes: Vec<Node>) -> Self {
+        for node in &nodes {
+            if let Some((range, line)) = node.span.clone() {
+                self.cover(range, line);
+            }
+        }
+        self.fields.push((name, Field::Nodes(nodes)));
+        self
+    }
+
+    fn value(mut self, name: &'static str, value: Json) -> Self {
+        self.fields.push((name, Field::Value(value)));
+        self
+    }
+
+    fn to_json(&self) -> Json {
+        let span = self.span.as_ref().map_or(Json::Null, |(range, line)| {
+            Json::object([
+                ("line", (*line).into()),
+                ("start", range.start.into()),
+                ("end", range.end.into()),
+            ])
+        });
+
+        let mut entries = vec![
+            ("type".to_string(), self.kind.into()),
+            ("span".to_string(), span),
+        ];
+        entries.extend(self.fields.iter().map(|(name, field)| {
+            let value = match field {
+                Field::Node(node) => node.to_json(),
+                Field::Nodes(nodes) => nodes.iter().map(Node::to_json).collect::<Vec<_>>().into(),
+                Field::Token(token) => token_json(token),
+                Field::Tokens(tokens) => tokens.iter().map(token_json).collect::<Vec<_>>().into(),
+                Field::Value(value) => value.clone(),
+            };
+            (name.to_string(), value)
+        }));
+        Json::Object(entries)
+    }
+
+    // writes the node and its children, returns the number of its DOT node
+    fn to_dot(&self, output: &mut String, next: &mut usize) -> usize {
+        let id = *next;
+        *next += 1;
+
+        let mut label = vec![self.kind.to_string()];
+        for (name, field) in &self.fields {
+            match field {
+                Field::Token(token) => label.push(format!("{name}: {}", token.lexeme)),
+                Field::Tokens(tokens) if !tokens.is_empty() => {
+                    let lexemes: Vec<&str> = tokens.iter().map(|token| &*token.lexeme).collect();
+                    label.push(format!("{name}: {}", lexemes.join(", ")));
+                }
+                Field::Value(value) if *value != Json::Null => {
+                    label.push(format!("{name}: {value}"));
+                }
+                _ => {}
+            }
+        }
+        let label: Vec<String> = label.iter().map(|line| escape_label(line)).collect();
+        *output += &format!("  n{id} [label=\"{}\"];\n", label.join("\\n"));
+
+        for (name, field) in &self.fields {
+            let children = match field {
+                Field::Node(node) => vec![(node, name.to_string())],
+                Field::Nodes(nodes) => nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, node)| (node, format!("{name}[{index}]")))
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            for (child, edge) in children {
+                let child = child.to_dot(output, next);
+                *output += &format!("  n{id} -> n{child} [label=\"{edge}\"];\n");
+            }
+        }
+        id
+    }
+
+    fn to_sexp(&self) -> String {
+        let mut sexp = format!("({}", self.kind);
+        if let Some((range, line)) = &self.span {
+            sexp += &format!(" (span {line} {} {})", range.start, range.end);
+        }
+
+        for (name, field) in &self.fields {
+            let value = match field {
+                Field::Node(node) => node.to_sexp(),
+                Field::Nodes(nodes) => list(nodes.iter().map(Node::to_sexp)),
+                Field::Token(token) => Json::from(&*token.lexeme).to_string(),
+                Field::Tokens(tokens) => list(
+                    tokens
+                        .iter()
+                        .map(|token| Json::from(&*token.lexeme).to_string()),
+                ),
+                Field::Value(Json::Null) => "nil".to_string(),
+                Field::Value(value) => value.to_string(),
+            };
+            sexp += &format!(" ({name} {value})");
+        }
+        sexp + ")"
+    }
+}
+
+fn token_json(token: &Token) -> Json {
+    let span = token.span();
+    Json::object([
+        ("lexeme", (*token.lexeme).into()),
+        ("line", token.line.into()),
+        ("start", span.start.into()),
+        ("end", span.end.into()),
+    ])
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    format!("({})", items.collect::<Vec<_>>().join(" "))
+}
+
+struct Exporter<'a> {
+    depths: &'a Depths,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(script: &'a Script) -> Self {
+        Self {
+            depths: &script.depths,
+        }
+    }
+
+    // scope distance of a resolved variable, null for a global
+    fn depth(&self, id: NodeId) -> Json {
+        self.depths.get(id).into()
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) -> Vec<Node> {
+        statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect()
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Node {
+        expr.accept(self)
+    }
+
+    fn expressions(&mut self, exprs: &[Expr]) -> Vec<Node> {
+        exprs.iter().map(|expr| expr.accept(self)).collect()
+    }
+}
+
+impl statement::Visitor<Node> for Exporter<'_> {
+    fn visit_block_stmt(&mut self, stmt: &Block) -> Node {
+        let statements = self.statements(&stmt.statements);
+        Node::new("Block", stmt.id)
+            .token("brace", &stmt.brace)
+            .nodes("statements", statements)
+            .token("closing_brace", &stmt.closing_brace)
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &Break) -> Node {
+        Node::new("Break", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Class) -> Node {
+        let superclass = stmt
+            .superclass
+            .as_ref()
+            .map(|superclass| expression::Visitor::visit_variable_expr(self, superclass));
+        let methods = stmt
+            .methods
+            .iter()
+            .map(|method| self.visit_function_stmt(method))
+            .collect();
+
+        Node::new("Class", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .token("name", &stmt.name)
+            .optional_node("superclass", superclass)
+            .nodes("methods", methods)
+            .token("closing_brace", &stmt.closing_brace)
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &Continue) -> Node {
+        Node::new("Continue", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) -> Node {
+        let expression = self.expression(&stmt.expression);
+        Node::new("Expression", stmt.id)
+            .node("expression", expression)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Function) -> Node {
+        let body = self.statements(&stmt.body);
+        Node::new("Function", stmt.id)
+            .optional_token("keyword", stmt.keyword.as_ref())
+            .token("name", &stmt.name)
+            .tokens("params", &stmt.params)
+            .nodes("body", body)
+            .token("closing_brace", &stmt.closing_brace)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) -> Node {
+        let condition = self.expression(&stmt.condition);
+        let then_branch = stmt.then_branch.accept(self);
+        let else_branch = stmt
+            .else_branch
+            .as_ref()
+            .map(|else_branch| else_branch.accept(self));
+
+        Node::new("If", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .node("condition", condition)
+            .node("then_branch", then_branch)
+            .optional_node("else_branch", else_branch)
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) -> Node {
+        Node::new("Import", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .token("path", &stmt.path)
+            .optional_token("name", stmt.name.as_ref())
+            .tokens("names", &stmt.names)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) -> Node {
+        let expression = self.expression(&stmt.expression);
+        Node::new("Print", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .node("expression", expression)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) -> Node {
+        let value = stmt.value.as_ref().map(|value| self.expression(value));
+        Node::new("Return", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .optional_node("value", value)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) -> Node {
+        let value = self.expression(&stmt.value);
+        Node::new("Throw", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .node("value", value)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) -> Node {
+        let body = self.statements(&stmt.body);
+        let catch_body = self.statements(&stmt.catch_body);
+        let finally_body = stmt
+            .finally_body
+            .as_ref()
+            .map(|finally_body| self.statements(finally_body));
+
+        let node = Node::new("Try", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .nodes("body", body)
+            .optional_token("catch_name", stmt.catch_name.as_ref())
+            .nodes("catch_body", catch_body);
+        let node = match finally_body {
+            Some(finally_body) => node.nodes("finally_body", finally_body),
+            None => node.value("finally_body", Json::Null),
+        };
+        node.token("closing_brace", &stmt.closing_brace)
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) -> Node {
+        let initializer = stmt
+            .initializer
+            .as_ref()
+            .map(|initializer| self.expression(initializer));
+        Node::new("Var", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .token("name", &stmt.name)
+            .optional_node("initializer", initializer)
+            .token("semicolon", &stmt.semicolon)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) -> Node {
+        let condition = self.expression(&stmt.condition);
+        let body = stmt.body.accept(self);
+        let increment = stmt
+            .increment
+            .as_ref()
+            .map(|increment| self.expression(increment));
+
+        Node::new("While", stmt.id)
+            .token("keyword", &stmt.keyword)
+            .node("condition", condition)
+            .node("body", body)
+            .optional_node("increment", increment)
+    }
+}
+
+impl expression::Visitor<Node> for Exporter<'_> {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> Node {
+        let value = self.expression(&expr.value);
+        Node::new("Assign", expr.id)
+            .token("name", &expr.name)
+            .node("value", value)
+            .value("depth", self.depth(expr.id))
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> Node {
+        let left = self.expression(&expr.left);
+        let right = self.expression(&expr.right);
+        Node::new("Binary", expr.id)
+            .node("left", left)
+            .token("operator", &expr.operator)
+            .node("right", right)
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> Node {
+        let callee = self.expression(&expr.callee);
+        let arguments = self.expressions(&expr.arguments);
+        Node::new("Call", expr.id)
+            .node("callee", callee)
+            .token("paren", &expr.paren)
+            .nodes("arguments", arguments)
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) -> Node {
+        let object = self.expression(&expr.object);
+        Node::new("Get", expr.id)
+            .node("object", object)
+            .token("name", &expr.name)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> Node {
+        let expression = self.expression(&expr.expression);
+        Node::new("Grouping", expr.id)
+            .token("paren", &expr.paren)
+            .node("expression", expression)
+            .token("closing_paren", &expr.closing_paren)
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) -> Node {
+        let object = self.expression(&expr.object);
+        let index = self.expression(&expr.index);
+        Node::new("Index", expr.id)
+            .node("object", object)
+            .token("bracket", &expr.bracket)
+            .node("index", index)
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> Node {
+        let object = self.expression(&expr.object);
+        let index = self.expression(&expr.index);
+        let value = self.expression(&expr.value);
+        Node::new("IndexSet", expr.id)
+            .node("object", object)
+            .token("bracket", &expr.bracket)
+            .node("index", index)
+            .node("value", value)
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> Node {
+        let elements = self.expressions(&expr.elements);
+        Node::new("List", expr.id)
+            .token("bracket", &expr.bracket)
+            .nodes("elements", elements)
+            .token("closing_bracket", &expr.closing_bracket)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> Node {
+        let value = match &expr.value {
+            LiteralValue::Nil => Json::Null,
+            LiteralValue::Bool(value) => (*value).into(),
+            LiteralValue::Number(value) => (*value).into(),
+            LiteralValue::String(value) => (**value).into(),
+        };
+        Node::new("Literal", expr.id)
+            .token("token", &expr.token)
+            .value("value", value)
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Logical) -> Node {
+        let left = self.expression(&expr.left);
+        let right = self.expression(&expr.right);
+        Node::new("Logical", expr.id)
+            .node("left", left)
+            .token("operator", &expr.operator)
+            .node("right", right)
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) -> Node {
+        let keys = self.expressions(&expr.keys);
+        let values = self.expressions(&expr.values);
+        Node::new("Map", expr.id)
+            .token("brace", &expr.brace)
+            .nodes("keys", keys)
+            .nodes("values", values)
+            .token("closing_brace", &expr.closing_brace)
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) -> Node {
+        let object = self.expression(&expr.object);
+        let value = self.expression(&expr.value);
+        Node::new("Set", expr.id)
+            .node("object", object)
+            .token("name", &expr.name)
+            .node("value", value)
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) -> Node {
+        Node::new("Super", expr.id)
+            .token("keyword", &expr.keyword)
+            .token("method", &expr.method)
+            .value("depth", self.depth(expr.id))
+    }
+
+    fn visit_this_expr(&mut self, expr: &This) -> Node {
+        Node::new("This", expr.id)
+            .token("keyword", &expr.keyword)
+            .value("depth", self.depth(expr.id))
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> Node {
+        let right = self.expression(&expr.right);
+        Node::new("Unary", expr.id)
+            .token("operator", &expr.operator)
+            .node("right", right)
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> Node {
+        Node::new("Variable", expr.id)
+            .token("name", &expr.name)
+            .value("depth", self.depth(expr.id))
+    }
+}
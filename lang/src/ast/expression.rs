@@ -1,54 +1,567 @@
-use super::ast_printer::AstPrinter;
+// Generated by `0x6b73746b gen-ast src/ast` from `nodes.grammar`, nodes are changed there.
+use super::literal_value::LiteralValue;
+use super::node_id::NodeId;
 use crate::lexical_analysis::Token;
-pub trait Expr {
-    fn accept(&self, visitor: &AstPrinter) -> String;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Assign(Assign),
+    Binary(Binary),
+    Call(Call),
+    Get(Get),
+    Grouping(Grouping),
+    Index(Index),
+    IndexSet(IndexSet),
+    List(List),
+    Literal(Literal),
+    Logical(Logical),
+    Map(Map),
+    Set(Set),
+    Super(Super),
+    This(This),
+    Unary(Unary),
+    Variable(Variable),
+}
+
+impl Expr {
+    pub fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> R {
+        match self {
+            Expr::Assign(expr) => visitor.visit_assign_expr(expr),
+            Expr::Binary(expr) => visitor.visit_binary_expr(expr),
+            Expr::Call(expr) => visitor.visit_call_expr(expr),
+            Expr::Get(expr) => visitor.visit_get_expr(expr),
+            Expr::Grouping(expr) => visitor.visit_grouping_expr(expr),
+            Expr::Index(expr) => visitor.visit_index_expr(expr),
+            Expr::IndexSet(expr) => visitor.visit_index_set_expr(expr),
+            Expr::List(expr) => visitor.visit_list_expr(expr),
+            Expr::Literal(expr) => visitor.visit_literal_expr(expr),
+            Expr::Logical(expr) => visitor.visit_logical_expr(expr),
+            Expr::Map(expr) => visitor.visit_map_expr(expr),
+            Expr::Set(expr) => visitor.visit_set_expr(expr),
+            Expr::Super(expr) => visitor.visit_super_expr(expr),
+            Expr::This(expr) => visitor.visit_this_expr(expr),
+            Expr::Unary(expr) => visitor.visit_unary_expr(expr),
+            Expr::Variable(expr) => visitor.visit_variable_expr(expr),
+        }
+    }
+
+    // characters of the source covered by the tokens of the node, none if it has no tokens
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Expr::Assign(expr) => expr.span(),
+            Expr::Binary(expr) => expr.span(),
+            Expr::Call(expr) => expr.span(),
+            Expr::Get(expr) => expr.span(),
+            Expr::Grouping(expr) => expr.span(),
+            Expr::Index(expr) => expr.span(),
+            Expr::IndexSet(expr) => expr.span(),
+            Expr::List(expr) => expr.span(),
+            Expr::Literal(expr) => expr.span(),
+            Expr::Logical(expr) => expr.span(),
+            Expr::Map(expr) => expr.span(),
+            Expr::Set(expr) => expr.span(),
+            Expr::Super(expr) => expr.span(),
+            Expr::This(expr) => expr.span(),
+            Expr::Unary(expr) => expr.span(),
+            Expr::Variable(expr) => expr.span(),
+        }
+    }
+
+    // identity of the node, which side tables of the passes are keyed by
+    pub fn id(&self) -> NodeId {
+        match self {
+            Expr::Assign(expr) => expr.id,
+            Expr::Binary(expr) => expr.id,
+            Expr::Call(expr) => expr.id,
+            Expr::Get(expr) => expr.id,
+            Expr::Grouping(expr) => expr.id,
+            Expr::Index(expr) => expr.id,
+            Expr::IndexSet(expr) => expr.id,
+            Expr::List(expr) => expr.id,
+            Expr::Literal(expr) => expr.id,
+            Expr::Logical(expr) => expr.id,
+            Expr::Map(expr) => expr.id,
+            Expr::Set(expr) => expr.id,
+            Expr::Super(expr) => expr.id,
+            Expr::This(expr) => expr.id,
+            Expr::Unary(expr) => expr.id,
+            Expr::Variable(expr) => expr.id,
+        }
+    }
+}
+
+pub trait Visitor<R> {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> R;
+    fn visit_binary_expr(&mut self, expr: &Binary) -> R;
+    fn visit_call_expr(&mut self, expr: &Call) -> R;
+    fn visit_get_expr(&mut self, expr: &Get) -> R;
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> R;
+    fn visit_index_expr(&mut self, expr: &Index) -> R;
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> R;
+    fn visit_list_expr(&mut self, expr: &List) -> R;
+    fn visit_literal_expr(&mut self, expr: &Literal) -> R;
+    fn visit_logical_expr(&mut self, expr: &Logical) -> R;
+    fn visit_map_expr(&mut self, expr: &Map) -> R;
+    fn visit_set_expr(&mut self, expr: &Set) -> R;
+    fn visit_super_expr(&mut self, expr: &Super) -> R;
+    fn visit_this_expr(&mut self, expr: &This) -> R;
+    fn visit_unary_expr(&mut self, expr: &Unary) -> R;
+    fn visit_variable_expr(&mut self, expr: &Variable) -> R;
+}
+
+// Visits every node below the one it's given. Passes override the methods of the nodes they're
+// interested in, and call the function of the same name to go on with the children.
+pub trait Walker {
+    fn walk_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn walk_assign_expr(&mut self, expr: &Assign) {
+        walk_assign_expr(self, expr);
+    }
+
+    fn walk_binary_expr(&mut self, expr: &Binary) {
+        walk_binary_expr(self, expr);
+    }
+
+    fn walk_call_expr(&mut self, expr: &Call) {
+        walk_call_expr(self, expr);
+    }
+
+    fn walk_get_expr(&mut self, expr: &Get) {
+        walk_get_expr(self, expr);
+    }
+
+    fn walk_grouping_expr(&mut self, expr: &Grouping) {
+        walk_grouping_expr(self, expr);
+    }
+
+    fn walk_index_expr(&mut self, expr: &Index) {
+        walk_index_expr(self, expr);
+    }
+
+    fn walk_index_set_expr(&mut self, expr: &IndexSet) {
+        walk_index_set_expr(self, expr);
+    }
+
+    fn walk_list_expr(&mut self, expr: &List) {
+        walk_list_expr(self, expr);
+    }
+
+    fn walk_literal_expr(&mut self, expr: &Literal) {
+        walk_literal_expr(self, expr);
+    }
+
+    fn walk_logical_expr(&mut self, expr: &Logical) {
+        walk_logical_expr(self, expr);
+    }
+
+    fn walk_map_expr(&mut self, expr: &Map) {
+        walk_map_expr(self, expr);
+    }
+
+    fn walk_set_expr(&mut self, expr: &Set) {
+        walk_set_expr(self, expr);
+    }
+
+    fn walk_super_expr(&mut self, expr: &Super) {
+        walk_super_expr(self, expr);
+    }
+
+    fn walk_this_expr(&mut self, expr: &This) {
+        walk_this_expr(self, expr);
+    }
+
+    fn walk_unary_expr(&mut self, expr: &Unary) {
+        walk_unary_expr(self, expr);
+    }
+
+    fn walk_variable_expr(&mut self, expr: &Variable) {
+        walk_variable_expr(self, expr);
+    }
+}
+
+pub fn walk_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Expr) {
+    match expr {
+        Expr::Assign(expr) => walker.walk_assign_expr(expr),
+        Expr::Binary(expr) => walker.walk_binary_expr(expr),
+        Expr::Call(expr) => walker.walk_call_expr(expr),
+        Expr::Get(expr) => walker.walk_get_expr(expr),
+        Expr::Grouping(expr) => walker.walk_grouping_expr(expr),
+        Expr::Index(expr) => walker.walk_index_expr(expr),
+        Expr::IndexSet(expr) => walker.walk_index_set_expr(expr),
+        Expr::List(expr) => walker.walk_list_expr(expr),
+        Expr::Literal(expr) => walker.walk_literal_expr(expr),
+        Expr::Logical(expr) => walker.walk_logical_expr(expr),
+        Expr::Map(expr) => walker.walk_map_expr(expr),
+        Expr::Set(expr) => walker.walk_set_expr(expr),
+        Expr::Super(expr) => walker.walk_super_expr(expr),
+        Expr::This(expr) => walker.walk_this_expr(expr),
+        Expr::Unary(expr) => walker.walk_unary_expr(expr),
+        Expr::Variable(expr) => walker.walk_variable_expr(expr),
+    }
+}
+
+pub fn walk_assign_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Assign) {
+    walker.walk_expr(&expr.value);
+}
+
+pub fn walk_binary_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Binary) {
+    walker.walk_expr(&expr.left);
+    walker.walk_expr(&expr.right);
+}
+
+pub fn walk_call_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Call) {
+    walker.walk_expr(&expr.callee);
+    for item in expr.arguments.iter() {
+        walker.walk_expr(item);
+    }
+}
+
+pub fn walk_get_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Get) {
+    walker.walk_expr(&expr.object);
+}
+
+pub fn walk_grouping_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Grouping) {
+    walker.walk_expr(&expr.expression);
+}
+
+pub fn walk_index_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Index) {
+    walker.walk_expr(&expr.object);
+    walker.walk_expr(&expr.index);
+}
+
+pub fn walk_index_set_expr<W: Walker + ?Sized>(walker: &mut W, expr: &IndexSet) {
+    walker.walk_expr(&expr.object);
+    walker.walk_expr(&expr.index);
+    walker.walk_expr(&expr.value);
 }
 
-pub trait Visitor {
-    fn visit_binary_expr(&self, expr: &Binary) -> String;
-    fn visit_grouping_expr(&self, expr: &Grouping) -> String;
-    fn visit_literal_expr(&self, expr: &Literal) -> String;
-    fn visit_unary_expr(&self, expr: &Unary) -> String;
+pub fn walk_list_expr<W: Walker + ?Sized>(walker: &mut W, expr: &List) {
+    for item in expr.elements.iter() {
+        walker.walk_expr(item);
+    }
 }
+
+pub fn walk_literal_expr<W: Walker + ?Sized>(_walker: &mut W, _expr: &Literal) {}
+
+pub fn walk_logical_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Logical) {
+    walker.walk_expr(&expr.left);
+    walker.walk_expr(&expr.right);
+}
+
+pub fn walk_map_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Map) {
+    for item in expr.keys.iter() {
+        walker.walk_expr(item);
+    }
+    for item in expr.values.iter() {
+        walker.walk_expr(item);
+    }
+}
+
+pub fn walk_set_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Set) {
+    walker.walk_expr(&expr.object);
+    walker.walk_expr(&expr.value);
+}
+
+pub fn walk_super_expr<W: Walker + ?Sized>(_walker: &mut W, _expr: &Super) {}
+
+pub fn walk_this_expr<W: Walker + ?Sized>(_walker: &mut W, _expr: &This) {}
+
+pub fn walk_unary_expr<W: Walker + ?Sized>(walker: &mut W, expr: &Unary) {
+    walker.walk_expr(&expr.right);
+}
+
+pub fn walk_variable_expr<W: Walker + ?Sized>(_walker: &mut W, _expr: &Variable) {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assign {
+    pub id: NodeId,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+impl Assign {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.name.span()));
+        cover(&mut span, self.value.span());
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Binary {
-    pub left: Box<dyn Expr>,
+    pub id: NodeId,
+    pub left: Box<Expr>,
     pub operator: Token,
-    pub right: Box<dyn Expr>,
+    pub right: Box<Expr>,
+}
+
+impl Binary {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.left.span());
+        cover(&mut span, Some(self.operator.span()));
+        cover(&mut span, self.right.span());
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub id: NodeId,
+    pub callee: Box<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+impl Call {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.callee.span());
+        cover(&mut span, Some(self.paren.span()));
+        for item in self.arguments.iter() {
+            cover(&mut span, item.span());
+        }
+        span
+    }
 }
 
-impl Expr for Binary {
-    fn accept(&self, visitor: &AstPrinter) -> String {
-        return visitor.visit_binary_expr(&self).to_string();
+#[derive(Debug, Clone, PartialEq)]
+pub struct Get {
+    pub id: NodeId,
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
+impl Get {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.object.span());
+        cover(&mut span, Some(self.name.span()));
+        span
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Grouping {
-    pub expression: Box<dyn Expr>,
+    pub id: NodeId,
+    pub paren: Token,
+    pub expression: Box<Expr>,
+    pub closing_paren: Token,
+}
+
+impl Grouping {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.paren.span()));
+        cover(&mut span, self.expression.span());
+        cover(&mut span, Some(self.closing_paren.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub id: NodeId,
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
 }
 
-impl Expr for Grouping {
-    fn accept(&self, visitor: &AstPrinter) -> String {
-        return visitor.visit_grouping_expr(&self).to_string();
+impl Index {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.object.span());
+        cover(&mut span, Some(self.bracket.span()));
+        cover(&mut span, self.index.span());
+        span
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSet {
+    pub id: NodeId,
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+impl IndexSet {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.object.span());
+        cover(&mut span, Some(self.bracket.span()));
+        cover(&mut span, self.index.span());
+        cover(&mut span, self.value.span());
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct List {
+    pub id: NodeId,
+    pub bracket: Token,
+    pub elements: Vec<Expr>,
+    pub closing_bracket: Token,
+}
+
+impl List {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.bracket.span()));
+        for item in self.elements.iter() {
+            cover(&mut span, item.span());
+        }
+        cover(&mut span, Some(self.closing_bracket.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Literal {
-    pub value: String,
+    pub id: NodeId,
+    pub token: Token,
+    pub value: LiteralValue,
+}
+
+impl Literal {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.token.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Logical {
+    pub id: NodeId,
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+impl Logical {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.left.span());
+        cover(&mut span, Some(self.operator.span()));
+        cover(&mut span, self.right.span());
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Map {
+    pub id: NodeId,
+    pub brace: Token,
+    pub keys: Vec<Expr>,
+    pub values: Vec<Expr>,
+    pub closing_brace: Token,
 }
 
-impl Expr for Literal {
-    fn accept(&self, visitor: &AstPrinter) -> String {
-        return visitor.visit_literal_expr(&self).to_string();
+impl Map {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.brace.span()));
+        for item in self.keys.iter() {
+            cover(&mut span, item.span());
+        }
+        for item in self.values.iter() {
+            cover(&mut span, item.span());
+        }
+        cover(&mut span, Some(self.closing_brace.span()));
+        span
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Set {
+    pub id: NodeId,
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+impl Set {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.object.span());
+        cover(&mut span, Some(self.name.span()));
+        cover(&mut span, self.value.span());
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Super {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub method: Token,
+}
+
+impl Super {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, Some(self.method.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct This {
+    pub id: NodeId,
+    pub keyword: Token,
+}
+
+impl This {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Unary {
+    pub id: NodeId,
     pub operator: Token,
-    pub right: Box<dyn Expr>,
+    pub right: Box<Expr>,
+}
+
+impl Unary {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.operator.span()));
+        cover(&mut span, self.right.span());
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub id: NodeId,
+    pub name: Token,
+}
+
+impl Variable {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.name.span()));
+        span
+    }
 }
 
-impl Expr for Unary {
-    fn accept(&self, visitor: &AstPrinter) -> String {
-        return visitor.visit_unary_expr(&self).to_string();
+// widens a span to cover the range
+fn cover(span: &mut Option<Range<usize>>, range: Option<Range<usize>>) {
+    if let Some(range) = range {
+        *span = Some(match span.take() {
+            Some(span) => span.start.min(range.start)..span.end.max(range.end),
+            None => range,
+        });
     }
 }
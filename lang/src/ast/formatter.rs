@@ -0,0 +1,786 @@
+use std::ops::Range;
+
+use crate::lexical_analysis::{Comment, Lexer, Token, TokenType};
+
+use super::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map,
+    Set, Super, This, Unary, Variable,
+};
+use super::literal_value::LiteralValue;
+use super::parser::{Parser, ITERATOR};
+use super::statement::{
+    self, Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt,
+    Throw, Try, Var, While,
+};
+
+const INDENT: &str = "  ";
+const MAX_WIDTH: usize = 80;
+
+// lexes and parses a script and prints it in the canonical layout, errors are reported as they're
+// found; formatting a formatted script doesn't change it
+pub fn format(source: &str) -> Option<String> {
+    let mut lexer = Lexer::new(source.to_string());
+    lexer.scan_tokens();
+
+    let mut parser = Parser::new(lexer.tokens.clone());
+    let statements = parser.parse();
+
+    if lexer.had_error || parser.had_error {
+        return None;
+    }
+
+    let mut formatter = Formatter::new(source, &lexer.tokens, &lexer.comments);
+    Some(formatter.format(&statements))
+}
+
+// loop which the parser desugared into a block
+enum Loop<'a> {
+    For(&'a Stmt, &'a While),
+    ForIn(&'a Token, &'a Expr, &'a Stmt, &'a While),
+}
+
+// Prints statements one per line, with blocks indented and opening braces on the line of their
+// statement. Comments and single blank lines between statements are kept; comments are placed
+// by the lines of the tokens around them, which the formatter follows through the source as it goes.
+struct Formatter<'a> {
+    lines: Vec<&'a str>,
+    tokens: &'a [Token],
+    comments: &'a [Comment],
+    next_comment: usize,
+    // number of tokens of the source which are formatted
+    cursor: usize,
+    output: String,
+    indent: usize,
+    // start of the line of the next statement, i.e. the header of a loop whose body isn't a block
+    prefix: String,
+    // column where the expression being formatted starts
+    column: usize,
+    width: usize,
+    // nothing has been written in the current block yet
+    block_start: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str, tokens: &'a [Token], comments: &'a [Comment]) -> Self {
+        Self {
+            lines: source.lines().collect(),
+            tokens,
+            comments,
+            next_comment: 0,
+            cursor: 0,
+            output: String::new(),
+            indent: 0,
+            prefix: String::new(),
+            column: 0,
+            width: MAX_WIDTH,
+            block_start: true,
+        }
+    }
+
+    fn format(&mut self, statements: &[Stmt]) -> String {
+        self.statements(statements);
+        self.comments_before(usize::MAX);
+
+        std::mem::take(&mut self.output)
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.leading();
+            statement.accept(self);
+        }
+    }
+
+    // comments and a blank line above the statement which starts at the next token
+    fn leading(&mut self) {
+        let line = self
+            .tokens
+            .get(self.cursor)
+            .map_or(usize::MAX, |token| token.line);
+        self.comments_before(line);
+        self.blank_line(line);
+    }
+
+    fn comments_before(&mut self, line: usize) {
+        while let Some(comment) = self
+            .comments
+            .get(self.next_comment)
+            .filter(|comment| comment.line < line)
+        {
+            self.blank_line(comment.line);
+            self.output += &INDENT.repeat(self.indent);
+            self.output += &comment.text;
+            self.output.push('\n');
+            self.next_comment += 1;
+            self.block_start = false;
+        }
+    }
+
+    // keeps one blank line of the source above the line
+    fn blank_line(&mut self, line: usize) {
+        let blank = line >= 2
+            && self
+                .lines
+                .get(line - 2)
+                .is_some_and(|text| text.trim().is_empty());
+
+        if blank && !self.block_start {
+            self.output.push('\n');
+        }
+    }
+
+    // comments following the code formatted so far go at the end of the line
+    fn write_line(&mut self, text: &str) {
+        self.output += &INDENT.repeat(self.indent);
+        self.output += text;
+
+        while let Some(comment) = self
+            .comments
+            .get(self.next_comment)
+            .filter(|comment| comment.trailing && self.reached(comment))
+        {
+            self.output.push(' ');
+            self.output += &comment.text;
+            self.next_comment += 1;
+        }
+
+        self.output.push('\n');
+        self.block_start = false;
+    }
+
+    // every token before the comment is formatted
+    fn reached(&self, comment: &Comment) -> bool {
+        let preceding = self
+            .tokens
+            .partition_point(|token| token.offset < comment.offset);
+        self.cursor >= preceding
+    }
+
+    fn touch(&mut self, token: &Token) {
+        let index = self
+            .tokens
+            .partition_point(|other| other.offset <= token.offset);
+        self.cursor = self.cursor.max(index);
+    }
+
+    // moves past the next token of the type, i.e. the semicolon ending a statement
+    fn skip_to(&mut self, token_type: TokenType) -> Option<usize> {
+        let index = self.cursor
+            + self.tokens[self.cursor..]
+                .iter()
+                .position(|token| token.token_type == token_type)?;
+        self.cursor = index + 1;
+        Some(index)
+    }
+
+    // closing brace, bracket or parenthesis of the token at `open`
+    fn matching(&self, open: usize) -> usize {
+        let (left, right) = match self.tokens.get(open).map(|token| token.token_type) {
+            Some(TokenType::LeftParen) => (TokenType::LeftParen, TokenType::RightParen),
+            Some(TokenType::LeftBracket) => (TokenType::LeftBracket, TokenType::RightBracket),
+            _ => (TokenType::LeftBrace, TokenType::RightBrace),
+        };
+
+        let mut depth = 0;
+        for (index, token) in self.tokens.iter().enumerate().skip(open) {
+            match token.token_type {
+                token_type if token_type == left => depth += 1,
+                token_type if token_type == right && depth == 1 => return index,
+                token_type if token_type == right => depth -= 1,
+                _ => {}
+            }
+        }
+        self.tokens.len() - 1
+    }
+
+    // offset of the token closing the one which starts at `offset`
+    fn closing_offset(&self, offset: usize) -> usize {
+        let open = self.tokens.partition_point(|token| token.offset < offset);
+        self.tokens
+            .get(self.matching(open))
+            .map_or(offset, |token| token.offset)
+    }
+
+    // the next comment is within the characters
+    fn commented(&self, span: &Range<usize>) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| span.contains(&comment.offset))
+    }
+
+    // comments before the offset on lines of their own, or at the end of the last line if they
+    // follow code
+    fn comments_within(&mut self, lines: &mut Vec<String>, offset: usize) {
+        while let Some(comment) = self
+            .comments
+            .get(self.next_comment)
+            .filter(|comment| comment.offset < offset)
+        {
+            match lines.last_mut() {
+                Some(line) if comment.trailing => *line += &format!(" {}", comment.text),
+                _ => lines.push(INDENT.repeat(self.indent) + &comment.text),
+            }
+            self.next_comment += 1;
+        }
+    }
+
+    // `else` following the then branch formatted last
+    fn else_keyword(&self) -> Option<&'a Token> {
+        let tokens: &'a [Token] = self.tokens;
+        tokens[self.cursor.min(tokens.len())..]
+            .iter()
+            .find(|token| token.token_type == TokenType::Else)
+    }
+
+    fn take_prefix(&mut self) -> String {
+        std::mem::take(&mut self.prefix)
+    }
+
+    // column after the text written at the start of a line
+    fn column(&self, text: &str) -> usize {
+        self.indent * INDENT.len() + text.chars().count()
+    }
+
+    fn end_statement(&mut self, text: String) {
+        self.skip_to(TokenType::Semicolon);
+        self.write_line(&(text + ";"));
+    }
+
+    // writes the header with the opening brace and the statements, returns the closing brace for the
+    // caller to continue the line, like `} else {`
+    fn block(&mut self, header: String, statements: &[Stmt]) -> String {
+        self.braces(header, statements.is_empty(), |formatter| {
+            formatter.statements(statements)
+        })
+    }
+
+    fn braces(&mut self, header: String, empty: bool, body: impl FnOnce(&mut Self)) -> String {
+        let open = self.skip_to(TokenType::LeftBrace);
+        let close = open.map_or(self.cursor, |open| self.matching(open));
+        let (close_line, close_offset) = self
+            .tokens
+            .get(close)
+            .map_or((0, 0), |token| (token.line, token.offset));
+
+        let header = match header.is_empty() {
+            true => "{".to_string(),
+            false => header + " {",
+        };
+        let commented = self
+            .comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.offset < close_offset);
+
+        if empty && !commented {
+            self.cursor = self.cursor.max(close + 1);
+            return header + "}";
+        }
+
+        self.write_line(&header);
+        self.indent += 1;
+        self.block_start = true;
+        body(self);
+        self.comments_before(close_line);
+        self.indent -= 1;
+        self.cursor = self.cursor.max(close + 1);
+
+        "}".to_string()
+    }
+
+    // body of a loop or an if statement, a statement which isn't a block goes on the line of the header
+    fn body(&mut self, header: String, body: &Stmt) {
+        self.prefix = header + " ";
+        body.accept(self);
+    }
+
+    fn function(&mut self, header: String, function: &Function) -> String {
+        self.touch(&function.name);
+        for param in &function.params {
+            self.touch(param);
+        }
+
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|param| param.lexeme.to_string())
+            .collect();
+        let header = format!("{header}{}({})", function.name.lexeme, params.join(", "));
+        self.block(header, &function.body)
+    }
+
+    fn for_loop(&mut self, prefix: String, initializer: Option<&Stmt>, stmt: &While) {
+        self.touch(&stmt.keyword);
+
+        let mut header = format!("{prefix}for (");
+        match initializer {
+            Some(Stmt::Var(var)) => {
+                let declaration = self.variable(&header, var);
+                header += &declaration;
+            }
+            Some(Stmt::Expression(initializer)) => {
+                header += &self.expression(&initializer.expression, self.column(&header))
+            }
+            _ => {}
+        }
+        header.push(';');
+
+        if !matches!(
+            &stmt.condition,
+            Expr::Literal(Literal {
+                value: LiteralValue::Bool(true),
+                ..
+            })
+        ) {
+            header.push(' ');
+            header += &self.expression(&stmt.condition, self.column(&header));
+        }
+        header.push(';');
+
+        if let Some(increment) = &stmt.increment {
+            header.push(' ');
+            header += &self.expression(increment, self.column(&header));
+        }
+        header.push(')');
+
+        self.body(header, &stmt.body);
+    }
+
+    fn for_in_loop(&mut self, prefix: String, name: &Token, iterable: &Expr, body: &Stmt) {
+        let header = format!("{prefix}for ({} in ", name.lexeme);
+        self.touch(name);
+
+        let iterable = self.expression(iterable, self.column(&header));
+        self.body(format!("{header}{iterable})"), body);
+    }
+
+    // `var name = initializer` written after the text
+    fn variable(&mut self, text: &str, stmt: &Var) -> String {
+        self.touch(&stmt.name);
+
+        let declaration = format!("var {}", stmt.name.lexeme);
+        match &stmt.initializer {
+            Some(initializer) => {
+                let column = self.column(text) + declaration.len() + 3;
+                format!("{declaration} = {}", self.expression(initializer, column))
+            }
+            None => declaration,
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr, column: usize) -> String {
+        let column = std::mem::replace(&mut self.column, column);
+        let text = expr.accept(self);
+        self.column = column;
+        text
+    }
+
+    fn fits(&self, text: &str) -> bool {
+        !text.contains('\n') && self.column + text.chars().count() <= self.width
+    }
+
+    // arguments of a call or elements of a collection, one per line if they don't fit on the line or
+    // comments are among them; `span` runs from the opening to the closing token and `starts` are
+    // the offsets of the items
+    fn delimited(
+        &mut self,
+        open: String,
+        span: Range<usize>,
+        starts: Vec<usize>,
+        close: &str,
+        item: impl Fn(&mut Self, usize, usize) -> String,
+    ) -> String {
+        let count = starts.len();
+        if !self.commented(&span) {
+            // comments of nested lists are taken again if the items go on lines of their own
+            let next_comment = self.next_comment;
+            let width = std::mem::replace(&mut self.width, usize::MAX);
+            let items: Vec<String> = (0..count).map(|index| item(self, index, 0)).collect();
+            self.width = width;
+
+            let text = format!("{open}{}{close}", items.join(", "));
+            if count == 0 || self.fits(&text) {
+                return text;
+            }
+            self.next_comment = next_comment;
+        }
+
+        self.indent += 1;
+        let indent = INDENT.repeat(self.indent);
+        let mut lines = vec![open];
+        for (index, start) in starts.into_iter().enumerate() {
+            self.comments_within(&mut lines, start);
+            let text = item(self, index, indent.len());
+            let comma = if index + 1 < count { "," } else { "" };
+            lines.push(format!("{indent}{text}{comma}"));
+        }
+        self.comments_within(&mut lines, span.end);
+        self.indent -= 1;
+
+        lines.push(INDENT.repeat(self.indent) + close);
+        lines.join("\n")
+    }
+
+    fn binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        let left = self.expression(left, self.column);
+        self.touch(operator);
+
+        let column = end_column(self.column, &left) + operator.lexeme.len() + 2;
+        let right = self.expression(right, column);
+        format!("{left} {} {right}", operator.lexeme)
+    }
+
+    // `target = value` where the target starts at the column of the expression
+    fn assignment(&mut self, target: String, value: &Expr) -> String {
+        let column = end_column(self.column, &target) + 3;
+        let value = self.expression(value, column);
+        format!("{target} = {value}")
+    }
+}
+
+// column after the text when it starts at the column
+fn end_column(column: usize, text: &str) -> usize {
+    match text.rfind('\n') {
+        Some(newline) => text[newline + 1..].chars().count(),
+        None => column + text.chars().count(),
+    }
+}
+
+// offsets where the expressions start, or `end` for those without tokens
+fn starts(exprs: &[Expr], end: usize) -> Vec<usize> {
+    exprs
+        .iter()
+        .map(|expr| expr.span().map_or(end, |span| span.start))
+        .collect()
+}
+
+fn desugared_loop(block: &Block) -> Option<Loop<'_>> {
+    match block.statements.as_slice() {
+        [Stmt::Var(iterator), Stmt::While(stmt)] if &*iterator.name.lexeme == ITERATOR => {
+            let Some(Expr::Call(Call { callee, .. })) = &iterator.initializer else {
+                return None;
+            };
+            let Expr::Get(Get {
+                object: iterable, ..
+            }) = callee.as_ref()
+            else {
+                return None;
+            };
+            let Stmt::Block(Block { statements, .. }) = stmt.body.as_ref() else {
+                return None;
+            };
+            let [Stmt::Var(Var { name, .. }), body] = statements.as_slice() else {
+                return None;
+            };
+
+            Some(Loop::ForIn(name, iterable, body, stmt))
+        }
+        [initializer @ (Stmt::Var(_) | Stmt::Expression(_)), Stmt::While(stmt)]
+            if stmt.keyword.token_type == TokenType::For =>
+        {
+            Some(Loop::For(initializer, stmt))
+        }
+        _ => None,
+    }
+}
+
+impl statement::Visitor<()> for Formatter<'_> {
+    fn visit_block_stmt(&mut self, stmt: &Block) {
+        let prefix = self.take_prefix();
+
+        match desugared_loop(stmt) {
+            Some(Loop::For(initializer, stmt)) => self.for_loop(prefix, Some(initializer), stmt),
+            Some(Loop::ForIn(name, iterable, body, stmt)) => {
+                self.touch(&stmt.keyword);
+                self.for_in_loop(prefix, name, iterable, body)
+            }
+            None => {
+                let close = self.block(prefix.trim_end().to_string(), &stmt.statements);
+                self.write_line(&close);
+            }
+        }
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &Break) {
+        let prefix = self.take_prefix();
+        self.touch(&stmt.keyword);
+        self.end_statement(prefix + "break");
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Class) {
+        let mut header = format!("{}class {}", self.take_prefix(), stmt.name.lexeme);
+        self.touch(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            header += &format!(" < {}", superclass.name.lexeme);
+            self.touch(&superclass.name);
+        }
+
+        let close = self.braces(header, stmt.methods.is_empty(), |formatter| {
+            for method in &stmt.methods {
+                formatter.leading();
+                let close = formatter.function(String::new(), method);
+                formatter.write_line(&close);
+            }
+        });
+        self.write_line(&close);
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &Continue) {
+        let prefix = self.take_prefix();
+        self.touch(&stmt.keyword);
+        self.end_statement(prefix + "continue");
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) {
+        let prefix = self.take_prefix();
+        let expression = self.expression(&stmt.expression, self.column(&prefix));
+        self.end_statement(prefix + &expression);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Function) {
+        let header = self.take_prefix() + "fun ";
+        let close = self.function(header, stmt);
+        self.write_line(&close);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) {
+        let header = self.take_prefix() + "if (";
+        let condition = self.expression(&stmt.condition, self.column(&header));
+        let header = format!("{header}{condition})");
+
+        let Some(else_branch) = &stmt.else_branch else {
+            return self.body(header, &stmt.then_branch);
+        };
+
+        match stmt.then_branch.as_ref() {
+            Stmt::Block(block) if desugared_loop(block).is_none() => {
+                let close = self.block(header, &block.statements);
+                match self.else_keyword() {
+                    // the closing brace ends its line so the comments stay above the `else`
+                    Some(keyword) if self.commented(&(0..keyword.offset)) => {
+                        self.write_line(&close);
+                        self.comments_before(keyword.line);
+                        self.prefix = "else ".to_string();
+                    }
+                    _ => self.prefix = close + " else ",
+                }
+            }
+            then_branch => {
+                self.body(header, then_branch);
+                if let Some(keyword) = self.else_keyword() {
+                    self.comments_before(keyword.line);
+                }
+                self.prefix = "else ".to_string();
+            }
+        }
+        else_branch.accept(self);
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) {
+        let prefix = self.take_prefix();
+        self.touch(&stmt.keyword);
+        self.touch(&stmt.path);
+
+        let text = match &stmt.name {
+            Some(name) => format!("{prefix}import {} as {}", stmt.path.lexeme, name.lexeme),
+            None => {
+                let names: Vec<String> = stmt
+                    .names
+                    .iter()
+                    .map(|name| name.lexeme.to_string())
+                    .collect();
+                format!(
+                    "{prefix}from {} import {}",
+                    stmt.path.lexeme,
+                    names.join(", ")
+                )
+            }
+        };
+        self.end_statement(text);
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) {
+        let text = self.take_prefix() + "print ";
+        self.touch(&stmt.keyword);
+
+        let expression = self.expression(&stmt.expression, self.column(&text));
+        self.end_statement(text + &expression);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) {
+        let text = self.take_prefix() + "return";
+        self.touch(&stmt.keyword);
+
+        match &stmt.value {
+            Some(value) => {
+                let value = self.expression(value, self.column(&text) + 1);
+                self.end_statement(format!("{text} {value}"));
+            }
+            None => self.end_statement(text),
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) {
+        let text = self.take_prefix() + "throw ";
+        self.touch(&stmt.keyword);
+
+        let value = self.expression(&stmt.value, self.column(&text));
+        self.end_statement(text + &value);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) {
+        let header = self.take_prefix() + "try";
+        self.touch(&stmt.keyword);
+
+        let mut close = self.block(header, &stmt.body);
+        if let Some(name) = &stmt.catch_name {
+            self.touch(name);
+            close = self.block(format!("{close} catch ({})", name.lexeme), &stmt.catch_body);
+        }
+        if let Some(finally_body) = &stmt.finally_body {
+            close = self.block(format!("{close} finally"), finally_body);
+        }
+        self.write_line(&close);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        let prefix = self.take_prefix();
+        let declaration = self.variable(&prefix, stmt);
+        self.end_statement(prefix + &declaration);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) {
+        let prefix = self.take_prefix();
+
+        if stmt.keyword.token_type == TokenType::For {
+            return self.for_loop(prefix, None, stmt);
+        }
+
+        let header = prefix + "while (";
+        self.touch(&stmt.keyword);
+
+        let condition = self.expression(&stmt.condition, self.column(&header));
+        self.body(format!("{header}{condition})"), &stmt.body);
+    }
+}
+
+impl expression::Visitor<String> for Formatter<'_> {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        self.touch(&expr.name);
+        self.assignment(expr.name.lexeme.to_string(), &expr.value)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> String {
+        self.binary(&expr.left, &expr.operator, &expr.right)
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let callee = self.expression(&expr.callee, self.column);
+        let open = expr
+            .callee
+            .span()
+            .map_or(expr.paren.offset, |span| span.end);
+        let call = self.delimited(
+            callee + "(",
+            open..expr.paren.offset,
+            starts(&expr.arguments, expr.paren.offset),
+            ")",
+            |formatter, index, column| formatter.expression(&expr.arguments[index], column),
+        );
+        self.touch(&expr.paren);
+        call
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) -> String {
+        let object = self.expression(&expr.object, self.column);
+        self.touch(&expr.name);
+        format!("{object}.{}", expr.name.lexeme)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> String {
+        let expression = self.expression(&expr.expression, self.column + 1);
+        format!("({expression})")
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) -> String {
+        let object = self.expression(&expr.object, self.column);
+        self.touch(&expr.bracket);
+
+        let index = self.expression(&expr.index, end_column(self.column, &object) + 1);
+        format!("{object}[{index}]")
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> String {
+        let object = self.expression(&expr.object, self.column);
+        self.touch(&expr.bracket);
+
+        let index = self.expression(&expr.index, end_column(self.column, &object) + 1);
+        self.assignment(format!("{object}[{index}]"), &expr.value)
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> String {
+        self.touch(&expr.bracket);
+        let close = self.closing_offset(expr.bracket.offset);
+        self.delimited(
+            "[".to_string(),
+            expr.bracket.offset..close,
+            starts(&expr.elements, close),
+            "]",
+            |formatter, index, column| formatter.expression(&expr.elements[index], column),
+        )
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        match &expr.value {
+            LiteralValue::String(value) => format!("\"{value}\""),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Logical) -> String {
+        self.binary(&expr.left, &expr.operator, &expr.right)
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) -> String {
+        self.touch(&expr.brace);
+        let close = self.closing_offset(expr.brace.offset);
+        self.delimited(
+            "{".to_string(),
+            expr.brace.offset..close,
+            starts(&expr.keys, close),
+            "}",
+            |formatter, index, column| {
+                let key = formatter.expression(&expr.keys[index], column);
+                let value = formatter.expression(&expr.values[index], end_column(column, &key) + 2);
+                format!("{key}: {value}")
+            },
+        )
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) -> String {
+        let object = self.expression(&expr.object, self.column);
+        self.touch(&expr.name);
+        self.assignment(format!("{object}.{}", expr.name.lexeme), &expr.value)
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) -> String {
+        self.touch(&expr.method);
+        format!("super.{}", expr.method.lexeme)
+    }
+
+    fn visit_this_expr(&mut self, expr: &This) -> String {
+        self.touch(&expr.keyword);
+        "this".to_string()
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> String {
+        self.touch(&expr.operator);
+        let right = self.expression(&expr.right, self.column + expr.operator.lexeme.len());
+        format!("{}{right}", expr.operator.lexeme)
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        self.touch(&expr.name);
+        expr.name.lexeme.to_string()
+    }
+}
@@ -1,74 +1,560 @@
-use std::io::Write;
-use std::{
-    fs::{DirBuilder, File},
-    path::Path,
-};
-
-pub fn generate_ast(output_directory: &str) {
-    define_ast(
-        output_directory,
-        "expression.rs",
-        Vec::from([
-            "Binary = left: Box<dyn Expr>, operator: Token, right: Box<dyn Expr>",
-            "Grouping = expression: Box<dyn Expr>",
-            "Literal = value: String", // originally it was Java Object, it needs changes in the future; see lexical_analysis -> struct Token.literal comments
-            "Unary = operator: Token, right: Box<dyn Expr>",
-        ]),
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// definitions of the nodes, checked in next to the modules generated from them
+const GRAMMAR: &str = include_str!("nodes.grammar");
+const GRAMMAR_FILE: &str = "nodes.grammar";
+
+// generated source file of the AST
+pub struct Module {
+    pub file_name: String,
+    pub source: String,
+}
+
+struct Section {
+    base: String,
+    file_name: String,
+    imports: Vec<String>,
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    name: String,
+    field_type: String,
+    // options and lists around the type, outermost first; boxes and `Rc`s are seen through
+    wrappers: Vec<Wrapper>,
+    // i.e. `Expr` of `Option<Box<Expr>>`
+    inner_type: String,
+}
+
+#[derive(Clone, Copy)]
+enum Wrapper {
+    Option,
+    Vec,
+}
+
+// modules generated from the grammar, or the first mistake in its definitions
+pub fn generate_modules() -> Result<Vec<Module>, String> {
+    let sections = parse_grammar(GRAMMAR)?;
+
+    Ok(sections
+        .iter()
+        .map(|section| Module {
+            file_name: section.file_name.clone(),
+            source: define_ast(section, &sections),
+        })
+        .collect())
+}
+
+pub fn write_modules(output_directory: &str, modules: &[Module]) -> io::Result<()> {
+    let output_directory = Path::new(output_directory);
+    fs::create_dir_all(output_directory)?;
+
+    for module in modules {
+        fs::write(output_directory.join(&module.file_name), &module.source)?;
+    }
+
+    Ok(())
+}
+
+// Modules whose file in the directory differs from what the grammar generates, or is missing, so
+// that the checked-in AST can't drift from its definitions.
+pub fn outdated_modules<'a>(output_directory: &str, modules: &'a [Module]) -> Vec<&'a Module> {
+    let output_directory = Path::new(output_directory);
+
+    modules
+        .iter()
+        .filter(|module| {
+            fs::read_to_string(output_directory.join(&module.file_name))
+                .map_or(true, |source| source != module.source)
+        })
+        .collect()
+}
+
+fn parse_grammar(grammar: &str) -> Result<Vec<Section>, String> {
+    let mut sections: Vec<Section> = Vec::new();
+    // lines which the enums and nodes are defined on
+    let mut definitions = HashMap::new();
+
+    for (index, line) in grammar.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| format!("{GRAMMAR_FILE}:{line_number}: {message}");
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let name = if let Some(import) = line.strip_prefix("use ") {
+            let section = sections
+                .last_mut()
+                .ok_or_else(|| error("`use` comes before the first enum".to_string()))?;
+            section.imports.push(import.trim().to_string());
+            continue;
+        } else if let Some((name, fields)) = line.split_once('=') {
+            let node = parse_node(name.trim(), fields.trim()).map_err(error)?;
+            let section = sections.last_mut().ok_or_else(|| {
+                error(format!("node `{}` comes before the first enum", node.name))
+            })?;
+            section.nodes.push(node);
+            name.trim()
+        } else if let Some((base, file_name)) = line.split_once(" in ") {
+            let (base, file_name) = (base.trim(), file_name.trim());
+            if !is_type_name(base) {
+                return Err(error(format!("`{base}` isn't a valid enum name")));
+            }
+            match file_name.strip_suffix(".rs") {
+                Some(module) if is_field_name(module) => {}
+                _ => return Err(error(format!("`{file_name}` isn't a valid module file"))),
+            }
+            if let Some(section) = sections
+                .iter()
+                .find(|section| section.file_name == file_name)
+            {
+                let message = format!("`{file_name}` is already generated for `{}`", section.base);
+                return Err(error(message));
+            }
+
+            sections.push(Section {
+                base: base.to_string(),
+                file_name: file_name.to_string(),
+                imports: Vec::new(),
+                nodes: Vec::new(),
+            });
+            base
+        } else {
+            return Err(error(format!(
+                "expected `Enum in file.rs`, `use path` or `Node = field: Type, ...`, found `{line}`"
+            )));
+        };
+
+        if let Some(previous) = definitions.insert(name.to_string(), line_number) {
+            return Err(error(format!(
+                "`{name}` is already defined on line {previous}"
+            )));
+        }
+    }
+
+    if sections.is_empty() {
+        return Err(format!("{GRAMMAR_FILE}: no enums are defined"));
+    }
+    if let Some(section) = sections.iter().find(|section| section.nodes.is_empty()) {
+        return Err(format!("{GRAMMAR_FILE}: `{}` has no nodes", section.base));
+    }
+
+    Ok(sections)
+}
+
+fn parse_node(name: &str, fields: &str) -> Result<Node, String> {
+    if !is_type_name(name) {
+        return Err(format!("`{name}` isn't a valid node name"));
+    }
+    if fields.is_empty() {
+        return Err(format!("`{name}` has no fields"));
+    }
+
+    let mut node = Node {
+        name: name.to_string(),
+        fields: Vec::new(),
+    };
+
+    for field in split_fields(fields).ok_or_else(|| format!("unbalanced brackets in `{name}`"))? {
+        let (field_name, field_type) = field
+            .split_once(':')
+            .map(|(field_name, field_type)| (field_name.trim(), field_type.trim()))
+            .ok_or_else(|| format!("field `{}` of `{name}` has no type", field.trim()))?;
+
+        if !is_field_name(field_name) {
+            return Err(format!(
+                "`{field_name}` isn't a valid field name of `{name}`"
+            ));
+        }
+        if field_type.is_empty() {
+            return Err(format!("field `{field_name}` of `{name}` has no type"));
+        }
+        if node.fields.iter().any(|field| field.name == field_name) {
+            return Err(format!("`{name}` has two fields named `{field_name}`"));
+        }
+
+        let (wrappers, inner_type) = parse_type(field_type);
+        node.fields.push(Field {
+            name: field_name.to_string(),
+            field_type: field_type.to_string(),
+            wrappers,
+            inner_type: inner_type.to_string(),
+        });
+    }
+
+    Ok(node)
+}
+
+fn parse_type(field_type: &str) -> (Vec<Wrapper>, &str) {
+    let mut wrappers = Vec::new();
+    let mut inner_type = field_type;
+
+    loop {
+        let generic = ["Box", "Rc", "Option", "Vec"].into_iter().find_map(|name| {
+            let argument = inner_type.strip_prefix(name)?.strip_prefix('<')?;
+            Some((name, argument.strip_suffix('>')?.trim()))
+        });
+
+        match generic {
+            Some((name, argument)) => {
+                match name {
+                    "Option" => wrappers.push(Wrapper::Option),
+                    "Vec" => wrappers.push(Wrapper::Vec),
+                    _ => {}
+                }
+                inner_type = argument;
+            }
+            None => return (wrappers, inner_type),
+        }
+    }
+}
+
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|character: char| character.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric())
+}
+
+fn is_field_name(name: &str) -> bool {
+    name.starts_with(|character: char| character.is_ascii_lowercase() || character == '_')
+        && name.chars().all(|character| {
+            character.is_ascii_lowercase() || character.is_ascii_digit() || character == '_'
+        })
+}
+
+fn define_ast(section: &Section, sections: &[Section]) -> String {
+    let base_name = &section.base;
+    let base_name_lower_case = base_name.to_lowercase();
+
+    let mut output = format!(
+        "// Generated by `0x6b73746b gen-ast src/ast` from `{GRAMMAR_FILE}`, nodes are changed there.\n"
     );
+    // in the order rustfmt keeps them, paths of the crate first
+    let mut imports: Vec<&str> = section.imports.iter().map(String::as_str).collect();
+    imports.push("std::ops::Range");
+    imports.sort_by_key(|import| {
+        let external = !["self::", "super::", "crate::"]
+            .iter()
+            .any(|prefix| import.starts_with(prefix));
+        (external, external.then_some(*import))
+    });
+    for import in imports {
+        output += &format!("use {import};\n");
+    }
+
+    output += &format!("\n#[derive(Debug, Clone, PartialEq)]\npub enum {base_name} {{\n");
+    for node in &section.nodes {
+        output += &format!("    {0}({0}),\n", node.name);
+    }
+    output += "}\n\n";
+
+    output += &format!("impl {base_name} {{\n");
+    output +=
+        "    pub fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> R {\n        match self {\n";
+    for node in &section.nodes {
+        let name_lower_case = snake_case(&node.name);
+        output += &format!(
+            "            {base_name}::{}({base_name_lower_case}) => visitor.visit_{name_lower_case}_{base_name_lower_case}({base_name_lower_case}),\n",
+            node.name
+        );
+    }
+    output += "        }\n    }\n\n";
+    output += "    // characters of the source covered by the tokens of the node, none if it has no tokens\n";
+    output += "    pub fn span(&self) -> Option<Range<usize>> {\n        match self {\n";
+    for node in &section.nodes {
+        output += &format!(
+            "            {base_name}::{}({base_name_lower_case}) => {base_name_lower_case}.span(),\n",
+            node.name
+        );
+    }
+    output += "        }\n    }\n";
+    if let Some(id_type) = id_type(section) {
+        output += "\n    // identity of the node, which side tables of the passes are keyed by\n";
+        output += &format!("    pub fn id(&self) -> {id_type} {{\n        match self {{\n");
+        for node in &section.nodes {
+            output += &format!(
+                "            {base_name}::{}({base_name_lower_case}) => {base_name_lower_case}.id,\n",
+                node.name
+            );
+        }
+        output += "        }\n    }\n";
+    }
+    output += "}\n\n";
+
+    output += "pub trait Visitor<R> {\n";
+    for node in &section.nodes {
+        let name_lower_case = snake_case(&node.name);
+        output += &format!(
+            "    fn visit_{name_lower_case}_{base_name_lower_case}(&mut self, {base_name_lower_case}: &{}) -> R;\n",
+            node.name
+        );
+    }
+    output += "}\n";
+
+    output += &define_walker(section, sections);
+
+    for node in &section.nodes {
+        output += &define_type(node, sections);
+    }
+
+    output += "\n// widens a span to cover the range\n";
+    output += "fn cover(span: &mut Option<Range<usize>>, range: Option<Range<usize>>) {\n";
+    output += "    if let Some(range) = range {\n";
+    output += "        *span = Some(match span.take() {\n";
+    output += "            Some(span) => span.start.min(range.start)..span.end.max(range.end),\n";
+    output += "            None => range,\n";
+    output += "        });\n";
+    output += "    }\n";
+    output += "}\n";
+
+    output
 }
 
-fn define_ast(output_directory: &str, file_name: &str, types: Vec<&str>) {
-    let output_directory_path = Path::new(output_directory);
+// Trait whose methods visit every node below the one they're given, and functions which do the
+// walking, so that a pass overriding the method of a node can still walk its children.
+fn define_walker(section: &Section, sections: &[Section]) -> String {
+    let base_name = &section.base;
+    let base_name_lower_case = base_name.to_lowercase();
 
-    if !output_directory_path.exists() {
-        DirBuilder::new()
-            .recursive(true)
-            .create(output_directory)
-            .unwrap();
+    // walkers of the other enums whose nodes are fields of this one
+    let mut supertraits: Vec<String> = Vec::new();
+    for node in &section.nodes {
+        for field in &node.fields {
+            if let Some(other) = section_of(&field.inner_type, sections) {
+                let supertrait =
+                    format!("super::{}::Walker", other.file_name.trim_end_matches(".rs"));
+                if other.base != section.base && !supertraits.contains(&supertrait) {
+                    supertraits.push(supertrait);
+                }
+            }
+        }
     }
 
-    let mut file = File::create(output_directory_path.join(file_name)).unwrap();
+    let mut output = "\n// Visits every node below the one it's given. Passes override the methods of the nodes they're\n// interested in, and call the function of the same name to go on with the children.\n".to_string();
+    match supertraits.is_empty() {
+        true => output += "pub trait Walker {\n",
+        false => output += &format!("pub trait Walker: {} {{\n", supertraits.join(" + ")),
+    }
+    output += &format!(
+        "    fn walk_{base_name_lower_case}(&mut self, {base_name_lower_case}: &{base_name}) {{\n        walk_{base_name_lower_case}(self, {base_name_lower_case});\n    }}\n"
+    );
+    for node in &section.nodes {
+        let name_lower_case = snake_case(&node.name);
+        output += &format!(
+            "\n    fn walk_{name_lower_case}_{base_name_lower_case}(&mut self, {base_name_lower_case}: &{}) {{\n        walk_{name_lower_case}_{base_name_lower_case}(self, {base_name_lower_case});\n    }}\n",
+            node.name
+        );
+    }
+    output += "}\n";
 
-    writeln!(file, "use crate::lexical_analysis::Token;\nuse super::ast_printer::AstPrinter;").unwrap();
+    output += &format!(
+        "\npub fn walk_{base_name_lower_case}<W: Walker + ?Sized>(walker: &mut W, {base_name_lower_case}: &{base_name}) {{\n    match {base_name_lower_case} {{\n"
+    );
+    for node in &section.nodes {
+        let name_lower_case = snake_case(&node.name);
+        output += &format!(
+            "        {base_name}::{}({base_name_lower_case}) => walker.walk_{name_lower_case}_{base_name_lower_case}({base_name_lower_case}),\n",
+            node.name
+        );
+    }
+    output += "    }\n}\n";
 
-    writeln!(
-        file,
-        "pub trait Expr {{\n    fn accept(&self, visitor: &AstPrinter) -> String;\n}}\n"
-    )
-    .unwrap();
+    for node in &section.nodes {
+        let name_lower_case = snake_case(&node.name);
+        let mut body = String::new();
+        for field in &node.fields {
+            if let Some(other) = section_of(&field.inner_type, sections) {
+                let method = match field.inner_type == other.base {
+                    true => format!("walk_{}", other.base.to_lowercase()),
+                    false => format!(
+                        "walk_{}_{}",
+                        snake_case(&field.inner_type),
+                        other.base.to_lowercase()
+                    ),
+                };
+                let place = format!("{base_name_lower_case}.{}", field.name);
+                body += &traverse(
+                    field,
+                    &place,
+                    false,
+                    &field.wrappers,
+                    1,
+                    &|place, is_ref| match is_ref {
+                        true => format!("walker.{method}({place});"),
+                        false => format!("walker.{method}(&{place});"),
+                    },
+                );
+            }
+        }
 
-    writeln!(file, "pub trait Visitor {{").unwrap();
+        let (walker, parameter, body) = match body.is_empty() {
+            true => (
+                "_walker".to_string(),
+                format!("_{base_name_lower_case}"),
+                "{}".to_string(),
+            ),
+            false => (
+                "walker".to_string(),
+                base_name_lower_case.clone(),
+                format!("{{\n{body}}}"),
+            ),
+        };
+        output += &format!(
+            "\npub fn walk_{name_lower_case}_{base_name_lower_case}<W: Walker + ?Sized>({walker}: &mut W, {parameter}: &{}) {body}\n",
+            node.name
+        );
+    }
+
+    output
+}
+
+fn define_type(node: &Node, sections: &[Section]) -> String {
+    let mut output = format!(
+        "\n#[derive(Debug, Clone, PartialEq)]\npub struct {} {{\n",
+        node.name
+    );
+    for field in &node.fields {
+        output += &format!("    pub {}: {},\n", field.name, field.field_type);
+    }
+    output += "}\n";
+
+    let mut body = String::new();
+    for field in &node.fields {
+        let is_node = section_of(&field.inner_type, sections).is_some();
+        if !is_node && field.inner_type != "Token" {
+            continue;
+        }
+
+        let place = format!("self.{}", field.name);
+        body += &traverse(
+            field,
+            &place,
+            false,
+            &field.wrappers,
+            2,
+            &|place, _| match is_node {
+                true => format!("cover(&mut span, {place}.span());"),
+                false => format!("cover(&mut span, Some({place}.span()));"),
+            },
+        );
+    }
+
+    output += &format!(
+        "\nimpl {} {{\n    pub fn span(&self) -> Option<Range<usize>> {{\n",
+        node.name
+    );
+    match body.is_empty() {
+        true => output += "        None\n",
+        false => output += &format!("        let mut span = None;\n{body}        span\n"),
+    }
+    output += "    }\n}\n";
 
-    for ast_type in &types {
-        let ast_type_components = ast_type.split("=").collect::<Vec<&str>>();
-        let name: &str = ast_type_components.get(0).unwrap().trim();
-        let name_lower_case = name.to_lowercase();
-        let struct_signature = format!("    fn visit_{name_lower_case}_expr(&self, expr: &{name}) -> String;");
-        writeln!(file, "{struct_signature}").unwrap();
+    output
+}
+
+// Lines which run `visit` on every value of the field at `place`, a reference or the field itself,
+// going into the options and lists wrapped around it.
+fn traverse(
+    field: &Field,
+    place: &str,
+    is_ref: bool,
+    wrappers: &[Wrapper],
+    depth: usize,
+    visit: &dyn Fn(&str, bool) -> String,
+) -> String {
+    let indent = "    ".repeat(depth);
+
+    match wrappers.split_first() {
+        None => format!("{indent}{}\n", visit(place, is_ref)),
+        Some((wrapper, wrappers)) => {
+            let (binding, head) = match wrapper {
+                Wrapper::Option => {
+                    // the field is named after what it holds, i.e. `else_branch`
+                    let binding = match is_ref {
+                        true => "item",
+                        false => field.name.as_str(),
+                    };
+                    let value = match is_ref {
+                        true => place.to_string(),
+                        false => format!("&{place}"),
+                    };
+                    (binding, format!("if let Some({binding}) = {value} {{"))
+                }
+                Wrapper::Vec => ("item", format!("for item in {place}.iter() {{")),
+            };
+
+            let inner = traverse(field, binding, true, wrappers, depth + 1, visit);
+            format!("{indent}{head}\n{inner}{indent}}}\n")
+        }
     }
+}
+
+// type of the `id` field shared by all nodes of the section, if they have one
+fn id_type(section: &Section) -> Option<&str> {
+    let mut types = section.nodes.iter().map(|node| {
+        node.fields
+            .iter()
+            .find(|field| field.name == "id")
+            .map(|field| field.field_type.as_str())
+    });
 
-    writeln!(file, "}}").unwrap();
+    let first = types.next()??;
+    types.all(|id_type| id_type == Some(first)).then_some(first)
+}
 
-    for ast_type in types {
-        let ast_type_components = ast_type.split("=").collect::<Vec<&str>>();
-        let name: &str = ast_type_components.get(0).unwrap().trim();
-        let name_lower_case = name.to_lowercase();
-        let fields: &str = ast_type_components.get(1).unwrap().trim();
-        define_type(&mut file, name, fields);
+// section whose enum or one of its nodes is named `name`
+fn section_of<'a>(name: &str, sections: &'a [Section]) -> Option<&'a Section> {
+    sections
+        .iter()
+        .find(|section| section.base == name || section.nodes.iter().any(|node| node.name == name))
+}
 
-        let accept =
-            format!("impl Expr for {name} {{\nfn accept(&self, visitor: &AstPrinter) -> String {{return visitor.visit_{name_lower_case}_expr(&self).to_string();}}");
-        writeln!(file, "{accept}").unwrap();
-        writeln!(file, "}}\n").unwrap();
+// IndexSet is visited by visit_index_set_expr
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    for (index, character) in name.char_indices() {
+        if character.is_uppercase() && index > 0 {
+            result.push('_');
+        }
+        result.push(character.to_ascii_lowercase());
     }
+
+    result
 }
 
-fn define_type(file: &mut File, name: &str, fields: &str) {
-    let struct_signature = format!("pub struct {name} {{");
-    writeln!(file, "{struct_signature}").unwrap();
-    for field in fields.split(",").collect::<Vec<&str>>() {
-        let field_trimmed = field.trim();
-        writeln!(file, "    pub {field_trimmed},").unwrap();
+// Splits on commas that aren't nested inside generic arguments, i.e. `Option<Box<Stmt>>`; none if
+// the brackets aren't balanced.
+fn split_fields(fields: &str) -> Option<Vec<&str>> {
+    let mut result = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (index, character) in fields.char_indices() {
+        match character {
+            '<' | '{' | '(' => depth += 1,
+            '>' | '}' | ')' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                result.push(&fields[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&fields[start..]);
+
+    match depth {
+        0 => Some(result),
+        _ => None,
     }
-    writeln!(file, "}}\n").unwrap();
 }
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::json::Json;
+use crate::lexical_analysis::{Lexer, TokenType};
+
+use super::analysis::{Analysis, DeclarationKind};
+use super::parser::Parser;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    Keyword,
+    // `true`, `false` and `nil`
+    Constant,
+    Number,
+    String,
+    Comment,
+    Operator,
+    Punctuation,
+    Local,
+    Global,
+    Parameter,
+    Function,
+    Method,
+    Class,
+    Module,
+    Property,
+}
+
+impl Highlight {
+    // used as the CSS class and the token type of the JSON stream
+    pub fn name(self) -> &'static str {
+        match self {
+            Highlight::Keyword => "keyword",
+            Highlight::Constant => "constant",
+            Highlight::Number => "number",
+            Highlight::String => "string",
+            Highlight::Comment => "comment",
+            Highlight::Operator => "operator",
+            Highlight::Punctuation => "punctuation",
+            Highlight::Local => "local",
+            Highlight::Global => "global",
+            Highlight::Parameter => "parameter",
+            Highlight::Function => "function",
+            Highlight::Method => "method",
+            Highlight::Class => "class",
+            Highlight::Module => "module",
+            Highlight::Property => "property",
+        }
+    }
+
+    // SGR parameters of the terminal colour, none for plain text
+    fn ansi(self) -> Option<&'static str> {
+        match self {
+            Highlight::Keyword => Some("35"),
+            Highlight::Constant | Highlight::Number => Some("33"),
+            Highlight::String => Some("32"),
+            Highlight::Comment => Some("90"),
+            Highlight::Global => Some("1"),
+            Highlight::Parameter => Some("3"),
+            Highlight::Function | Highlight::Method => Some("34"),
+            Highlight::Class | Highlight::Module => Some("1;36"),
+            Highlight::Property => Some("36"),
+            Highlight::Operator | Highlight::Punctuation | Highlight::Local => None,
+        }
+    }
+}
+
+// highlighted characters of the source
+pub struct Span {
+    pub highlight: Highlight,
+    pub range: Range<usize>,
+    // the name of a declaration rather than a reference to it
+    pub declaration: bool,
+}
+
+// Spans of the tokens and comments of a script in source order. Identifiers are told apart by the
+// declarations they refer to, so a script with errors is highlighted as far as the parser recovered;
+// the errors themselves aren't reported and characters the lexer rejected aren't highlighted.
+pub fn highlight(source: &str) -> Vec<Span> {
+    let ((tokens, comments, analysis), _) = crate::capture_errors(|| {
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.scan_tokens();
+
+        let mut parser = Parser::new(lexer.tokens.clone());
+        let statements = parser.parse();
+        (lexer.tokens, lexer.comments, Analysis::new(&statements))
+    });
+
+    // names by their offset, with the declaration they stand for
+    let mut names = HashMap::new();
+    for reference in &analysis.references {
+        if let Some(declaration) = reference.declaration {
+            names.insert(reference.name.offset, (declaration, false));
+        }
+    }
+    for (index, declaration) in analysis.declarations.iter().enumerate() {
+        names.insert(declaration.name.offset, (index, true));
+    }
+
+    let mut spans: Vec<Span> = comments
+        .iter()
+        .map(|comment| Span {
+            highlight: Highlight::Comment,
+            range: comment.offset..comment.offset + comment.text.chars().count(),
+            declaration: false,
+        })
+        .collect();
+
+    for (index, token) in tokens.iter().enumerate() {
+        let mut declaration = false;
+
+        let highlight = match token.token_type {
+            TokenType::Eof => continue,
+            TokenType::Identifier => match names.get(&token.offset) {
+                Some(&(index, is_declaration)) => {
+                    declaration = is_declaration;
+                    let declaration = &analysis.declarations[index];
+                    match declaration.kind {
+                        DeclarationKind::Variable if declaration.global => Highlight::Global,
+                        DeclarationKind::Variable => Highlight::Local,
+                        DeclarationKind::Parameter => Highlight::Parameter,
+                        DeclarationKind::Function => Highlight::Function,
+                        DeclarationKind::Method => Highlight::Method,
+                        DeclarationKind::Class => Highlight::Class,
+                        DeclarationKind::Module => Highlight::Module,
+                    }
+                }
+                None if index > 0 && tokens[index - 1].token_type == TokenType::Dot => {
+                    Highlight::Property
+                }
+                // globals the script doesn't declare, i.e. native functions
+                None => Highlight::Global,
+            },
+            TokenType::String => Highlight::String,
+            TokenType::Number => Highlight::Number,
+            TokenType::True | TokenType::False | TokenType::Nil => Highlight::Constant,
+            TokenType::LeftParen
+            | TokenType::RightParen
+            | TokenType::LeftBrace
+            | TokenType::RightBrace
+            | TokenType::LeftBracket
+            | TokenType::RightBracket
+            | TokenType::Colon
+            | TokenType::Comma
+            | TokenType::Dot
+            | TokenType::Semicolon => Highlight::Punctuation,
+            TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::Bang
+            | TokenType::BangEqual
+            | TokenType::Equal
+            | TokenType::EqualEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => Highlight::Operator,
+            _ => Highlight::Keyword,
+        };
+
+        spans.push(Span {
+            highlight,
+            range: token.span(),
+            declaration,
+        });
+    }
+
+    spans.sort_by_key(|span| span.range.start);
+    spans
+}
+
+// source with the spans wrapped by `open` and `close`, and plain text passed through `escape`
+fn render(
+    source: &str,
+    open: impl Fn(Highlight) -> String,
+    close: impl Fn(Highlight) -> String,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let text = |range: Range<usize>| escape(&chars[range].iter().collect::<String>());
+
+    let mut output = String::new();
+    let mut current = 0;
+    for span in highlight(source) {
+        let start = span.range.start.max(current);
+        let end = span.range.end.min(chars.len());
+        if start >= end {
+            continue;
+        }
+
+        output += &text(current..start);
+        output += &open(span.highlight);
+        output += &text(start..end);
+        output += &close(span.highlight);
+        current = end;
+    }
+
+    output + &text(current..chars.len())
+}
+
+// source coloured with ANSI escape codes for terminals
+pub fn ansi(source: &str) -> String {
+    render(
+        source,
+        |highlight| {
+            highlight
+                .ansi()
+                .map_or(String::new(), |code| format!("\x1b[{code}m"))
+        },
+        |highlight| {
+            highlight
+                .ansi()
+                .map_or(String::new(), |_| "\x1b[0m".to_string())
+        },
+        str::to_string,
+    )
+}
+
+// source as a `pre` element with a span per token, whose CSS class is the name of its highlight
+pub fn html(source: &str) -> String {
+    let code = render(
+        source,
+        |highlight| format!("<span class=\"{}\">", highlight.name()),
+        |_| "</span>".to_string(),
+        |text| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        },
+    );
+
+    format!("<pre class=\"lox\"><code>{code}</code></pre>\n")
+}
+
+// Array of semantic tokens with their line and column counted from one, their offset and length in
+// characters, their type and the `declaration` modifier for names of declarations.
+pub fn json(source: &str) -> Json {
+    let chars: Vec<char> = source.chars().collect();
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut current = 0;
+
+    let tokens = highlight(source)
+        .into_iter()
+        .map(|span| {
+            for (index, character) in chars
+                .iter()
+                .enumerate()
+                .take(span.range.start)
+                .skip(current)
+            {
+                if *character == '\n' {
+                    line += 1;
+                    line_start = index + 1;
+                }
+            }
+            current = span.range.start;
+
+            let modifiers: Vec<Json> = match span.declaration {
+                true => vec!["declaration".into()],
+                false => Vec::new(),
+            };
+            Json::object([
+                ("line", line.into()),
+                ("column", (span.range.start - line_start + 1).into()),
+                ("offset", span.range.start.into()),
+                ("length", span.range.len().into()),
+                ("type", span.highlight.name().into()),
+                ("modifiers", modifiers.into()),
+            ])
+        })
+        .collect::<Vec<Json>>();
+
+    tokens.into()
+}
@@ -0,0 +1,48 @@
+use super::expression::Expr;
+use super::statement::Stmt;
+
+// Line of the first token of a node which keeps one, used to report errors which aren't raised by
+// a particular operation. Empty blocks have no token.
+impl Expr {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Expr::Assign(expr) => Some(expr.name.line),
+            Expr::Binary(expr) => expr.left.line().or(Some(expr.operator.line)),
+            Expr::Call(expr) => expr.callee.line().or(Some(expr.paren.line)),
+            Expr::Get(expr) => expr.object.line().or(Some(expr.name.line)),
+            Expr::Grouping(expr) => expr.expression.line(),
+            Expr::Index(expr) => expr.object.line().or(Some(expr.bracket.line)),
+            Expr::IndexSet(expr) => expr.object.line().or(Some(expr.bracket.line)),
+            Expr::List(expr) => Some(expr.bracket.line),
+            Expr::Literal(expr) => Some(expr.token.line),
+            Expr::Logical(expr) => expr.left.line().or(Some(expr.operator.line)),
+            Expr::Map(expr) => Some(expr.brace.line),
+            Expr::Set(expr) => expr.object.line().or(Some(expr.name.line)),
+            Expr::Super(expr) => Some(expr.keyword.line),
+            Expr::This(expr) => Some(expr.keyword.line),
+            Expr::Unary(expr) => Some(expr.operator.line),
+            Expr::Variable(expr) => Some(expr.name.line),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Block(stmt) => stmt.statements.iter().find_map(Stmt::line),
+            Stmt::Break(stmt) => Some(stmt.keyword.line),
+            Stmt::Class(stmt) => Some(stmt.name.line),
+            Stmt::Continue(stmt) => Some(stmt.keyword.line),
+            Stmt::Expression(stmt) => stmt.expression.line(),
+            Stmt::Function(stmt) => Some(stmt.name.line),
+            Stmt::If(stmt) => stmt.condition.line().or(Some(stmt.keyword.line)),
+            Stmt::Import(stmt) => Some(stmt.keyword.line),
+            Stmt::Print(stmt) => Some(stmt.keyword.line),
+            Stmt::Return(stmt) => Some(stmt.keyword.line),
+            Stmt::Throw(stmt) => Some(stmt.keyword.line),
+            Stmt::Try(stmt) => Some(stmt.keyword.line),
+            Stmt::Var(stmt) => Some(stmt.name.line),
+            Stmt::While(stmt) => Some(stmt.keyword.line),
+        }
+    }
+}
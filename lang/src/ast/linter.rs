@@ -0,0 +1,505 @@
+use std::fmt;
+
+use crate::lexical_analysis::{Comment, Lexer, Token, TokenType};
+use crate::Diagnostic;
+
+use super::analysis::{Analysis, DeclarationKind};
+use super::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map,
+    Set, Super, This, Unary, Variable,
+};
+use super::literal_value::LiteralValue;
+use super::parser::Parser;
+use super::resolver::Resolver;
+use super::statement::{
+    self, Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt,
+    Throw, Try, Var, While,
+};
+
+// prefix of the comments which enable and disable rules
+const DIRECTIVE: &str = "lint:";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    Shadowing,
+    SelfComparison,
+    ConstantCondition,
+    AssignmentInCondition,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnusedVariable,
+        Rule::UnusedParameter,
+        Rule::UnreachableCode,
+        Rule::Shadowing,
+        Rule::SelfComparison,
+        Rule::ConstantCondition,
+        Rule::AssignmentInCondition,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnusedParameter => "unused-parameter",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::Shadowing => "shadowing",
+            Rule::SelfComparison => "self-comparison",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+// problem found by a rule, printed like a compile error followed by the name of the rule
+pub struct Lint {
+    pub rule: Rule,
+    pub diagnostic: Diagnostic,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}]", self.diagnostic, self.rule.name())
+    }
+}
+
+// Lexes, parses and resolves a script and checks it with every rule which isn't disabled, errors
+// are reported as they're found. A `// lint: disable rule, ...` comment on its own line disables
+// the rules from its line on and `// lint: enable rule, ...` enables them again; after code, the
+// comment applies to its line only. Without rules, the comment applies to all of them.
+pub fn lint(source: &str) -> Option<Vec<Lint>> {
+    let mut lexer = Lexer::new(source.to_string());
+    lexer.scan_tokens();
+
+    let mut parser = Parser::new(lexer.tokens.clone());
+    let statements = parser.parse();
+
+    if lexer.had_error || parser.had_error {
+        return None;
+    }
+
+    let mut resolver = Resolver::new();
+    resolver.resolve(&statements);
+
+    let directives = directives(&lexer.comments)?;
+    if resolver.had_error {
+        return None;
+    }
+
+    let mut linter = Linter::default();
+    linter.names(&Analysis::new(&statements));
+    linter.statements(&statements);
+
+    let mut lints: Vec<Lint> = linter
+        .lints
+        .into_iter()
+        .filter(|lint| enabled(&directives, lint.rule, lint.diagnostic.line))
+        .collect();
+    lints.sort_by_key(|lint| lint.diagnostic.line);
+    Some(lints)
+}
+
+struct Directive {
+    line: usize,
+    rules: Vec<Rule>,
+    enable: bool,
+    // the directive follows code and applies to its line only
+    trailing: bool,
+}
+
+fn directives(comments: &[Comment]) -> Option<Vec<Directive>> {
+    let mut directives = Vec::new();
+    let mut had_error = false;
+
+    for comment in comments {
+        let text = comment.text.trim_start_matches('/').trim();
+        let Some(directive) = text.strip_prefix(DIRECTIVE) else {
+            continue;
+        };
+
+        let directive = directive.trim();
+        let (enable, rules) = match directive.split_once(char::is_whitespace) {
+            Some((action, rules)) => (action, rules.trim()),
+            None => (directive, ""),
+        };
+        let enable = match enable {
+            "enable" => true,
+            "disable" => false,
+            _ => {
+                crate::error(
+                    comment.line,
+                    "Expect 'enable' or 'disable' after 'lint:'.".to_string(),
+                );
+                had_error = true;
+                continue;
+            }
+        };
+
+        let mut names = rules
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .peekable();
+        let rules = match names.peek() {
+            None => Rule::ALL.to_vec(),
+            Some(_) => names
+                .filter_map(|name| {
+                    let rule = Rule::from_name(name);
+                    if rule.is_none() {
+                        crate::error(comment.line, format!("Unknown lint rule '{name}'."));
+                        had_error = true;
+                    }
+                    rule
+                })
+                .collect(),
+        };
+
+        directives.push(Directive {
+            line: comment.line,
+            rules,
+            enable,
+            trailing: comment.trailing,
+        });
+    }
+
+    (!had_error).then_some(directives)
+}
+
+// every rule is enabled unless the last directive for it says otherwise
+fn enabled(directives: &[Directive], rule: Rule, line: usize) -> bool {
+    let applies = |directive: &&Directive| {
+        directive.rules.contains(&rule)
+            && match directive.trailing {
+                true => directive.line == line,
+                false => directive.line <= line,
+            }
+    };
+
+    let trailing = directives
+        .iter()
+        .rev()
+        .filter(applies)
+        .find(|directive| directive.trailing);
+    let own_line = directives
+        .iter()
+        .rev()
+        .filter(applies)
+        .find(|directive| !directive.trailing);
+
+    trailing
+        .or(own_line)
+        .is_none_or(|directive| directive.enable)
+}
+
+#[derive(Default)]
+struct Linter {
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn report(&mut self, rule: Rule, token: &Token, message: String) {
+        self.lints.push(Lint {
+            rule,
+            diagnostic: Diagnostic {
+                line: token.line,
+                span: Some(token.span()),
+                location: format!(" at '{}'", token.lexeme),
+                message,
+            },
+        });
+    }
+
+    // rules about declarations and the names referring to them
+    fn names(&mut self, analysis: &Analysis) {
+        for (index, declaration) in analysis.declarations.iter().enumerate() {
+            let name = &declaration.name;
+
+            if let Some(shadowed) = declaration.shadows {
+                let line = analysis.declarations[shadowed].name.line;
+                let message = format!("'{}' shadows the declaration on line {line}.", name.lexeme);
+                self.report(Rule::Shadowing, name, message);
+            }
+
+            // globals can be used by the scripts importing this one, and an underscore marks
+            // names which are unused on purpose
+            if declaration.global
+                || name.lexeme.starts_with('_')
+                || analysis
+                    .references_to(index)
+                    .any(|reference| !reference.write)
+            {
+                continue;
+            }
+
+            let (rule, kind) = match declaration.kind {
+                DeclarationKind::Parameter => (Rule::UnusedParameter, "Parameter"),
+                DeclarationKind::Variable => (Rule::UnusedVariable, "Variable"),
+                DeclarationKind::Function => (Rule::UnusedVariable, "Function"),
+                DeclarationKind::Class => (Rule::UnusedVariable, "Class"),
+                DeclarationKind::Module => (Rule::UnusedVariable, "Module"),
+                DeclarationKind::Method => continue,
+            };
+            let message = format!("{kind} '{}' is never used.", name.lexeme);
+            self.report(rule, name, message);
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        // only the first statement which never runs is reported
+        let unreachable = statements
+            .iter()
+            .position(exits)
+            .and_then(|exit| statements.get(exit + 1));
+        if let Some(line) = unreachable.and_then(Stmt::line) {
+            self.lints.push(Lint {
+                rule: Rule::UnreachableCode,
+                diagnostic: Diagnostic {
+                    line,
+                    span: None,
+                    location: String::new(),
+                    message: "Unreachable code.".to_string(),
+                },
+            });
+        }
+
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn condition(&mut self, keyword: &Token, condition: &Expr) {
+        // `while (true)` is how loops which exit from their body are written
+        let infinite_loop = keyword.token_type != TokenType::If
+            && matches!(
+                condition,
+                Expr::Literal(Literal {
+                    value: LiteralValue::Bool(true),
+                    ..
+                })
+            );
+
+        if constant(condition) && !infinite_loop {
+            let message = "Condition is always the same.".to_string();
+            self.report(Rule::ConstantCondition, keyword, message);
+        }
+
+        // a condition in extra parentheses is assigned on purpose
+        let target = match condition {
+            Expr::Assign(expr) => Some(&expr.name),
+            Expr::Set(expr) => Some(&expr.name),
+            Expr::IndexSet(expr) => Some(&expr.bracket),
+            _ => None,
+        };
+        if let Some(target) = target {
+            let message = "Assignment in a condition, use '==' to compare.".to_string();
+            self.report(Rule::AssignmentInCondition, target, message);
+        }
+
+        self.expression(condition);
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+}
+
+// the statements after the statement never run
+fn exits(statement: &Stmt) -> bool {
+    match statement {
+        Stmt::Return(_) | Stmt::Throw(_) | Stmt::Break(_) | Stmt::Continue(_) => true,
+        Stmt::Block(stmt) => stmt.statements.last().is_some_and(exits),
+        Stmt::If(stmt) => {
+            exits(&stmt.then_branch) && stmt.else_branch.as_deref().is_some_and(exits)
+        }
+        _ => false,
+    }
+}
+
+// expression made of literals only
+fn constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(_) => true,
+        Expr::Grouping(expr) => constant(&expr.expression),
+        Expr::Unary(expr) => constant(&expr.right),
+        Expr::Binary(expr) => constant(&expr.left) && constant(&expr.right),
+        Expr::Logical(expr) => constant(&expr.left) && constant(&expr.right),
+        _ => false,
+    }
+}
+
+// variable or property which can be read without side effects, i.e. `this.x`
+fn path(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Variable(expr) => Some(expr.name.lexeme.to_string()),
+        Expr::This(_) => Some("this".to_string()),
+        Expr::Get(expr) => Some(format!("{}.{}", path(&expr.object)?, expr.name.lexeme)),
+        Expr::Grouping(expr) => path(&expr.expression),
+        _ => None,
+    }
+}
+
+impl statement::Visitor<()> for Linter {
+    fn visit_block_stmt(&mut self, stmt: &Block) {
+        self.statements(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &Class) {
+        for method in &stmt.methods {
+            self.statements(&method.body);
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) {
+        self.expression(&stmt.expression);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Function) {
+        self.statements(&stmt.body);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) {
+        self.condition(&stmt.keyword, &stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_import_stmt(&mut self, _stmt: &Import) {}
+
+    fn visit_print_stmt(&mut self, stmt: &Print) {
+        self.expression(&stmt.expression);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) {
+        if let Some(value) = &stmt.value {
+            self.expression(value);
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) {
+        self.expression(&stmt.value);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) {
+        self.statements(&stmt.body);
+        self.statements(&stmt.catch_body);
+        if let Some(finally_body) = &stmt.finally_body {
+            self.statements(finally_body);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        if let Some(initializer) = &stmt.initializer {
+            self.expression(initializer);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) {
+        self.condition(&stmt.keyword, &stmt.condition);
+        stmt.body.accept(self);
+        if let Some(increment) = &stmt.increment {
+            self.expression(increment);
+        }
+    }
+}
+
+impl expression::Visitor<()> for Linter {
+    fn visit_assign_expr(&mut self, expr: &Assign) {
+        self.expression(&expr.value);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) {
+        let comparison = matches!(
+            expr.operator.token_type,
+            TokenType::EqualEqual
+                | TokenType::BangEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+        );
+
+        if let (true, Some(left)) = (comparison, path(&expr.left)) {
+            if path(&expr.right).as_ref() == Some(&left) {
+                let message = format!("'{left}' is compared with itself.");
+                self.report(Rule::SelfComparison, &expr.operator, message);
+            }
+        }
+
+        self.expression(&expr.left);
+        self.expression(&expr.right);
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) {
+        self.expression(&expr.callee);
+        for argument in &expr.arguments {
+            self.expression(argument);
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) {
+        self.expression(&expr.object);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) {
+        self.expression(&expr.expression);
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) {
+        self.expression(&expr.object);
+        self.expression(&expr.index);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) {
+        self.expression(&expr.object);
+        self.expression(&expr.index);
+        self.expression(&expr.value);
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) {
+        for element in &expr.elements {
+            self.expression(element);
+        }
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &Logical) {
+        self.expression(&expr.left);
+        self.expression(&expr.right);
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) {
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            self.expression(key);
+            self.expression(value);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) {
+        self.expression(&expr.object);
+        self.expression(&expr.value);
+    }
+
+    fn visit_super_expr(&mut self, _expr: &Super) {}
+
+    fn visit_this_expr(&mut self, _expr: &This) {}
+
+    fn visit_unary_expr(&mut self, expr: &Unary) {
+        self.expression(&expr.right);
+    }
+
+    fn visit_variable_expr(&mut self, _expr: &Variable) {}
+}
@@ -0,0 +1,23 @@
+use std::fmt;
+
+use crate::symbol::Symbol;
+
+// runtime-independent value of a literal expression; originally it was Java Object in Lox
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiteralValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Symbol),
+}
+
+impl fmt::Display for LiteralValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LiteralValue::Nil => write!(f, "nil"),
+            LiteralValue::Bool(value) => write!(f, "{value}"),
+            LiteralValue::Number(value) => write!(f, "{value}"),
+            LiteralValue::String(value) => write!(f, "{value}"),
+        }
+    }
+}
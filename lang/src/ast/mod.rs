@@ -1,4 +1,56 @@
+pub mod analysis;
 pub mod ast_printer;
+pub mod cfg;
+pub mod export;
 pub mod expression;
+pub mod formatter;
 pub mod generate_ast;
+pub mod highlighter;
+pub mod line;
+pub mod linter;
+pub mod literal_value;
+pub mod node_id;
 pub mod parser;
+pub mod resolver;
+pub mod rpn_printer;
+pub mod source_printer;
+pub mod statement;
+
+use std::rc::Rc;
+
+use crate::lexical_analysis::Lexer;
+use parser::Parser;
+use resolver::{Depths, Resolver};
+use statement::Stmt;
+
+// statements of a script with the scope distances of the variables they use, which the tree-walker
+// looks variables up by; functions declared in the script share its distances
+pub struct Script {
+    pub statements: Vec<Stmt>,
+    pub depths: Rc<Depths>,
+}
+
+// lexes, parses and resolves a script, errors are reported as they're found
+pub fn parse(source: String) -> Option<Script> {
+    let mut lexer = Lexer::new(source);
+    lexer.scan_tokens();
+
+    let mut parser = Parser::new(lexer.tokens);
+    let statements = parser.parse();
+
+    if lexer.had_error || parser.had_error {
+        return None;
+    }
+
+    let mut resolver = Resolver::new();
+    resolver.resolve(&statements);
+
+    if resolver.had_error {
+        return None;
+    }
+
+    Some(Script {
+        statements,
+        depths: Rc::new(resolver.depths),
+    })
+}
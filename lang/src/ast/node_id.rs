@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ids given out so far by every parser of the process
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+// Identity of an expression or statement, which the parser takes from a counter shared by every
+// parser, so nodes of REPL lines, imported modules and other scripts of a session never share one.
+// Passes keep what they find out about nodes in side tables keyed by it rather than in fields of the
+// tree; the ids of one script are close together, so a table can be a `Vec` indexed by them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn new(index: usize) -> Self {
+        NodeId(index)
+    }
+
+    // id which no node parsed before has
+    pub fn next() -> Self {
+        NodeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
@@ -1,38 +1,791 @@
+use std::rc::Rc;
+
 use crate::lexical_analysis::{Token, TokenType};
+use crate::symbol::Symbol;
+
+use super::expression::{
+    Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map, Set,
+    Super, This, Unary, Variable,
+};
+use super::literal_value::LiteralValue;
+use super::node_id::NodeId;
+use super::statement::{
+    Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt, Throw,
+    Try, Var, While,
+};
+
+pub const MAX_ARGUMENTS: usize = 255;
+
+// most nodes above one another in a tree; every pass walks the tree recursively, so a deeper one
+// would overflow the Rust stack of whichever thread runs it
+pub const MAX_NESTING: usize = 256;
+
+// native stack the parser may use, well below the 2 MiB of a spawned Rust thread; a level of
+// parentheses takes a dozen frames of the parser, which are kilobytes each in a debug build
+const MAX_STACK: usize = 512 * 1024;
 
-use super::expression::{Binary, Expr, Grouping, Literal, Unary};
+// variable holding the iterator of a desugared `for ... in` loop; the name can't be written in a
+// script, so it doesn't clash with user variables
+pub const ITERATOR: &str = " iterator";
 
 pub struct Parser {
     pub tokens: Vec<Token>,
-    pub current: u8,
+    pub current: usize,
+    pub had_error: bool,
+    // nesting of the node being parsed
+    depth: usize,
+    // stack position where parsing started
+    stack_base: usize,
+    // deepest nesting reached since the innermost chain of left associative operators started
+    deepest: usize,
+    // set when nesting ran too deep; the rest of the script is skipped, and the errors of leaving
+    // the nesting early aren't reported
+    gave_up: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            had_error: false,
+            depth: 0,
+            stack_base: 0,
+            deepest: 0,
+            gave_up: false,
+        }
+    }
+
+    fn id(&mut self) -> NodeId {
+        NodeId::next()
+    }
+
+    pub fn parse(&mut self) -> Vec<Stmt> {
+        self.stack_base = crate::stack_position();
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        statements
+    }
+
+    fn declaration(&mut self) -> Option<Stmt> {
+        let statement = if Self::match_token(self, Vec::from([TokenType::Class])) {
+            self.class_declaration()
+        } else if Self::match_token(self, Vec::from([TokenType::Fun])) {
+            self.function("function").map(Stmt::Function)
+        } else if Self::match_token(self, Vec::from([TokenType::Var])) {
+            self.var_declaration()
+        } else if Self::match_token(self, Vec::from([TokenType::Import])) {
+            self.import_declaration()
+        } else if Self::match_token(self, Vec::from([TokenType::From])) {
+            self.selective_import_declaration()
+        } else {
+            self.statement()
+        };
+
+        match statement {
+            Ok(statement) => Some(statement),
+            Err(_) => {
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let name = self.consume(TokenType::Identifier, "Expect class name.".to_string())?;
+
+        let superclass = if Self::match_token(self, Vec::from([TokenType::Less])) {
+            let name =
+                self.consume(TokenType::Identifier, "Expect superclass name.".to_string())?;
+            Some(Variable {
+                id: self.id(),
+                name,
+            })
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::LeftBrace,
+            "Expect '{' before class body.".to_string(),
+        )?;
+
+        let mut methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        let closing_brace = self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after class body.".to_string(),
+        )?;
+
+        Ok(Stmt::Class(Class {
+            id: self.id(),
+            keyword,
+            name,
+            superclass,
+            methods,
+            closing_brace,
+        }))
+    }
+
+    // import "path" as name;
+    fn import_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let path = self.consume(TokenType::String, "Expect module path.".to_string())?;
+        self.consume(TokenType::As, "Expect 'as' after module path.".to_string())?;
+        let name = self.consume(TokenType::Identifier, "Expect module name.".to_string())?;
+        let semicolon =
+            self.consume(TokenType::Semicolon, "Expect ';' after import.".to_string())?;
+
+        Ok(Stmt::Import(Import {
+            id: self.id(),
+            keyword,
+            path,
+            name: Some(name),
+            names: Vec::new(),
+            semicolon,
+        }))
+    }
+
+    // from "path" import name, name;
+    fn selective_import_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let path = self.consume(TokenType::String, "Expect module path.".to_string())?;
+        self.consume(
+            TokenType::Import,
+            "Expect 'import' after module path.".to_string(),
+        )?;
+
+        let mut names = Vec::new();
+        loop {
+            names.push(self.consume(TokenType::Identifier, "Expect imported name.".to_string())?);
+
+            if !Self::match_token(self, Vec::from([TokenType::Comma])) {
+                break;
+            }
+        }
+
+        let semicolon =
+            self.consume(TokenType::Semicolon, "Expect ';' after import.".to_string())?;
+
+        Ok(Stmt::Import(Import {
+            id: self.id(),
+            keyword,
+            path,
+            name: None,
+            names,
+            semicolon,
+        }))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Function, ParseError> {
+        // methods are declared without `fun`
+        let keyword = Some(self.previous()).filter(|token| token.token_type == TokenType::Fun);
+        let name = self.consume(TokenType::Identifier, format!("Expect {kind} name."))?;
+        self.consume(
+            TokenType::LeftParen,
+            format!("Expect '(' after {kind} name."),
+        )?;
+
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.error(
+                        self.peek(),
+                        format!("Can't have more than {MAX_ARGUMENTS} parameters."),
+                    );
+                }
+
+                params.push(
+                    self.consume(TokenType::Identifier, "Expect parameter name.".to_string())?,
+                );
+
+                if !Self::match_token(self, Vec::from([TokenType::Comma])) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after parameters.".to_string(),
+        )?;
+        self.consume(
+            TokenType::LeftBrace,
+            format!("Expect '{{' before {kind} body."),
+        )?;
+        let body = self.block()?;
+
+        Ok(Function {
+            id: self.id(),
+            keyword,
+            name,
+            params,
+            body: Rc::new(body),
+            closing_brace: self.previous(),
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let name = self.consume(TokenType::Identifier, "Expect variable name.".to_string())?;
+
+        let initializer = if Self::match_token(self, Vec::from([TokenType::Equal])) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        let semicolon = self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.".to_string(),
+        )?;
+
+        Ok(Stmt::Var(Var {
+            id: self.id(),
+            keyword,
+            name,
+            initializer,
+            semicolon,
+        }))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        self.nested("Statement", Self::nested_statement)
+    }
+
+    fn nested_statement(&mut self) -> Result<Stmt, ParseError> {
+        if Self::match_token(self, Vec::from([TokenType::For])) {
+            return self.for_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Break])) {
+            return self.break_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Continue])) {
+            return self.continue_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::If])) {
+            return self.if_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Print])) {
+            return self.print_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Return])) {
+            return self.return_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::While])) {
+            return self.while_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Throw])) {
+            return self.throw_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Try])) {
+            return self.try_statement();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::LeftBrace])) {
+            let brace = self.previous();
+            let statements = self.block()?;
+            return Ok(Stmt::Block(Block {
+                id: self.id(),
+                brace,
+                statements,
+                closing_brace: self.previous(),
+            }));
+        }
+
+        self.expression_statement()
+    }
+
+    // for loop is desugared into a while loop wrapped in blocks; the increment is kept apart from
+    // the body, so `continue` doesn't skip it
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.".to_string())?;
+
+        if (self.check(TokenType::Identifier) && self.check_ahead(1, TokenType::In))
+            || (self.check(TokenType::Var) && self.check_ahead(2, TokenType::In))
+        {
+            return self.for_in_statement(keyword);
+        }
+
+        let initializer = if Self::match_token(self, Vec::from([TokenType::Semicolon])) {
+            None
+        } else if Self::match_token(self, Vec::from([TokenType::Var])) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if !self.check(TokenType::Semicolon) {
+            self.expression()?
+        } else {
+            // a missing condition is `true`, at the semicolon where it's left out
+            Expr::Literal(Literal {
+                id: self.id(),
+                token: self.peek(),
+                value: LiteralValue::Bool(true),
+            })
+        };
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after loop condition.".to_string(),
+        )?;
+
+        let increment = if !self.check(TokenType::RightParen) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.".to_string(),
+        )?;
+
+        let brace = self.synthetic_token(TokenType::LeftBrace, "{", &keyword);
+        let mut body = Stmt::While(While {
+            id: self.id(),
+            keyword,
+            condition,
+            body: Box::new(self.statement()?),
+            increment,
+        });
+
+        // the block of the initializer spans the whole loop
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(Block {
+                id: self.id(),
+                brace,
+                statements: Vec::from([initializer, body]),
+                closing_brace: self.synthetic_token(TokenType::RightBrace, "}", &self.previous()),
+            });
+        }
+
+        Ok(body)
+    }
+
+    // for (x in iterable) body is desugared into
+    // { var <iterator> = iterable.iter(); while (<iterator>.has_next()) { var x = <iterator>.next(); body } }
+    fn for_in_statement(&mut self, keyword: Token) -> Result<Stmt, ParseError> {
+        Self::match_token(self, Vec::from([TokenType::Var]));
+        let name = self.consume(TokenType::Identifier, "Expect variable name.".to_string())?;
+        let in_keyword = self.consume(
+            TokenType::In,
+            "Expect 'in' after variable name.".to_string(),
+        )?;
+        let iterable = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.".to_string(),
+        )?;
+
+        let body = self.statement()?;
+        let last = self.previous();
+
+        let iterator = self.synthetic_token(TokenType::Identifier, ITERATOR, &in_keyword);
+        let var_keyword = self.synthetic_token(TokenType::Var, "var", &in_keyword);
+        let semicolon = self.synthetic_token(TokenType::Semicolon, ";", &in_keyword);
+        let brace = self.synthetic_token(TokenType::LeftBrace, "{", &keyword);
+        let closing_brace = self.synthetic_token(TokenType::RightBrace, "}", &last);
+        let method_call = |parser: &mut Self, object: Expr, method: &str| {
+            Expr::Call(Call {
+                id: parser.id(),
+                callee: Box::new(Expr::Get(Get {
+                    id: parser.id(),
+                    object: Box::new(object),
+                    name: parser.synthetic_token(TokenType::Identifier, method, &in_keyword),
+                })),
+                paren: parser.synthetic_token(TokenType::RightParen, ")", &in_keyword),
+                arguments: Vec::new(),
+            })
+        };
+        let iterator_variable = |parser: &mut Self| {
+            Expr::Variable(Variable {
+                id: parser.id(),
+                name: iterator.clone(),
+            })
+        };
+
+        let object = iterator_variable(self);
+        let next = Stmt::Var(Var {
+            id: self.id(),
+            keyword: var_keyword.clone(),
+            name,
+            initializer: Some(method_call(self, object, "next")),
+            semicolon: semicolon.clone(),
+        });
+        let object = iterator_variable(self);
+        let loop_statement = Stmt::While(While {
+            id: self.id(),
+            keyword,
+            condition: method_call(self, object, "has_next"),
+            body: Box::new(Stmt::Block(Block {
+                id: self.id(),
+                brace: brace.clone(),
+                statements: Vec::from([next, body]),
+                closing_brace: closing_brace.clone(),
+            })),
+            increment: None,
+        });
+
+        Ok(Stmt::Block(Block {
+            id: self.id(),
+            brace,
+            statements: Vec::from([
+                Stmt::Var(Var {
+                    id: self.id(),
+                    keyword: var_keyword,
+                    name: iterator.clone(),
+                    initializer: Some(method_call(self, iterable, "iter")),
+                    semicolon,
+                }),
+                loop_statement,
+            ]),
+            closing_brace,
+        }))
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.".to_string())?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after if condition.".to_string(),
+        )?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if Self::match_token(self, Vec::from([TokenType::Else])) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(If {
+            id: self.id(),
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let expression = self.expression()?;
+        let semicolon =
+            self.consume(TokenType::Semicolon, "Expect ';' after value.".to_string())?;
+
+        Ok(Stmt::Print(Print {
+            id: self.id(),
+            keyword,
+            expression,
+            semicolon,
+        }))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let value = if !self.check(TokenType::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        let semicolon = self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after return value.".to_string(),
+        )?;
+
+        Ok(Stmt::Return(Return {
+            id: self.id(),
+            keyword,
+            value,
+            semicolon,
+        }))
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let semicolon = self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after 'break'.".to_string(),
+        )?;
+
+        Ok(Stmt::Break(Break {
+            id: self.id(),
+            keyword,
+            semicolon,
+        }))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let semicolon = self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after 'continue'.".to_string(),
+        )?;
+
+        Ok(Stmt::Continue(Continue {
+            id: self.id(),
+            keyword,
+            semicolon,
+        }))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        let semicolon = self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after thrown value.".to_string(),
+        )?;
+
+        Ok(Stmt::Throw(Throw {
+            id: self.id(),
+            keyword,
+            value,
+            semicolon,
+        }))
+    }
+
+    // at least one of the catch and finally clauses has to follow the try block
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.".to_string())?;
+        let body = self.block()?;
+
+        let (catch_name, catch_body) = if Self::match_token(self, Vec::from([TokenType::Catch])) {
+            self.consume(
+                TokenType::LeftParen,
+                "Expect '(' after 'catch'.".to_string(),
+            )?;
+            let name = self.consume(
+                TokenType::Identifier,
+                "Expect error variable name.".to_string(),
+            )?;
+            self.consume(
+                TokenType::RightParen,
+                "Expect ')' after error variable.".to_string(),
+            )?;
+            self.consume(
+                TokenType::LeftBrace,
+                "Expect '{' before catch body.".to_string(),
+            )?;
+            (Some(name), self.block()?)
+        } else {
+            (None, Vec::new())
+        };
+
+        let finally_body = if Self::match_token(self, Vec::from([TokenType::Finally])) {
+            self.consume(
+                TokenType::LeftBrace,
+                "Expect '{' after 'finally'.".to_string(),
+            )?;
+            Some(Rc::new(self.block()?))
+        } else {
+            None
+        };
+
+        if catch_name.is_none() && finally_body.is_none() {
+            return Err(self.error(
+                self.peek(),
+                "Expect 'catch' or 'finally' after try block.".to_string(),
+            ));
+        }
+
+        Ok(Stmt::Try(Try {
+            id: self.id(),
+            keyword,
+            body,
+            catch_name,
+            catch_body,
+            finally_body,
+            closing_brace: self.previous(),
+        }))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(
+            TokenType::LeftParen,
+            "Expect '(' after 'while'.".to_string(),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after condition.".to_string(),
+        )?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While(While {
+            id: self.id(),
+            keyword,
+            condition,
+            body,
+            increment: None,
+        }))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        self.nested("Statement", Self::nested_block)
+    }
+
+    fn nested_block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.".to_string())?;
+
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expression = self.expression()?;
+        let semicolon = self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after expression.".to_string(),
+        )?;
+
+        Ok(Stmt::Expression(Expression {
+            id: self.id(),
+            expression,
+            semicolon,
+        }))
     }
 
-    fn expression(&mut self) -> Box<dyn Expr> {
-        Self::equality(self)
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.nested("Expression", Self::assignment)
     }
 
-    fn equality(&mut self) -> Box<dyn Expr> {
-        let mut expr = Self::comparison(self);
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.or()?;
+
+        if Self::match_token(self, Vec::from([TokenType::Equal])) {
+            let equals = self.previous();
+            let value = Box::new(self.nested("Expression", Self::assignment)?);
+
+            // the target becomes the assignment, which keeps its id
+            return match expr {
+                Expr::Variable(variable) => Ok(Expr::Assign(Assign {
+                    id: variable.id,
+                    name: variable.name,
+                    value,
+                })),
+                Expr::Get(get) => Ok(Expr::Set(Set {
+                    id: get.id,
+                    object: get.object,
+                    name: get.name,
+                    value,
+                })),
+                Expr::Index(index) => Ok(Expr::IndexSet(IndexSet {
+                    id: index.id,
+                    object: index.object,
+                    bracket: index.bracket,
+                    index: index.index,
+                    value,
+                })),
+                _ => {
+                    // reported but not thrown, because the parser isn't in a confused state
+                    self.error(equals, "Invalid assignment target.".to_string());
+                    Ok(expr)
+                }
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = self.and()?;
+
+        while Self::match_token(self, Vec::from([TokenType::Or])) {
+            let operator = self.previous();
+            let right = self.and()?;
+            expr = Expr::Logical(Logical {
+                id: self.id(),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+            links += 1;
+            self.link(links)?;
+        }
+
+        self.end_chain(outer, links);
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = self.equality()?;
+
+        while Self::match_token(self, Vec::from([TokenType::And])) {
+            let operator = self.previous();
+            let right = self.equality()?;
+            expr = Expr::Logical(Logical {
+                id: self.id(),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+            links += 1;
+            self.link(links)?;
+        }
+
+        self.end_chain(outer, links);
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = Self::comparison(self)?;
 
         while Self::match_token(
             self,
             Vec::from([TokenType::BangEqual, TokenType::EqualEqual]),
         ) {
             let operator = Self::previous(self);
-            let right = Self::comparison(self);
-            expr = Box::new(Binary {
-                left: expr,
-                operator: operator.clone(),
-                right,
+            let right = Self::comparison(self)?;
+            expr = Expr::Binary(Binary {
+                id: self.id(),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
             });
+            links += 1;
+            self.link(links)?;
         }
 
-        expr
+        self.end_chain(outer, links);
+        Ok(expr)
     }
 
     fn match_token(&mut self, tokens: Vec<TokenType>) -> bool {
@@ -54,12 +807,19 @@ impl Parser {
         Self::peek(self).token_type == token_type
     }
 
-    fn advance(&mut self) -> TokenType {
+    fn check_ahead(&self, distance: usize, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + distance) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
+    fn advance(&mut self) -> Token {
         if !Self::is_at_end(self) {
-            self.current = self.current + 1;
+            self.current += 1;
         }
 
-        Self::previous(self).token_type
+        Self::previous(self)
     }
 
     fn is_at_end(&self) -> bool {
@@ -67,35 +827,51 @@ impl Parser {
     }
 
     fn peek(&self) -> Token {
-        match self.tokens.get(self.current as usize) {
+        match self.tokens.get(self.current) {
             Some(token) => token.to_owned(),
-            None => Token {
-                // TODO - I might be wrong here and maybe either nothing should be returned or it should be a different value than Nil - likely Eof
-                token_type: TokenType::Nil, // Maybe TokenType::Eof
-                lexeme: "".to_string(),
-                literal: "".to_string(),
-                numeric_literal: 0.0,
-                line: 0,
-            },
+            None => self.eof(),
         }
     }
 
     fn previous(&self) -> Token {
-        match self.tokens.get((self.current - 1) as usize) {
+        match self
+            .current
+            .checked_sub(1)
+            .and_then(|index| self.tokens.get(index))
+        {
             Some(token) => token.to_owned(),
-            None => Token {
-                // TODO - I might be wrong here and maybe either nothing should be returned or it should be a different value than Nil - likely Eof
-                token_type: TokenType::Nil,
-                lexeme: "".to_string(),
-                literal: "".to_string(),
-                numeric_literal: 0.0,
-                line: 0,
-            },
+            None => self.eof(),
         }
     }
 
-    fn comparison(&mut self) -> Box<dyn Expr> {
-        let mut expr = Self::term(self);
+    // token of code which the parser generates when desugaring
+    fn synthetic_token(&self, token_type: TokenType, lexeme: &str, origin: &Token) -> Token {
+        Token {
+            token_type,
+            lexeme: Symbol::from(lexeme),
+            literal: Symbol::default(),
+            numeric_literal: 0.0,
+            line: origin.line,
+            offset: origin.offset,
+        }
+    }
+
+    // the lexer always terminates tokens with Eof, so running past the end behaves the same
+    fn eof(&self) -> Token {
+        Token {
+            token_type: TokenType::Eof,
+            lexeme: Symbol::default(),
+            literal: Symbol::default(),
+            numeric_literal: 0.0,
+            line: self.tokens.last().map_or(0, |token| token.line),
+            offset: self.tokens.last().map_or(0, |token| token.offset),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = Self::term(self)?;
 
         while Self::match_token(
             self,
@@ -106,113 +882,352 @@ impl Parser {
                 TokenType::LessEqual,
             ]),
         ) {
-            let operator = Self::previous(&self);
-            let right = Self::term(self);
-            expr = Box::new(Binary {
-                left: expr,
-                operator: operator.clone(),
-                right,
+            let operator = Self::previous(self);
+            let right = Self::term(self)?;
+            expr = Expr::Binary(Binary {
+                id: self.id(),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
             });
+            links += 1;
+            self.link(links)?;
         }
 
-        expr
+        self.end_chain(outer, links);
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Box<dyn Expr> {
-        let mut expr = Self::factor(self);
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = Self::factor(self)?;
 
         while Self::match_token(self, Vec::from([TokenType::Minus, TokenType::Plus])) {
             let operator = Self::previous(self);
-            let right = Self::factor(self);
-            expr = Box::new(Binary {
-                left: expr,
-                operator: operator.clone(),
-                right,
-            })
+            let right = Self::factor(self)?;
+            expr = Expr::Binary(Binary {
+                id: self.id(),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+            links += 1;
+            self.link(links)?;
         }
 
-        expr
+        self.end_chain(outer, links);
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Box<dyn Expr> {
-        let mut expr = Self::unary(self);
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = Self::unary(self)?;
 
         while Self::match_token(self, Vec::from([TokenType::Slash, TokenType::Star])) {
             let operator = Self::previous(self);
-            let right = Self::unary(self);
-            expr = Box::new(Binary {
-                left: expr,
-                operator: operator.clone(),
-                right,
+            let right = Self::unary(self)?;
+            expr = Expr::Binary(Binary {
+                id: self.id(),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
             });
+            links += 1;
+            self.link(links)?;
         }
 
-        expr
+        self.end_chain(outer, links);
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Box<dyn Expr> {
+    fn unary(&mut self) -> Result<Expr, ParseError> {
         if Self::match_token(self, Vec::from([TokenType::Bang, TokenType::Minus])) {
             let operator = Self::previous(self);
-            let right = Self::unary(self);
-            return Box::new(Unary {
-                operator: operator.clone(),
-                right,
-            });
+            let right = self.nested("Expression", Self::unary)?;
+            return Ok(Expr::Unary(Unary {
+                id: self.id(),
+                operator,
+                right: Box::new(right),
+            }));
         }
 
-        Self::primary(self)
+        self.call()
     }
 
-    fn primary(&mut self) -> Box<dyn Expr> {
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let outer = self.start_chain();
+        let mut links = 0;
+        let mut expr = self.primary()?;
+
+        loop {
+            if Self::match_token(self, Vec::from([TokenType::LeftParen])) {
+                expr = self.finish_call(expr)?;
+            } else if Self::match_token(self, Vec::from([TokenType::Dot])) {
+                let name = self.consume(
+                    TokenType::Identifier,
+                    "Expect property name after '.'.".to_string(),
+                )?;
+                expr = Expr::Get(Get {
+                    id: self.id(),
+                    object: Box::new(expr),
+                    name,
+                });
+            } else if Self::match_token(self, Vec::from([TokenType::LeftBracket])) {
+                let index = self.expression()?;
+                let bracket = self.consume(
+                    TokenType::RightBracket,
+                    "Expect ']' after index.".to_string(),
+                )?;
+                expr = Expr::Index(Index {
+                    id: self.id(),
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
+            } else {
+                break;
+            }
+
+            links += 1;
+            self.link(links)?;
+        }
+
+        self.end_chain(outer, links);
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = Vec::new();
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.error(
+                        self.peek(),
+                        format!("Can't have more than {MAX_ARGUMENTS} arguments."),
+                    );
+                }
+                arguments.push(self.expression()?);
+
+                if !Self::match_token(self, Vec::from([TokenType::Comma])) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(
+            TokenType::RightParen,
+            "Expect ')' after arguments.".to_string(),
+        )?;
+
+        Ok(Expr::Call(Call {
+            id: self.id(),
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
         if Self::match_token(self, Vec::from([TokenType::False])) {
-            return Box::new(Literal {
-                value: "false".to_string(), // it's likely that I need to represent Literal in a different way than a String value
-            });
+            return Ok(Expr::Literal(Literal {
+                id: self.id(),
+                token: self.previous(),
+                value: LiteralValue::Bool(false),
+            }));
         }
 
         if Self::match_token(self, Vec::from([TokenType::True])) {
-            return Box::new(Literal {
-                value: "true".to_string(),
-            });
+            return Ok(Expr::Literal(Literal {
+                id: self.id(),
+                token: self.previous(),
+                value: LiteralValue::Bool(true),
+            }));
         }
 
         if Self::match_token(self, Vec::from([TokenType::Nil])) {
-            return Box::new(Literal {
-                value: "nil".to_string(),
-            });
+            return Ok(Expr::Literal(Literal {
+                id: self.id(),
+                token: self.previous(),
+                value: LiteralValue::Nil,
+            }));
         }
 
-        if Self::match_token(self, Vec::from([TokenType::Number, TokenType::String])) {
-            return Box::new(Literal {
-                value: self.previous().literal.clone(),
-            });
+        if Self::match_token(self, Vec::from([TokenType::Number])) {
+            return Ok(Expr::Literal(Literal {
+                id: self.id(),
+                token: self.previous(),
+                value: LiteralValue::Number(self.previous().numeric_literal),
+            }));
         }
 
-        // TODO for super keyword
-        // if Self::match_token(self, Vec::from([TokenType::Super])) {
-        //     let keyword: &Token = self.previous();
-        //     self.consume(TokenType::Dot, "Expect '.' after 'super'.");
-        //     let method: &Token = self.consume(TokenType::Identifier, "Expect superclass method name.");
-        //     return Box::new(Literal {
-        //         value: self.previous().literal
-        //     });
-        // }
+        if Self::match_token(self, Vec::from([TokenType::String])) {
+            return Ok(Expr::Literal(Literal {
+                id: self.id(),
+                token: self.previous(),
+                value: LiteralValue::String(self.previous().literal),
+            }));
+        }
 
-        // TODO: Revert if; commented out to have all paths returning a value
-        // if Self::match_token(self, Vec::from([TokenType::LeftParen])) {
-        let expr = self.expression();
+        if Self::match_token(self, Vec::from([TokenType::Super])) {
+            let keyword = self.previous();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.".to_string())?;
+            let method = self.consume(
+                TokenType::Identifier,
+                "Expect superclass method name.".to_string(),
+            )?;
+            return Ok(Expr::Super(Super {
+                id: self.id(),
+                keyword,
+                method,
+            }));
+        }
 
-        self.consume(
-            TokenType::RightParen,
-            "Expect ')' after expression.".to_string(),
-        ); // TODO use result of consume, especially if Err was returned
-        return Box::new(Grouping { expression: expr });
-        // }
+        if Self::match_token(self, Vec::from([TokenType::This])) {
+            return Ok(Expr::This(This {
+                id: self.id(),
+                keyword: self.previous(),
+            }));
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::Identifier])) {
+            return Ok(Expr::Variable(Variable {
+                id: self.id(),
+                name: self.previous(),
+            }));
+        }
 
-        // Err(self.error(self.peek(), "Expect expression."))
+        if Self::match_token(self, Vec::from([TokenType::LeftParen])) {
+            let paren = self.previous();
+            let expr = self.expression()?;
+            let closing_paren = self.consume(
+                TokenType::RightParen,
+                "Expect ')' after expression.".to_string(),
+            )?;
+            return Ok(Expr::Grouping(Grouping {
+                id: self.id(),
+                paren,
+                expression: Box::new(expr),
+                closing_paren,
+            }));
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::LeftBracket])) {
+            return self.list();
+        }
+
+        if Self::match_token(self, Vec::from([TokenType::LeftBrace])) {
+            return self.map();
+        }
+
+        Err(self.error(self.peek(), "Expect expression.".to_string()))
     }
 
-    fn consume(&mut self, token_type: TokenType, message: String) -> Result<TokenType, ParseError> {
+    fn list(&mut self) -> Result<Expr, ParseError> {
+        let bracket = self.previous();
+        let mut elements = Vec::new();
+
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                elements.push(self.expression()?);
+
+                if !Self::match_token(self, Vec::from([TokenType::Comma])) {
+                    break;
+                }
+            }
+        }
+
+        let closing_bracket = self.consume(
+            TokenType::RightBracket,
+            "Expect ']' after list elements.".to_string(),
+        )?;
+
+        Ok(Expr::List(List {
+            id: self.id(),
+            bracket,
+            elements,
+            closing_bracket,
+        }))
+    }
+
+    fn map(&mut self) -> Result<Expr, ParseError> {
+        let brace = self.previous();
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                keys.push(self.expression()?);
+                self.consume(TokenType::Colon, "Expect ':' after map key.".to_string())?;
+                values.push(self.expression()?);
+
+                if !Self::match_token(self, Vec::from([TokenType::Comma])) {
+                    break;
+                }
+            }
+        }
+
+        let closing_brace = self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after map entries.".to_string(),
+        )?;
+
+        Ok(Expr::Map(Map {
+            id: self.id(),
+            brace,
+            keys,
+            values,
+            closing_brace,
+        }))
+    }
+
+    // parses a node nested in the one being parsed
+    fn nested<T>(
+        &mut self,
+        kind: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let stack = crate::stack_position().abs_diff(self.stack_base);
+        if self.depth == MAX_NESTING || stack > MAX_STACK {
+            return Err(self.too_deep(kind));
+        }
+
+        self.depth += 1;
+        self.deepest = self.deepest.max(self.depth);
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // every operator of a chain nests the ones before it, however shallow the recursion of the
+    // parser is; returns the deepest nesting outside of the chain
+    fn start_chain(&mut self) -> usize {
+        std::mem::replace(&mut self.deepest, self.depth)
+    }
+
+    // checks the nesting of the operands parsed so far under the operators of the chain
+    fn link(&mut self, links: usize) -> Result<(), ParseError> {
+        if self.deepest + links > MAX_NESTING {
+            return Err(self.too_deep("Expression"));
+        }
+
+        Ok(())
+    }
+
+    fn end_chain(&mut self, outer: usize, links: usize) {
+        self.deepest = outer.max(self.deepest + links);
+    }
+
+    fn too_deep(&mut self, kind: &str) -> ParseError {
+        let error = self.error(self.peek(), format!("{kind} nested too deeply."));
+        self.gave_up = true;
+        self.current = self.tokens.len().saturating_sub(1);
+        error
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: String) -> Result<Token, ParseError> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
@@ -220,17 +1235,24 @@ impl Parser {
         Err(self.error(self.peek(), message))
     }
 
-    fn error(&self, token: Token, message: String) -> ParseError {
+    fn error(&mut self, token: Token, message: String) -> ParseError {
+        if self.gave_up {
+            return ParseError {};
+        }
+
         if token.token_type == TokenType::Eof {
-            self.report(token.line, " at end".to_string(), message.to_string());
+            self.report(&token, " at end".to_string(), message);
         } else {
-            self.report(token.line, format!(" at '{}'", token.lexeme), message);
+            self.report(&token, format!(" at '{}'", token.lexeme), message);
         }
 
         ParseError {}
     }
 
-    fn report(&self, line: u8, where_error: String, message: String) {}
+    fn report(&mut self, token: &Token, where_error: String, message: String) {
+        self.had_error = true;
+        crate::report_span(token.line, Some(token.span()), where_error, message);
+    }
 
     fn synchronize(&mut self) {
         self.advance();
@@ -253,6 +1275,10 @@ impl Parser {
                 TokenType::While,
                 TokenType::Print,
                 TokenType::Return,
+                TokenType::Throw,
+                TokenType::Try,
+                TokenType::Import,
+                TokenType::From,
             ]
             .contains(&self.peek().token_type)
             {
@@ -262,10 +1288,6 @@ impl Parser {
             self.advance();
         }
     }
-
-    pub fn parse(&mut self) -> Box<dyn Expr> {
-        return self.expression(); // TODO handle Result from primary up to this place
-    }
 }
 
-struct ParseError {}
+pub struct ParseError {}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+
+use crate::lexical_analysis::Token;
+use crate::symbol::Symbol;
+
+use super::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map,
+    Set, Super, This, Unary, Variable,
+};
+use super::node_id::NodeId;
+use super::statement::{
+    self, Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt,
+    Throw, Try, Var, While,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// scope distances of the variable usages of one script which resolve to a local, indexed by the id
+// of the usage's node counted from the lowest id of the script
+#[derive(Default)]
+pub struct Depths {
+    first: usize,
+    distances: Vec<Option<usize>>,
+}
+
+impl Depths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<usize> {
+        let index = id.index().checked_sub(self.first)?;
+        self.distances.get(index).copied().flatten()
+    }
+
+    fn insert(&mut self, id: NodeId, distance: usize) {
+        let id = id.index();
+        if self.distances.is_empty() {
+            self.first = id;
+        } else if id < self.first {
+            // usages are mostly resolved in the order they're parsed, so this is rare
+            let missing = self.first - id;
+            self.distances.splice(0..0, (0..missing).map(|_| None));
+            self.first = id;
+        }
+
+        let index = id - self.first;
+        if index >= self.distances.len() {
+            self.distances.resize(index + 1, None);
+        }
+        self.distances[index] = Some(distance);
+    }
+}
+
+// static analysis pass which binds every variable usage to the scope it's declared in;
+// usages missing from `depths` are global variables
+pub struct Resolver {
+    scopes: Vec<HashMap<Symbol, bool>>,
+    pub depths: Depths,
+    current_function: FunctionType,
+    current_class: ClassType,
+    // loops enclosing the current statement within the current function
+    loop_depth: usize,
+    pub had_error: bool,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            depths: Depths::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            had_error: false,
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn resolve_expression(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn resolve_function(&mut self, function: &Function, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        // a loop around a function declaration can't be left from the function's body
+        let enclosing_loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(&function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let already_declared = match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.lexeme.clone(), false).is_some(),
+            None => false,
+        };
+
+        if already_declared {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn resolve_local(&mut self, name: &Token, id: NodeId) {
+        for (distance, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                self.depths.insert(id, distance);
+                return;
+            }
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.had_error = true;
+        crate::report_span(
+            token.line,
+            Some(token.span()),
+            format!(" at '{}'", token.lexeme),
+            message.to_string(),
+        );
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl statement::Visitor<()> for Resolver {
+    fn visit_block_stmt(&mut self, stmt: &Block) {
+        self.begin_scope();
+        self.resolve(&stmt.statements);
+        self.end_scope();
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Class) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            if superclass.name.lexeme == stmt.name.lexeme {
+                self.error(&superclass.name, "A class can't inherit from itself.");
+            }
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_local(&superclass.name, superclass.id);
+
+            self.begin_scope();
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert(Symbol::from("super"), true);
+            }
+        }
+
+        self.begin_scope();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(Symbol::from("this"), true);
+        }
+
+        for method in &stmt.methods {
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+
+        self.end_scope();
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) {
+        self.resolve_expression(&stmt.expression);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Function) {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        self.resolve_function(stmt, FunctionType::Function);
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) {
+        for name in stmt.name.iter().chain(&stmt.names) {
+            self.declare(name);
+            self.define(name);
+        }
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) {
+        self.resolve_expression(&stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) {
+        self.resolve_expression(&stmt.expression);
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &Break) {
+        if self.loop_depth == 0 {
+            self.error(&stmt.keyword, "Can't use 'break' outside of a loop.");
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &Continue) {
+        if self.loop_depth == 0 {
+            self.error(&stmt.keyword, "Can't use 'continue' outside of a loop.");
+        }
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) {
+        if self.current_function == FunctionType::None {
+            self.error(&stmt.keyword, "Can't return from top-level code.");
+        }
+
+        if let Some(value) = &stmt.value {
+            if self.current_function == FunctionType::Initializer {
+                self.error(&stmt.keyword, "Can't return a value from an initializer.");
+            }
+
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) {
+        self.resolve_expression(&stmt.value);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) {
+        self.begin_scope();
+        self.resolve(&stmt.body);
+        self.end_scope();
+
+        // the error variable lives in the same scope as the statements of the catch block
+        if let Some(name) = &stmt.catch_name {
+            self.begin_scope();
+            self.declare(name);
+            self.define(name);
+            self.resolve(&stmt.catch_body);
+            self.end_scope();
+        }
+
+        if let Some(finally_body) = &stmt.finally_body {
+            self.begin_scope();
+            self.resolve(finally_body);
+            self.end_scope();
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        self.declare(&stmt.name);
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_expression(initializer);
+        }
+        self.define(&stmt.name);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) {
+        self.resolve_expression(&stmt.condition);
+
+        self.loop_depth += 1;
+        stmt.body.accept(self);
+        self.loop_depth -= 1;
+
+        if let Some(increment) = &stmt.increment {
+            self.resolve_expression(increment);
+        }
+    }
+}
+
+impl expression::Visitor<()> for Resolver {
+    fn visit_assign_expr(&mut self, expr: &Assign) {
+        self.resolve_expression(&expr.value);
+        self.resolve_local(&expr.name, expr.id);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) {
+        self.resolve_expression(&expr.left);
+        self.resolve_expression(&expr.right);
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) {
+        self.resolve_expression(&expr.callee);
+        for argument in &expr.arguments {
+            self.resolve_expression(argument);
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) {
+        self.resolve_expression(&expr.object);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) {
+        self.resolve_expression(&expr.expression);
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) {
+        self.resolve_expression(&expr.object);
+        self.resolve_expression(&expr.index);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) {
+        self.resolve_expression(&expr.value);
+        self.resolve_expression(&expr.object);
+        self.resolve_expression(&expr.index);
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) {
+        for element in &expr.elements {
+            self.resolve_expression(element);
+        }
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &Logical) {
+        self.resolve_expression(&expr.left);
+        self.resolve_expression(&expr.right);
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) {
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            self.resolve_expression(key);
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) {
+        self.resolve_expression(&expr.value);
+        self.resolve_expression(&expr.object);
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) {
+        match self.current_class {
+            ClassType::None => self.error(&expr.keyword, "Can't use 'super' outside of a class."),
+            ClassType::Class => self.error(
+                &expr.keyword,
+                "Can't use 'super' in a class with no superclass.",
+            ),
+            ClassType::Subclass => {}
+        }
+
+        self.resolve_local(&expr.keyword, expr.id);
+    }
+
+    fn visit_this_expr(&mut self, expr: &This) {
+        if self.current_class == ClassType::None {
+            self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+
+        self.resolve_local(&expr.keyword, expr.id);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) {
+        self.resolve_expression(&expr.right);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) {
+        if let Some(scope) = self.scopes.last() {
+            if scope.get(&expr.name.lexeme) == Some(&false) {
+                self.error(
+                    &expr.name,
+                    "Can't read local variable in its own initializer.",
+                );
+            }
+        }
+
+        self.resolve_local(&expr.name, expr.id);
+    }
+}
@@ -0,0 +1,107 @@
+use super::expression::{
+    Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map, Set,
+    Super, This, Unary, Variable, Visitor,
+};
+use super::literal_value::LiteralValue;
+
+// Prints expressions in reverse Polish notation, operands before their operator, i.e.
+// `(1 + 2) * (4 - 3)` as `1 2 + 4 3 - *`. Groupings leave no trace, unary minus is `neg` to tell it
+// from subtraction, and operators taking any number of operands carry their count, like `call/2`.
+pub struct RpnPrinter {}
+
+impl Visitor<String> for RpnPrinter {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        format!("{} ={}", self.print(&expr.value), expr.name.lexeme)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> String {
+        self.postfix(&expr.operator.lexeme, vec![&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+        self.postfix(&format!("call/{}", expr.arguments.len()), exprs)
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) -> String {
+        format!("{} .{}", self.print(&expr.object), expr.name.lexeme)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> String {
+        self.print(&expr.expression)
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) -> String {
+        self.postfix("[]", vec![&expr.object, &expr.index])
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> String {
+        self.postfix("[]=", vec![&expr.object, &expr.index, &expr.value])
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> String {
+        let operator = format!("list/{}", expr.elements.len());
+        self.postfix(&operator, expr.elements.iter().collect())
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        match &expr.value {
+            LiteralValue::String(value) => format!("\"{value}\""),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Logical) -> String {
+        self.postfix(&expr.operator.lexeme, vec![&expr.left, &expr.right])
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) -> String {
+        let entries = expr
+            .keys
+            .iter()
+            .zip(&expr.values)
+            .flat_map(|(key, value)| [key, value])
+            .collect();
+        self.postfix(&format!("map/{}", expr.keys.len()), entries)
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) -> String {
+        self.postfix(
+            &format!(".{}=", expr.name.lexeme),
+            vec![&expr.object, &expr.value],
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) -> String {
+        format!("super.{}", expr.method.lexeme)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &This) -> String {
+        "this".to_string()
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> String {
+        let operator = match &*expr.operator.lexeme {
+            "-" => "neg",
+            operator => operator,
+        };
+        self.postfix(operator, vec![&expr.right])
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
+}
+
+impl RpnPrinter {
+    pub fn print(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    fn postfix(&mut self, operator: &str, exprs: Vec<&Expr>) -> String {
+        let mut operands: Vec<String> = exprs.iter().map(|expr| expr.accept(self)).collect();
+        operands.push(operator.to_string());
+        operands.join(" ")
+    }
+}
@@ -0,0 +1,174 @@
+use super::expression::{
+    Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map, Set,
+    Super, This, Unary, Variable, Visitor,
+};
+use super::literal_value::LiteralValue;
+
+// binding strength of the expressions of the grammar, from the loosest
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Assignment,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Call,
+}
+
+impl Precedence {
+    fn of(expr: &Expr) -> Self {
+        match expr {
+            Expr::Assign(_) | Expr::Set(_) | Expr::IndexSet(_) => Precedence::Assignment,
+            Expr::Logical(expr) => Precedence::infix(&expr.operator.lexeme),
+            Expr::Binary(expr) => Precedence::infix(&expr.operator.lexeme),
+            Expr::Unary(_) => Precedence::Unary,
+            // groupings are printed without their parentheses, which are put back if they're needed
+            Expr::Grouping(expr) => Precedence::of(&expr.expression),
+            _ => Precedence::Call,
+        }
+    }
+
+    fn infix(operator: &str) -> Self {
+        match operator {
+            "or" => Precedence::Or,
+            "and" => Precedence::And,
+            "==" | "!=" => Precedence::Equality,
+            ">" | ">=" | "<" | "<=" => Precedence::Comparison,
+            "+" | "-" => Precedence::Term,
+            _ => Precedence::Factor,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Precedence::Assignment => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary | Precedence::Call => Precedence::Call,
+        }
+    }
+}
+
+// Prints expressions as source which parses back to the same tree, with parentheses only where
+// precedence or associativity requires them, i.e. `((a + b)) * (c * d)` as `(a + b) * (c * d)`.
+pub struct SourcePrinter {}
+
+impl Visitor<String> for SourcePrinter {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        let value = self.operand(&expr.value, Precedence::Assignment);
+        format!("{} = {value}", expr.name.lexeme)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> String {
+        self.infix(&expr.left, &expr.operator.lexeme, &expr.right)
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let callee = self.operand(&expr.callee, Precedence::Call);
+        format!("{callee}({})", self.list(&expr.arguments))
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) -> String {
+        let object = self.operand(&expr.object, Precedence::Call);
+        format!("{object}.{}", expr.name.lexeme)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> String {
+        self.print(&expr.expression)
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) -> String {
+        let object = self.operand(&expr.object, Precedence::Call);
+        format!("{object}[{}]", self.print(&expr.index))
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> String {
+        let object = self.operand(&expr.object, Precedence::Call);
+        let index = self.print(&expr.index);
+        let value = self.operand(&expr.value, Precedence::Assignment);
+        format!("{object}[{index}] = {value}")
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> String {
+        format!("[{}]", self.list(&expr.elements))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        match &expr.value {
+            LiteralValue::String(value) => format!("\"{value}\""),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Logical) -> String {
+        self.infix(&expr.left, &expr.operator.lexeme, &expr.right)
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) -> String {
+        let entries: Vec<String> = expr
+            .keys
+            .iter()
+            .zip(&expr.values)
+            .map(|(key, value)| format!("{}: {}", self.print(key), self.print(value)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) -> String {
+        let object = self.operand(&expr.object, Precedence::Call);
+        let value = self.operand(&expr.value, Precedence::Assignment);
+        format!("{object}.{} = {value}", expr.name.lexeme)
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) -> String {
+        format!("super.{}", expr.method.lexeme)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &This) -> String {
+        "this".to_string()
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> String {
+        let right = self.operand(&expr.right, Precedence::Unary);
+        format!("{}{right}", expr.operator.lexeme)
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
+}
+
+impl SourcePrinter {
+    pub fn print(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    // operand which binds at least as strongly as `precedence`, parenthesized when it doesn't
+    fn operand(&mut self, expr: &Expr, precedence: Precedence) -> String {
+        let printed = self.print(expr);
+        match Precedence::of(expr) < precedence {
+            true => format!("({printed})"),
+            false => printed,
+        }
+    }
+
+    // operators are left-associative, so an operand of the same precedence on the right is grouped
+    fn infix(&mut self, left: &Expr, operator: &str, right: &Expr) -> String {
+        let precedence = Precedence::infix(operator);
+        let left = self.operand(left, precedence);
+        let right = self.operand(right, precedence.next());
+        format!("{left} {operator} {right}")
+    }
+
+    fn list(&mut self, exprs: &[Expr]) -> String {
+        let printed: Vec<String> = exprs.iter().map(|expr| self.print(expr)).collect();
+        printed.join(", ")
+    }
+}
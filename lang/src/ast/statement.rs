@@ -0,0 +1,580 @@
+// Generated by `0x6b73746b gen-ast src/ast` from `nodes.grammar`, nodes are changed there.
+use super::expression::{Expr, Variable};
+use super::node_id::NodeId;
+use crate::lexical_analysis::Token;
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Block(Block),
+    Break(Break),
+    Class(Class),
+    Continue(Continue),
+    Expression(Expression),
+    Function(Function),
+    If(If),
+    Import(Import),
+    Print(Print),
+    Return(Return),
+    Throw(Throw),
+    Try(Try),
+    Var(Var),
+    While(While),
+}
+
+impl Stmt {
+    pub fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> R {
+        match self {
+            Stmt::Block(stmt) => visitor.visit_block_stmt(stmt),
+            Stmt::Break(stmt) => visitor.visit_break_stmt(stmt),
+            Stmt::Class(stmt) => visitor.visit_class_stmt(stmt),
+            Stmt::Continue(stmt) => visitor.visit_continue_stmt(stmt),
+            Stmt::Expression(stmt) => visitor.visit_expression_stmt(stmt),
+            Stmt::Function(stmt) => visitor.visit_function_stmt(stmt),
+            Stmt::If(stmt) => visitor.visit_if_stmt(stmt),
+            Stmt::Import(stmt) => visitor.visit_import_stmt(stmt),
+            Stmt::Print(stmt) => visitor.visit_print_stmt(stmt),
+            Stmt::Return(stmt) => visitor.visit_return_stmt(stmt),
+            Stmt::Throw(stmt) => visitor.visit_throw_stmt(stmt),
+            Stmt::Try(stmt) => visitor.visit_try_stmt(stmt),
+            Stmt::Var(stmt) => visitor.visit_var_stmt(stmt),
+            Stmt::While(stmt) => visitor.visit_while_stmt(stmt),
+        }
+    }
+
+    // characters of the source covered by the tokens of the node, none if it has no tokens
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Stmt::Block(stmt) => stmt.span(),
+            Stmt::Break(stmt) => stmt.span(),
+            Stmt::Class(stmt) => stmt.span(),
+            Stmt::Continue(stmt) => stmt.span(),
+            Stmt::Expression(stmt) => stmt.span(),
+            Stmt::Function(stmt) => stmt.span(),
+            Stmt::If(stmt) => stmt.span(),
+            Stmt::Import(stmt) => stmt.span(),
+            Stmt::Print(stmt) => stmt.span(),
+            Stmt::Return(stmt) => stmt.span(),
+            Stmt::Throw(stmt) => stmt.span(),
+            Stmt::Try(stmt) => stmt.span(),
+            Stmt::Var(stmt) => stmt.span(),
+            Stmt::While(stmt) => stmt.span(),
+        }
+    }
+
+    // identity of the node, which side tables of the passes are keyed by
+    pub fn id(&self) -> NodeId {
+        match self {
+            Stmt::Block(stmt) => stmt.id,
+            Stmt::Break(stmt) => stmt.id,
+            Stmt::Class(stmt) => stmt.id,
+            Stmt::Continue(stmt) => stmt.id,
+            Stmt::Expression(stmt) => stmt.id,
+            Stmt::Function(stmt) => stmt.id,
+            Stmt::If(stmt) => stmt.id,
+            Stmt::Import(stmt) => stmt.id,
+            Stmt::Print(stmt) => stmt.id,
+            Stmt::Return(stmt) => stmt.id,
+            Stmt::Throw(stmt) => stmt.id,
+            Stmt::Try(stmt) => stmt.id,
+            Stmt::Var(stmt) => stmt.id,
+            Stmt::While(stmt) => stmt.id,
+        }
+    }
+}
+
+pub trait Visitor<R> {
+    fn visit_block_stmt(&mut self, stmt: &Block) -> R;
+    fn visit_break_stmt(&mut self, stmt: &Break) -> R;
+    fn visit_class_stmt(&mut self, stmt: &Class) -> R;
+    fn visit_continue_stmt(&mut self, stmt: &Continue) -> R;
+    fn visit_expression_stmt(&mut self, stmt: &Expression) -> R;
+    fn visit_function_stmt(&mut self, stmt: &Function) -> R;
+    fn visit_if_stmt(&mut self, stmt: &If) -> R;
+    fn visit_import_stmt(&mut self, stmt: &Import) -> R;
+    fn visit_print_stmt(&mut self, stmt: &Print) -> R;
+    fn visit_return_stmt(&mut self, stmt: &Return) -> R;
+    fn visit_throw_stmt(&mut self, stmt: &Throw) -> R;
+    fn visit_try_stmt(&mut self, stmt: &Try) -> R;
+    fn visit_var_stmt(&mut self, stmt: &Var) -> R;
+    fn visit_while_stmt(&mut self, stmt: &While) -> R;
+}
+
+// Visits every node below the one it's given. Passes override the methods of the nodes they're
+// interested in, and call the function of the same name to go on with the children.
+pub trait Walker: super::expression::Walker {
+    fn walk_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn walk_block_stmt(&mut self, stmt: &Block) {
+        walk_block_stmt(self, stmt);
+    }
+
+    fn walk_break_stmt(&mut self, stmt: &Break) {
+        walk_break_stmt(self, stmt);
+    }
+
+    fn walk_class_stmt(&mut self, stmt: &Class) {
+        walk_class_stmt(self, stmt);
+    }
+
+    fn walk_continue_stmt(&mut self, stmt: &Continue) {
+        walk_continue_stmt(self, stmt);
+    }
+
+    fn walk_expression_stmt(&mut self, stmt: &Expression) {
+        walk_expression_stmt(self, stmt);
+    }
+
+    fn walk_function_stmt(&mut self, stmt: &Function) {
+        walk_function_stmt(self, stmt);
+    }
+
+    fn walk_if_stmt(&mut self, stmt: &If) {
+        walk_if_stmt(self, stmt);
+    }
+
+    fn walk_import_stmt(&mut self, stmt: &Import) {
+        walk_import_stmt(self, stmt);
+    }
+
+    fn walk_print_stmt(&mut self, stmt: &Print) {
+        walk_print_stmt(self, stmt);
+    }
+
+    fn walk_return_stmt(&mut self, stmt: &Return) {
+        walk_return_stmt(self, stmt);
+    }
+
+    fn walk_throw_stmt(&mut self, stmt: &Throw) {
+        walk_throw_stmt(self, stmt);
+    }
+
+    fn walk_try_stmt(&mut self, stmt: &Try) {
+        walk_try_stmt(self, stmt);
+    }
+
+    fn walk_var_stmt(&mut self, stmt: &Var) {
+        walk_var_stmt(self, stmt);
+    }
+
+    fn walk_while_stmt(&mut self, stmt: &While) {
+        walk_while_stmt(self, stmt);
+    }
+}
+
+pub fn walk_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Stmt) {
+    match stmt {
+        Stmt::Block(stmt) => walker.walk_block_stmt(stmt),
+        Stmt::Break(stmt) => walker.walk_break_stmt(stmt),
+        Stmt::Class(stmt) => walker.walk_class_stmt(stmt),
+        Stmt::Continue(stmt) => walker.walk_continue_stmt(stmt),
+        Stmt::Expression(stmt) => walker.walk_expression_stmt(stmt),
+        Stmt::Function(stmt) => walker.walk_function_stmt(stmt),
+        Stmt::If(stmt) => walker.walk_if_stmt(stmt),
+        Stmt::Import(stmt) => walker.walk_import_stmt(stmt),
+        Stmt::Print(stmt) => walker.walk_print_stmt(stmt),
+        Stmt::Return(stmt) => walker.walk_return_stmt(stmt),
+        Stmt::Throw(stmt) => walker.walk_throw_stmt(stmt),
+        Stmt::Try(stmt) => walker.walk_try_stmt(stmt),
+        Stmt::Var(stmt) => walker.walk_var_stmt(stmt),
+        Stmt::While(stmt) => walker.walk_while_stmt(stmt),
+    }
+}
+
+pub fn walk_block_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Block) {
+    for item in stmt.statements.iter() {
+        walker.walk_stmt(item);
+    }
+}
+
+pub fn walk_break_stmt<W: Walker + ?Sized>(_walker: &mut W, _stmt: &Break) {}
+
+pub fn walk_class_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Class) {
+    if let Some(superclass) = &stmt.superclass {
+        walker.walk_variable_expr(superclass);
+    }
+    for item in stmt.methods.iter() {
+        walker.walk_function_stmt(item);
+    }
+}
+
+pub fn walk_continue_stmt<W: Walker + ?Sized>(_walker: &mut W, _stmt: &Continue) {}
+
+pub fn walk_expression_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Expression) {
+    walker.walk_expr(&stmt.expression);
+}
+
+pub fn walk_function_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Function) {
+    for item in stmt.body.iter() {
+        walker.walk_stmt(item);
+    }
+}
+
+pub fn walk_if_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &If) {
+    walker.walk_expr(&stmt.condition);
+    walker.walk_stmt(&stmt.then_branch);
+    if let Some(else_branch) = &stmt.else_branch {
+        walker.walk_stmt(else_branch);
+    }
+}
+
+pub fn walk_import_stmt<W: Walker + ?Sized>(_walker: &mut W, _stmt: &Import) {}
+
+pub fn walk_print_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Print) {
+    walker.walk_expr(&stmt.expression);
+}
+
+pub fn walk_return_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Return) {
+    if let Some(value) = &stmt.value {
+        walker.walk_expr(value);
+    }
+}
+
+pub fn walk_throw_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Throw) {
+    walker.walk_expr(&stmt.value);
+}
+
+pub fn walk_try_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Try) {
+    for item in stmt.body.iter() {
+        walker.walk_stmt(item);
+    }
+    for item in stmt.catch_body.iter() {
+        walker.walk_stmt(item);
+    }
+    if let Some(finally_body) = &stmt.finally_body {
+        for item in finally_body.iter() {
+            walker.walk_stmt(item);
+        }
+    }
+}
+
+pub fn walk_var_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &Var) {
+    if let Some(initializer) = &stmt.initializer {
+        walker.walk_expr(initializer);
+    }
+}
+
+pub fn walk_while_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &While) {
+    walker.walk_expr(&stmt.condition);
+    walker.walk_stmt(&stmt.body);
+    if let Some(increment) = &stmt.increment {
+        walker.walk_expr(increment);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub id: NodeId,
+    pub brace: Token,
+    pub statements: Vec<Stmt>,
+    pub closing_brace: Token,
+}
+
+impl Block {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.brace.span()));
+        for item in self.statements.iter() {
+            cover(&mut span, item.span());
+        }
+        cover(&mut span, Some(self.closing_brace.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Break {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub semicolon: Token,
+}
+
+impl Break {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub name: Token,
+    pub superclass: Option<Variable>,
+    pub methods: Vec<Function>,
+    pub closing_brace: Token,
+}
+
+impl Class {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, Some(self.name.span()));
+        if let Some(superclass) = &self.superclass {
+            cover(&mut span, superclass.span());
+        }
+        for item in self.methods.iter() {
+            cover(&mut span, item.span());
+        }
+        cover(&mut span, Some(self.closing_brace.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Continue {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub semicolon: Token,
+}
+
+impl Continue {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub id: NodeId,
+    pub expression: Expr,
+    pub semicolon: Token,
+}
+
+impl Expression {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, self.expression.span());
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub id: NodeId,
+    pub keyword: Option<Token>,
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Rc<Vec<Stmt>>,
+    pub closing_brace: Token,
+}
+
+impl Function {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        if let Some(keyword) = &self.keyword {
+            cover(&mut span, Some(keyword.span()));
+        }
+        cover(&mut span, Some(self.name.span()));
+        for item in self.params.iter() {
+            cover(&mut span, Some(item.span()));
+        }
+        for item in self.body.iter() {
+            cover(&mut span, item.span());
+        }
+        cover(&mut span, Some(self.closing_brace.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub condition: Expr,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+impl If {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, self.condition.span());
+        cover(&mut span, self.then_branch.span());
+        if let Some(else_branch) = &self.else_branch {
+            cover(&mut span, else_branch.span());
+        }
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub path: Token,
+    pub name: Option<Token>,
+    pub names: Vec<Token>,
+    pub semicolon: Token,
+}
+
+impl Import {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, Some(self.path.span()));
+        if let Some(name) = &self.name {
+            cover(&mut span, Some(name.span()));
+        }
+        for item in self.names.iter() {
+            cover(&mut span, Some(item.span()));
+        }
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Print {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub expression: Expr,
+    pub semicolon: Token,
+}
+
+impl Print {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, self.expression.span());
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Return {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub value: Option<Expr>,
+    pub semicolon: Token,
+}
+
+impl Return {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        if let Some(value) = &self.value {
+            cover(&mut span, value.span());
+        }
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throw {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub value: Expr,
+    pub semicolon: Token,
+}
+
+impl Throw {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, self.value.span());
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Try {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub body: Vec<Stmt>,
+    pub catch_name: Option<Token>,
+    pub catch_body: Vec<Stmt>,
+    pub finally_body: Option<Rc<Vec<Stmt>>>,
+    pub closing_brace: Token,
+}
+
+impl Try {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        for item in self.body.iter() {
+            cover(&mut span, item.span());
+        }
+        if let Some(catch_name) = &self.catch_name {
+            cover(&mut span, Some(catch_name.span()));
+        }
+        for item in self.catch_body.iter() {
+            cover(&mut span, item.span());
+        }
+        if let Some(finally_body) = &self.finally_body {
+            for item in finally_body.iter() {
+                cover(&mut span, item.span());
+            }
+        }
+        cover(&mut span, Some(self.closing_brace.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Var {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub name: Token,
+    pub initializer: Option<Expr>,
+    pub semicolon: Token,
+}
+
+impl Var {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, Some(self.name.span()));
+        if let Some(initializer) = &self.initializer {
+            cover(&mut span, initializer.span());
+        }
+        cover(&mut span, Some(self.semicolon.span()));
+        span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct While {
+    pub id: NodeId,
+    pub keyword: Token,
+    pub condition: Expr,
+    pub body: Box<Stmt>,
+    pub increment: Option<Expr>,
+}
+
+impl While {
+    pub fn span(&self) -> Option<Range<usize>> {
+        let mut span = None;
+        cover(&mut span, Some(self.keyword.span()));
+        cover(&mut span, self.condition.span());
+        cover(&mut span, self.body.span());
+        if let Some(increment) = &self.increment {
+            cover(&mut span, increment.span());
+        }
+        span
+    }
+}
+
+// widens a span to cover the range
+fn cover(span: &mut Option<Range<usize>>, range: Option<Range<usize>>) {
+    if let Some(range) = range {
+        *span = Some(match span.take() {
+            Some(span) => span.start.min(range.start)..span.end.max(range.end),
+            None => range,
+        });
+    }
+}
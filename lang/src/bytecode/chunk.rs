@@ -0,0 +1,179 @@
+use crate::runtime::value::Value;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetUpvalue,
+    SetUpvalue,
+    GetProperty,
+    SetProperty,
+    GetSuper,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    Invoke,
+    SuperInvoke,
+    Closure,
+    CloseUpvalue,
+    Return,
+    Class,
+    Inherit,
+    Method,
+    BuildList,
+    BuildMap,
+    GetIndex,
+    SetIndex,
+    Throw,
+    Rethrow,
+    PushHandler,
+    PopHandler,
+    Import,
+}
+
+impl OpCode {
+    // order has to match the declaration order, because an opcode is encoded as its discriminant
+    const ALL: [OpCode; 49] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::GetProperty,
+        OpCode::SetProperty,
+        OpCode::GetSuper,
+        OpCode::Equal,
+        OpCode::NotEqual,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Invoke,
+        OpCode::SuperInvoke,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+        OpCode::Class,
+        OpCode::Inherit,
+        OpCode::Method,
+        OpCode::BuildList,
+        OpCode::BuildMap,
+        OpCode::GetIndex,
+        OpCode::SetIndex,
+        OpCode::Throw,
+        OpCode::Rethrow,
+        OpCode::PushHandler,
+        OpCode::PopHandler,
+        OpCode::Import,
+    ];
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        OpCode::ALL.get(byte as usize).copied().ok_or(byte)
+    }
+}
+
+// sequence of instructions with their operands, a constant pool and a run-length encoded line table
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    // (line, number of consecutive bytes produced from that line)
+    pub lines: Vec<(usize, usize)>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self {
+            code: Vec::new(),
+            constants: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn write(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+
+        match self.lines.last_mut() {
+            Some((last_line, count)) if *last_line == line => *count += 1,
+            _ => self.lines.push((line, 1)),
+        }
+    }
+
+    // reuses an existing entry for equal strings and numbers to keep the pool small
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        if matches!(value, Value::String(_) | Value::Number(_)) {
+            if let Some(index) = self.constants.iter().position(|constant| {
+                constant == &value
+                    // 0 and -0 are equal numbers, but they're different constants
+                    && !matches!((constant, &value), (Value::Number(a), Value::Number(b)) if a.to_bits() != b.to_bits())
+            }) {
+                return index;
+            }
+        }
+
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    pub fn get_line(&self, offset: usize) -> usize {
+        let mut start = 0;
+        for (line, count) in &self.lines {
+            start += count;
+            if offset < start {
+                return *line;
+            }
+        }
+
+        self.lines.last().map_or(0, |(line, _)| *line)
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,935 @@
+use std::rc::Rc;
+
+use super::chunk::{Chunk, OpCode};
+use super::object::Prototype;
+use crate::ast::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, List, Literal, Logical, Map,
+    Set, Super, This, Unary, Variable,
+};
+use crate::ast::statement::{
+    self, Block, Break, Class, Continue, Expression, Function, If, Import, Print, Return, Stmt,
+    Throw, Try, Var, While,
+};
+use crate::lexical_analysis::TokenType;
+use crate::runtime::value::Value;
+use crate::symbol::Symbol;
+
+// slots, upvalues and constants are indexed by two byte operands
+const MAX_LOCALS: usize = 65536;
+const MAX_UPVALUES: usize = 65536;
+const MAX_CONSTANTS: usize = 65536;
+// element and entry counts of collection literals are two byte operands as well
+const MAX_ELEMENTS: usize = 65535;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    Function,
+    Initializer,
+    Method,
+    Script,
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool,
+}
+
+pub struct UpvalueDescriptor {
+    pub index: u16,
+    pub is_local: bool,
+}
+
+// loop being compiled; `break` and `continue` emit forward jumps which are patched once the end of
+// the loop body is known
+struct Loop {
+    scope_depth: usize,
+    // try statements around the loop, the ones inside it are left by `break` and `continue`
+    tries: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// try or catch block whose handler is active; `break`, `continue` and `return` leaving it pop the
+// handler and run a copy of the finally block on the way out
+struct TryBlock {
+    finally_body: Option<Rc<Vec<Stmt>>>,
+    // loops around the try statement, the finally block can't jump to the ones inside it
+    loops: usize,
+}
+
+struct FunctionState {
+    function: Prototype,
+    function_type: FunctionType,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueDescriptor>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<TryBlock>,
+}
+
+impl FunctionState {
+    fn new(name: String, function_type: FunctionType) -> Self {
+        // slot 0 holds the called closure, or the receiver in methods
+        let slot_zero = match function_type {
+            FunctionType::Method | FunctionType::Initializer => "this",
+            FunctionType::Function | FunctionType::Script => "",
+        };
+
+        Self {
+            function: Prototype::new(name),
+            function_type,
+            locals: Vec::from([Local {
+                name: slot_zero.to_string(),
+                depth: 0,
+                is_captured: false,
+            }]),
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+}
+
+// single pass over a resolved AST which emits bytecode; semantic errors are already reported by the resolver,
+// so the compiler only checks limits of the bytecode format
+pub struct Compiler {
+    states: Vec<FunctionState>,
+    line: usize,
+    pub had_error: bool,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            states: Vec::new(),
+            line: 0,
+            had_error: false,
+        }
+    }
+
+    // the script returns the value of its last statement if that's an expression, otherwise nil
+    pub fn compile(&mut self, statements: &[Stmt]) -> Option<Rc<Prototype>> {
+        self.states
+            .push(FunctionState::new(String::new(), FunctionType::Script));
+
+        let (body, result) = match statements.split_last() {
+            Some((Stmt::Expression(last), body)) => (body, Some(&last.expression)),
+            _ => (statements, None),
+        };
+
+        for statement in body {
+            statement.accept(self);
+        }
+
+        match result {
+            Some(expression) => {
+                expression.accept(self);
+                self.emit_op(OpCode::Return);
+            }
+            None => self.emit_return(),
+        }
+
+        let (function, _) = self.end_function();
+
+        if self.had_error {
+            return None;
+        }
+
+        Some(Rc::new(function))
+    }
+
+    fn current(&mut self) -> &mut FunctionState {
+        self.states
+            .last_mut()
+            .expect("No function is being compiled.")
+    }
+
+    // the resolver rejects `break` and `continue` outside of loops
+    fn innermost_loop(&mut self) -> &mut Loop {
+        self.current()
+            .loops
+            .last_mut()
+            .expect("No loop is being compiled.")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.current().function.chunk
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let line = self.line;
+        self.chunk().write(byte, line);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_op_with_operand(&mut self, op: OpCode, operand: u16) {
+        self.emit_op(op);
+        self.emit_short(operand);
+    }
+
+    fn emit_short(&mut self, short: u16) {
+        let [high, low] = short.to_be_bytes();
+        self.emit_byte(high);
+        self.emit_byte(low);
+    }
+
+    fn emit_return(&mut self) {
+        if self.current().function_type == FunctionType::Initializer {
+            self.emit_op_with_operand(OpCode::GetLocal, 0);
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+
+        self.emit_op(OpCode::Return);
+    }
+
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        // -2 to adjust for the jump offset itself
+        let jump = self.chunk().code.len() - offset - 2;
+
+        if jump > u16::MAX as usize {
+            self.error("Too much code to jump over.");
+        }
+
+        let code = &mut self.chunk().code;
+        code[offset] = ((jump >> 8) & 0xff) as u8;
+        code[offset + 1] = (jump & 0xff) as u8;
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        self.emit_op(OpCode::Loop);
+
+        let offset = self.chunk().code.len() - loop_start + 2;
+        if offset > u16::MAX as usize {
+            self.error("Loop body too large.");
+        }
+
+        self.emit_byte(((offset >> 8) & 0xff) as u8);
+        self.emit_byte((offset & 0xff) as u8);
+    }
+
+    fn make_constant(&mut self, value: Value) -> u16 {
+        let index = self.chunk().add_constant(value);
+
+        if index >= MAX_CONSTANTS {
+            self.error("Too many constants in one chunk.");
+            return 0;
+        }
+
+        index as u16
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let constant = self.make_constant(value);
+        self.emit_op_with_operand(OpCode::Constant, constant);
+    }
+
+    fn identifier_constant(&mut self, name: &str) -> u16 {
+        self.make_constant(Value::String(Symbol::from(name)))
+    }
+
+    fn end_function(&mut self) -> (Prototype, Vec<UpvalueDescriptor>) {
+        let state = self.states.pop().expect("No function is being compiled.");
+        let mut function = state.function;
+        function.upvalue_count = state.upvalues.len();
+
+        (function, state.upvalues)
+    }
+
+    fn begin_scope(&mut self) {
+        self.current().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.current().scope_depth -= 1;
+
+        loop {
+            let state = self.current();
+            let is_captured = match state.locals.last() {
+                Some(local) if local.depth > state.scope_depth => local.is_captured,
+                _ => break,
+            };
+            state.locals.pop();
+
+            if is_captured {
+                self.emit_op(OpCode::CloseUpvalue);
+            } else {
+                self.emit_op(OpCode::Pop);
+            }
+        }
+    }
+
+    // pops locals of the scopes a jump leaves, without forgetting them since code after the jump still
+    // belongs to those scopes
+    fn discard_locals(&mut self, depth: usize) {
+        let ops: Vec<OpCode> = self
+            .current()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| {
+                if local.is_captured {
+                    OpCode::CloseUpvalue
+                } else {
+                    OpCode::Pop
+                }
+            })
+            .collect();
+
+        for op in ops {
+            self.emit_op(op);
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        for statement in statements {
+            statement.accept(self);
+        }
+        self.end_scope();
+    }
+
+    fn begin_try(&mut self, finally_body: Option<Rc<Vec<Stmt>>>) -> usize {
+        let handler = self.emit_jump(OpCode::PushHandler);
+        let loops = self.current().loops.len();
+        self.current().tries.push(TryBlock {
+            finally_body,
+            loops,
+        });
+        handler
+    }
+
+    fn end_try(&mut self) {
+        self.current().tries.pop();
+        self.emit_op(OpCode::PopHandler);
+    }
+
+    // leaves try blocks from the innermost one down to `count`, before jumping out of them
+    fn exit_tries(&mut self, count: usize) {
+        for index in (count..self.current().tries.len()).rev() {
+            self.emit_op(OpCode::PopHandler);
+
+            let finally_body = self.current().tries[index].finally_body.clone();
+            if let Some(finally_body) = finally_body {
+                // the copy is compiled as if it followed the try statement
+                let loops = self.current().tries[index].loops;
+                let inner_tries = self.current().tries.split_off(index);
+                let inner_loops = self.current().loops.split_off(loops);
+
+                self.block(&finally_body);
+
+                self.current().tries.extend(inner_tries);
+                self.current().loops.extend(inner_loops);
+            }
+        }
+    }
+
+    // handler code of a finally block which runs it and raises the error again; the error value and
+    // its line are on top of the stack
+    fn finally_handler(&mut self, finally_body: &[Stmt]) {
+        self.begin_scope();
+        self.add_local(" error");
+        self.add_local(" line");
+        self.block(finally_body);
+        self.emit_op(OpCode::Rethrow);
+
+        // the stack is unwound by the VM, so the locals are dropped without emitting pops
+        let state = self.current();
+        state.scope_depth -= 1;
+        state.locals.truncate(state.locals.len() - 2);
+    }
+
+    fn add_local(&mut self, name: &str) {
+        if self.current().locals.len() == MAX_LOCALS {
+            self.error("Too many local variables in function.");
+            return;
+        }
+
+        let state = self.current();
+        let depth = state.scope_depth;
+        state.locals.push(Local {
+            name: name.to_string(),
+            depth,
+            is_captured: false,
+        });
+    }
+
+    fn is_local_scope(&mut self) -> bool {
+        self.current().scope_depth > 0
+    }
+
+    fn define_global(&mut self, name: &str) {
+        let global = self.identifier_constant(name);
+        self.emit_op_with_operand(OpCode::DefineGlobal, global);
+    }
+
+    // defines a variable holding the value on top of the stack
+    fn define_variable(&mut self, name: &str) {
+        if self.is_local_scope() {
+            self.add_local(name);
+        } else {
+            self.define_global(name);
+        }
+    }
+
+    fn resolve_local(&self, state_index: usize, name: &str) -> Option<u16> {
+        self.states[state_index]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u16)
+    }
+
+    fn resolve_upvalue(&mut self, state_index: usize, name: &str) -> Option<u16> {
+        if state_index == 0 {
+            return None;
+        }
+
+        if let Some(local) = self.resolve_local(state_index - 1, name) {
+            self.states[state_index - 1].locals[local as usize].is_captured = true;
+            return Some(self.add_upvalue(state_index, local, true));
+        }
+
+        let upvalue = self.resolve_upvalue(state_index - 1, name)?;
+        Some(self.add_upvalue(state_index, upvalue, false))
+    }
+
+    fn add_upvalue(&mut self, state_index: usize, index: u16, is_local: bool) -> u16 {
+        let upvalues = &self.states[state_index].upvalues;
+
+        if let Some(existing) = upvalues
+            .iter()
+            .position(|upvalue| upvalue.index == index && upvalue.is_local == is_local)
+        {
+            return existing as u16;
+        }
+
+        if upvalues.len() == MAX_UPVALUES {
+            self.error("Too many closure variables in function.");
+            return 0;
+        }
+
+        let upvalues = &mut self.states[state_index].upvalues;
+        upvalues.push(UpvalueDescriptor { index, is_local });
+        (upvalues.len() - 1) as u16
+    }
+
+    // emits a read of a variable, or a write of the value on top of the stack when `assign` is set
+    fn named_variable(&mut self, name: &str, assign: bool) {
+        let state_index = self.states.len() - 1;
+
+        let (get_op, set_op, operand) = if let Some(slot) = self.resolve_local(state_index, name) {
+            (OpCode::GetLocal, OpCode::SetLocal, slot)
+        } else if let Some(upvalue) = self.resolve_upvalue(state_index, name) {
+            (OpCode::GetUpvalue, OpCode::SetUpvalue, upvalue)
+        } else {
+            let global = self.identifier_constant(name);
+            (OpCode::GetGlobal, OpCode::SetGlobal, global)
+        };
+
+        self.emit_op_with_operand(if assign { set_op } else { get_op }, operand);
+    }
+
+    fn function(&mut self, declaration: &Function, function_type: FunctionType) {
+        self.line = declaration.name.line;
+        self.states.push(FunctionState::new(
+            declaration.name.lexeme.to_string(),
+            function_type,
+        ));
+        self.begin_scope();
+
+        self.current().function.arity = declaration.params.len();
+        for param in &declaration.params {
+            self.add_local(&param.lexeme);
+        }
+
+        for statement in declaration.body.iter() {
+            statement.accept(self);
+        }
+        self.emit_return();
+
+        let (function, upvalues) = self.end_function();
+        let constant = self.make_constant(Value::Prototype(Rc::new(function)));
+        self.emit_op_with_operand(OpCode::Closure, constant);
+
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_short(upvalue.index);
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Expr]) -> u8 {
+        for argument in arguments {
+            argument.accept(self);
+        }
+
+        // the parser rejects calls with more than 255 arguments
+        arguments.len() as u8
+    }
+
+    fn error(&mut self, message: &str) {
+        self.had_error = true;
+        crate::report(self.line, "".to_string(), message.to_string());
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl statement::Visitor<()> for Compiler {
+    fn visit_block_stmt(&mut self, stmt: &Block) {
+        self.block(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &Break) {
+        self.line = stmt.keyword.line;
+        let tries = self.innermost_loop().tries;
+        self.exit_tries(tries);
+        let depth = self.innermost_loop().scope_depth;
+        self.discard_locals(depth);
+
+        let jump = self.emit_jump(OpCode::Jump);
+        self.innermost_loop().breaks.push(jump);
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &Continue) {
+        self.line = stmt.keyword.line;
+        let tries = self.innermost_loop().tries;
+        self.exit_tries(tries);
+        let depth = self.innermost_loop().scope_depth;
+        self.discard_locals(depth);
+
+        let jump = self.emit_jump(OpCode::Jump);
+        self.innermost_loop().continues.push(jump);
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Class) {
+        self.line = stmt.name.line;
+        let name = self.identifier_constant(&stmt.name.lexeme);
+
+        if self.is_local_scope() {
+            self.add_local(&stmt.name.lexeme);
+        }
+
+        self.emit_op_with_operand(OpCode::Class, name);
+
+        if !self.is_local_scope() {
+            self.define_global(&stmt.name.lexeme);
+        }
+
+        if let Some(superclass) = &stmt.superclass {
+            self.line = superclass.name.line;
+            self.named_variable(&superclass.name.lexeme, false);
+
+            // the superclass stays on the stack as a hidden local captured by methods that use `super`
+            self.begin_scope();
+            self.add_local("super");
+
+            self.named_variable(&stmt.name.lexeme, false);
+            self.emit_op(OpCode::Inherit);
+        }
+
+        self.named_variable(&stmt.name.lexeme, false);
+
+        for method in &stmt.methods {
+            let name = self.identifier_constant(&method.name.lexeme);
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+
+            self.function(method, function_type);
+            self.emit_op_with_operand(OpCode::Method, name);
+        }
+
+        self.emit_op(OpCode::Pop);
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) {
+        stmt.expression.accept(self);
+        self.emit_op(OpCode::Pop);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Function) {
+        if self.is_local_scope() {
+            // declared before the body is compiled, so the function can refer to itself
+            self.add_local(&stmt.name.lexeme);
+            self.function(stmt, FunctionType::Function);
+        } else {
+            self.function(stmt, FunctionType::Function);
+            self.define_global(&stmt.name.lexeme);
+        }
+    }
+
+    // `from` imports every name separately, importing a module again only looks it up
+    fn visit_import_stmt(&mut self, stmt: &Import) {
+        self.line = stmt.keyword.line;
+        let path = self.make_constant(Value::String(stmt.path.literal.clone()));
+
+        if let Some(name) = &stmt.name {
+            self.emit_op_with_operand(OpCode::Import, path);
+            self.define_variable(&name.lexeme);
+            return;
+        }
+
+        for name in &stmt.names {
+            self.emit_op_with_operand(OpCode::Import, path);
+            let property = self.identifier_constant(&name.lexeme);
+            self.emit_op_with_operand(OpCode::GetProperty, property);
+            self.define_variable(&name.lexeme);
+        }
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) {
+        stmt.condition.accept(self);
+
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        stmt.then_branch.accept(self);
+
+        let else_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(then_jump);
+        self.emit_op(OpCode::Pop);
+
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+        self.patch_jump(else_jump);
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) {
+        stmt.expression.accept(self);
+        self.emit_op(OpCode::Print);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) {
+        self.line = stmt.keyword.line;
+
+        if self.current().tries.is_empty() {
+            match &stmt.value {
+                Some(value) => {
+                    value.accept(self);
+                    self.emit_op(OpCode::Return);
+                }
+                None => self.emit_return(),
+            }
+            return;
+        }
+
+        // the returned value is kept in a hidden local while finally blocks run
+        match &stmt.value {
+            Some(value) => value.accept(self),
+            None if self.current().function_type == FunctionType::Initializer => {
+                self.emit_op_with_operand(OpCode::GetLocal, 0)
+            }
+            None => self.emit_op(OpCode::Nil),
+        }
+        self.add_local(" return");
+        self.exit_tries(0);
+        self.line = stmt.keyword.line;
+        self.emit_op(OpCode::Return);
+        self.current().locals.pop();
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) {
+        stmt.value.accept(self);
+        self.line = stmt.keyword.line;
+        self.emit_op(OpCode::Throw);
+    }
+
+    // PushHandler try body PopHandler Jump(end) [catch or finally handler] end: finally body;
+    // errors in the catch block are handled by a copy of the finally block which raises them again
+    fn visit_try_stmt(&mut self, stmt: &Try) {
+        self.line = stmt.keyword.line;
+        let handler = self.begin_try(stmt.finally_body.clone());
+        self.block(&stmt.body);
+        self.end_try();
+        let end_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(handler);
+
+        match (&stmt.catch_name, &stmt.finally_body) {
+            (Some(name), finally_body) => {
+                self.begin_scope();
+                self.add_local(&name.lexeme);
+                self.add_local(" line");
+
+                let handler = finally_body
+                    .as_ref()
+                    .map(|finally_body| self.begin_try(Some(finally_body.clone())));
+                for statement in &stmt.catch_body {
+                    statement.accept(self);
+                }
+
+                if let (Some(handler), Some(finally_body)) = (handler, finally_body) {
+                    self.end_try();
+                    let catch_end = self.emit_jump(OpCode::Jump);
+                    self.patch_jump(handler);
+                    self.finally_handler(finally_body);
+                    self.patch_jump(catch_end);
+                }
+                self.end_scope();
+            }
+            (None, Some(finally_body)) => self.finally_handler(finally_body),
+            (None, None) => unreachable!("Parser accepted a try statement without handlers."),
+        }
+
+        self.patch_jump(end_jump);
+        if let Some(finally_body) = &stmt.finally_body {
+            self.block(finally_body);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        self.line = stmt.name.line;
+
+        match &stmt.initializer {
+            Some(initializer) => initializer.accept(self),
+            None => self.emit_op(OpCode::Nil),
+        }
+
+        self.define_variable(&stmt.name.lexeme);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) {
+        let loop_start = self.chunk().code.len();
+        stmt.condition.accept(self);
+
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+
+        let scope_depth = self.current().scope_depth;
+        let tries = self.current().tries.len();
+        self.current().loops.push(Loop {
+            scope_depth,
+            tries,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+        stmt.body.accept(self);
+        let state = self
+            .current()
+            .loops
+            .pop()
+            .expect("No loop is being compiled.");
+
+        for jump in state.continues {
+            self.patch_jump(jump);
+        }
+        if let Some(increment) = &stmt.increment {
+            increment.accept(self);
+            self.emit_op(OpCode::Pop);
+        }
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit_op(OpCode::Pop);
+
+        // the condition is already popped when breaking out of the body
+        for jump in state.breaks {
+            self.patch_jump(jump);
+        }
+    }
+}
+
+impl expression::Visitor<()> for Compiler {
+    fn visit_assign_expr(&mut self, expr: &Assign) {
+        expr.value.accept(self);
+        self.line = expr.name.line;
+        self.named_variable(&expr.name.lexeme, true);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+        self.line = expr.operator.line;
+
+        let op = match expr.operator.token_type {
+            TokenType::BangEqual => OpCode::NotEqual,
+            TokenType::EqualEqual => OpCode::Equal,
+            TokenType::Greater => OpCode::Greater,
+            TokenType::GreaterEqual => OpCode::GreaterEqual,
+            TokenType::Less => OpCode::Less,
+            TokenType::LessEqual => OpCode::LessEqual,
+            TokenType::Minus => OpCode::Subtract,
+            TokenType::Plus => OpCode::Add,
+            TokenType::Slash => OpCode::Divide,
+            TokenType::Star => OpCode::Multiply,
+            _ => unreachable!("Parser produced an unknown binary operator."),
+        };
+        self.emit_op(op);
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) {
+        match expr.callee.as_ref() {
+            // method calls skip creating a bound method
+            Expr::Get(get) => {
+                get.object.accept(self);
+                let name = self.identifier_constant(&get.name.lexeme);
+                let count = self.arguments(&expr.arguments);
+                self.line = expr.paren.line;
+                self.emit_op_with_operand(OpCode::Invoke, name);
+                self.emit_byte(count);
+            }
+            Expr::Super(sup) => {
+                self.line = sup.keyword.line;
+                self.named_variable("this", false);
+                let name = self.identifier_constant(&sup.method.lexeme);
+                let count = self.arguments(&expr.arguments);
+                self.named_variable("super", false);
+                self.line = expr.paren.line;
+                self.emit_op_with_operand(OpCode::SuperInvoke, name);
+                self.emit_byte(count);
+            }
+            callee => {
+                callee.accept(self);
+                let count = self.arguments(&expr.arguments);
+                self.line = expr.paren.line;
+                self.emit_op(OpCode::Call);
+                self.emit_byte(count);
+            }
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) {
+        expr.object.accept(self);
+        self.line = expr.name.line;
+        let name = self.identifier_constant(&expr.name.lexeme);
+        self.emit_op_with_operand(OpCode::GetProperty, name);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+        self.line = expr.bracket.line;
+        self.emit_op(OpCode::GetIndex);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+        expr.value.accept(self);
+        self.line = expr.bracket.line;
+        self.emit_op(OpCode::SetIndex);
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) {
+        self.line = expr.bracket.line;
+        if expr.elements.len() > MAX_ELEMENTS {
+            self.error("Too many elements in list literal.");
+            return;
+        }
+
+        for element in &expr.elements {
+            element.accept(self);
+        }
+        self.line = expr.bracket.line;
+        self.emit_op_with_operand(OpCode::BuildList, expr.elements.len() as u16);
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) {
+        match Value::from(&expr.value) {
+            Value::Nil => self.emit_op(OpCode::Nil),
+            Value::Bool(true) => self.emit_op(OpCode::True),
+            Value::Bool(false) => self.emit_op(OpCode::False),
+            value => self.emit_constant(value),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Logical) {
+        expr.left.accept(self);
+        self.line = expr.operator.line;
+
+        if expr.operator.token_type == TokenType::Or {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+            let end_jump = self.emit_jump(OpCode::Jump);
+
+            self.patch_jump(else_jump);
+            self.emit_op(OpCode::Pop);
+            expr.right.accept(self);
+            self.patch_jump(end_jump);
+        } else {
+            let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+
+            self.emit_op(OpCode::Pop);
+            expr.right.accept(self);
+            self.patch_jump(end_jump);
+        }
+    }
+
+    fn visit_map_expr(&mut self, expr: &Map) {
+        self.line = expr.brace.line;
+        if expr.keys.len() > MAX_ELEMENTS {
+            self.error("Too many entries in map literal.");
+            return;
+        }
+
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            key.accept(self);
+            value.accept(self);
+        }
+        self.line = expr.brace.line;
+        self.emit_op_with_operand(OpCode::BuildMap, expr.keys.len() as u16);
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) {
+        expr.object.accept(self);
+        expr.value.accept(self);
+        self.line = expr.name.line;
+        let name = self.identifier_constant(&expr.name.lexeme);
+        self.emit_op_with_operand(OpCode::SetProperty, name);
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) {
+        self.line = expr.keyword.line;
+        let name = self.identifier_constant(&expr.method.lexeme);
+        self.named_variable("this", false);
+        self.named_variable("super", false);
+        self.emit_op_with_operand(OpCode::GetSuper, name);
+    }
+
+    fn visit_this_expr(&mut self, expr: &This) {
+        self.line = expr.keyword.line;
+        self.named_variable("this", false);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) {
+        expr.right.accept(self);
+        self.line = expr.operator.line;
+
+        match expr.operator.token_type {
+            TokenType::Bang => self.emit_op(OpCode::Not),
+            TokenType::Minus => self.emit_op(OpCode::Negate),
+            _ => unreachable!("Parser produced an unknown unary operator."),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) {
+        self.line = expr.name.line;
+        self.named_variable(&expr.name.lexeme, false);
+    }
+}
@@ -0,0 +1,162 @@
+use super::chunk::{Chunk, OpCode};
+use super::object::Prototype;
+use crate::runtime::value::Value;
+
+// prints the compiled form of a script, the bytecode counterpart of AstPrinter
+pub struct Disassembler {}
+
+impl Disassembler {
+    // disassembles the function and, recursively, every function declared in it
+    pub fn disassemble(&self, function: &Prototype) -> String {
+        let mut output = self.disassemble_chunk(&function.chunk, &function.to_string());
+
+        for constant in &function.chunk.constants {
+            if let Value::Prototype(nested) = constant {
+                output += "\n";
+                output += &self.disassemble(nested);
+            }
+        }
+
+        output
+    }
+
+    pub fn disassemble_chunk(&self, chunk: &Chunk, name: &str) -> String {
+        let mut output = format!("== {name} ==\n");
+
+        let mut offset = 0;
+        while offset < chunk.code.len() {
+            let (instruction, next) = self.disassemble_instruction(chunk, offset);
+            output += &instruction;
+            output += "\n";
+            offset = next;
+        }
+
+        output
+    }
+
+    // returns a printed instruction and the offset of the next one
+    pub fn disassemble_instruction(&self, chunk: &Chunk, offset: usize) -> (String, usize) {
+        let line = chunk.get_line(offset);
+        let line_column = if offset > 0 && line == chunk.get_line(offset - 1) {
+            "   |".to_string()
+        } else {
+            format!("{line:4}")
+        };
+        let prefix = format!("{offset:04} {line_column} ");
+
+        let op = match OpCode::try_from(chunk.code[offset]) {
+            Ok(op) => op,
+            Err(byte) => return (format!("{prefix}Unknown opcode {byte}"), offset + 1),
+        };
+        let name = op_name(op);
+
+        let (text, next) = match op {
+            OpCode::Constant
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Class
+            | OpCode::Method
+            | OpCode::Import => {
+                let constant = self.short(chunk, offset + 1);
+                (
+                    format!(
+                        "{name:<16} {constant:4} '{}'",
+                        self.constant(chunk, constant)
+                    ),
+                    offset + 3,
+                )
+            }
+            OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::BuildList
+            | OpCode::BuildMap => {
+                let slot = self.short(chunk, offset + 1);
+                (format!("{name:<16} {slot:4}"), offset + 3)
+            }
+            OpCode::Call => {
+                let count = self.byte(chunk, offset + 1);
+                (format!("{name:<16} {count:4}"), offset + 2)
+            }
+            OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop | OpCode::PushHandler => {
+                let jump = self.short(chunk, offset + 1) as usize;
+                let target = if op == OpCode::Loop {
+                    (offset + 3).wrapping_sub(jump)
+                } else {
+                    offset + 3 + jump
+                };
+                (format!("{name:<16} {offset:4} -> {target}"), offset + 3)
+            }
+            OpCode::Invoke | OpCode::SuperInvoke => {
+                let constant = self.short(chunk, offset + 1);
+                let count = self.byte(chunk, offset + 3);
+                (
+                    format!(
+                        "{name:<16} ({count} args) {constant:4} '{}'",
+                        self.constant(chunk, constant)
+                    ),
+                    offset + 4,
+                )
+            }
+            OpCode::Closure => {
+                let constant = self.short(chunk, offset + 1);
+                let value = chunk.constants.get(constant as usize);
+                let mut text =
+                    format!("{name:<16} {constant:4} {}", self.constant(chunk, constant));
+
+                let mut next = offset + 3;
+                if let Some(Value::Prototype(function)) = value {
+                    for _ in 0..function.upvalue_count {
+                        let kind = if self.byte(chunk, next) == 1 {
+                            "local"
+                        } else {
+                            "upvalue"
+                        };
+                        let index = self.short(chunk, next + 1);
+                        text += &format!("\n{next:04}    |                     {kind} {index}");
+                        next += 3;
+                    }
+                }
+
+                (text, next)
+            }
+            _ => (name, offset + 1),
+        };
+
+        (prefix + &text, next)
+    }
+
+    fn byte(&self, chunk: &Chunk, offset: usize) -> u8 {
+        chunk.code.get(offset).copied().unwrap_or_default()
+    }
+
+    // two byte operands are big endian, like jump offsets
+    fn short(&self, chunk: &Chunk, offset: usize) -> u16 {
+        u16::from_be_bytes([self.byte(chunk, offset), self.byte(chunk, offset + 1)])
+    }
+
+    fn constant(&self, chunk: &Chunk, index: u16) -> String {
+        match chunk.constants.get(index as usize) {
+            Some(value) => value.to_string(),
+            None => "<invalid constant>".to_string(),
+        }
+    }
+}
+
+// OpCode::JumpIfFalse is printed as OP_JUMP_IF_FALSE
+fn op_name(op: OpCode) -> String {
+    let mut name = "OP".to_string();
+    for character in format!("{op:?}").chars() {
+        if character.is_uppercase() {
+            name.push('_');
+        }
+        name.push(character.to_ascii_uppercase());
+    }
+
+    name
+}
@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use super::chunk::Chunk;
+use crate::runtime::heap::{self, Trace};
+use crate::runtime::module::Module;
+use crate::runtime::value::Value;
+
+// compiled function; `name` is empty for the top-level script
+pub struct Prototype {
+    pub name: String,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+impl Prototype {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            arity: 0,
+            upvalue_count: 0,
+            chunk: Chunk::new(),
+        }
+    }
+}
+
+impl fmt::Display for Prototype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "<script>")
+        } else {
+            write!(f, "<fn {}>", self.name)
+        }
+    }
+}
+
+pub struct Closure {
+    pub function: Rc<Prototype>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    // module whose globals the function uses
+    pub module: Rc<Module>,
+}
+
+// a captured variable lives on the stack until its scope ends, then it's moved into the upvalue
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+impl Trace for Closure {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        self.upvalues
+            .iter()
+            .for_each(|upvalue| tracer(heap::address(upvalue)));
+        tracer(heap::address(&self.module));
+    }
+
+    // immutable, any cycle through it is broken by clearing its upvalues
+    fn clear(&self) {}
+}
+
+impl Trace for RefCell<Upvalue> {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        if let Ok(upvalue) = self.try_borrow() {
+            if let Upvalue::Closed(value) = &*upvalue {
+                heap::trace_value(value, tracer);
+            }
+        }
+    }
+
+    // open upvalues point at the stack of a running vm, so they're always reachable
+    fn clear(&self) {
+        if let Ok(mut upvalue) = self.try_borrow_mut() {
+            let value = std::mem::replace(&mut *upvalue, Upvalue::Closed(Value::Nil));
+            drop(upvalue);
+            drop(value);
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::chunk::{Chunk, OpCode};
+use super::object::Prototype;
+use crate::runtime::value::Value;
+use crate::symbol::Symbol;
+
+// Layout of a compiled script, all integers are little endian:
+//   magic "kstk" | format version u16 | top-level function | CRC-32 of everything before it
+// function:
+//   name | arity u8 | upvalue count u16 | code length u32 + code
+//   | line table length u32 + (line u32, byte count u32)* | constant count u32 + constant*
+// constant: tag u8 followed by a payload; nested functions are stored as constants
+// string: length u32 + UTF-8 bytes
+pub const MAGIC: &[u8; 4] = b"kstk";
+pub const FORMAT_VERSION: u16 = 5;
+
+const TAG_NIL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_NUMBER: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_FUNCTION: u8 = 5;
+
+pub enum LoadError {
+    NotBytecode,
+    VersionMismatch { found: u16 },
+    Corrupted(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::NotBytecode => write!(f, "File is not a compiled script."),
+            LoadError::VersionMismatch { found } => write!(
+                f,
+                "Compiled script has format version {found}, but this interpreter supports version {FORMAT_VERSION}. Recompile the script."
+            ),
+            LoadError::Corrupted(reason) => write!(f, "Compiled script is corrupted: {reason}"),
+        }
+    }
+}
+
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn serialize(function: &Prototype) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    write_function(&mut bytes, function);
+
+    let checksum = crc32(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+pub fn deserialize(bytes: &[u8]) -> Result<Rc<Prototype>, LoadError> {
+    if !is_bytecode(bytes) {
+        return Err(LoadError::NotBytecode);
+    }
+
+    let mut reader = Reader {
+        bytes,
+        position: MAGIC.len(),
+    };
+
+    let version = reader.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(LoadError::VersionMismatch { found: version });
+    }
+
+    if bytes.len() < MAGIC.len() + 2 + 4 {
+        return Err(corrupted("unexpected end of file"));
+    }
+
+    let (payload, checksum) = bytes.split_at(bytes.len() - 4);
+    let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    if crc32(payload) != expected {
+        return Err(corrupted("checksum mismatch"));
+    }
+
+    reader.bytes = payload;
+    let function = reader.function()?;
+
+    if reader.position != payload.len() {
+        return Err(corrupted("trailing bytes after the script"));
+    }
+
+    Ok(Rc::new(function))
+}
+
+fn write_function(bytes: &mut Vec<u8>, function: &Prototype) {
+    write_string(bytes, &function.name);
+    bytes.push(function.arity as u8);
+    bytes.extend_from_slice(&(function.upvalue_count as u16).to_le_bytes());
+
+    let chunk = &function.chunk;
+    write_u32(bytes, chunk.code.len());
+    bytes.extend_from_slice(&chunk.code);
+
+    write_u32(bytes, chunk.lines.len());
+    for (line, count) in &chunk.lines {
+        write_u32(bytes, *line);
+        write_u32(bytes, *count);
+    }
+
+    write_u32(bytes, chunk.constants.len());
+    for constant in &chunk.constants {
+        match constant {
+            Value::Nil => bytes.push(TAG_NIL),
+            Value::Bool(false) => bytes.push(TAG_FALSE),
+            Value::Bool(true) => bytes.push(TAG_TRUE),
+            Value::Number(number) => {
+                bytes.push(TAG_NUMBER);
+                bytes.extend_from_slice(&number.to_le_bytes());
+            }
+            Value::String(string) => {
+                bytes.push(TAG_STRING);
+                write_string(bytes, string);
+            }
+            Value::Prototype(nested) => {
+                bytes.push(TAG_FUNCTION);
+                write_function(bytes, nested);
+            }
+            _ => unreachable!("Compiler stored a runtime object in a constant pool."),
+        }
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    write_u32(bytes, string.len());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn corrupted(reason: &str) -> LoadError {
+    LoadError::Corrupted(reason.to_string())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], LoadError> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| corrupted("unexpected end of file"))?;
+
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<usize, LoadError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn f64(&mut self) -> Result<f64, LoadError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> Result<String, LoadError> {
+        let length = self.u32()?;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| corrupted("string is not valid UTF-8"))
+    }
+
+    fn function(&mut self) -> Result<Prototype, LoadError> {
+        let mut function = Prototype::new(self.string()?);
+        function.arity = self.u8()? as usize;
+        function.upvalue_count = self.u16()? as usize;
+
+        let code_length = self.u32()?;
+        let code = self.take(code_length)?.to_vec();
+
+        let line_count = self.u32()?;
+        let mut lines = Vec::new();
+        for _ in 0..line_count {
+            lines.push((self.u32()?, self.u32()?));
+        }
+
+        let constant_count = self.u32()?;
+        let mut constants = Vec::new();
+        for _ in 0..constant_count {
+            let constant = match self.u8()? {
+                TAG_NIL => Value::Nil,
+                TAG_FALSE => Value::Bool(false),
+                TAG_TRUE => Value::Bool(true),
+                TAG_NUMBER => Value::Number(self.f64()?),
+                TAG_STRING => Value::String(Symbol::from(self.string()?)),
+                TAG_FUNCTION => Value::Prototype(Rc::new(self.function()?)),
+                tag => return Err(corrupted(&format!("unknown constant tag {tag}"))),
+            };
+            constants.push(constant);
+        }
+
+        function.chunk = Chunk {
+            code,
+            constants,
+            lines,
+        };
+        verify(&function)?;
+
+        Ok(function)
+    }
+}
+
+// checks that instructions are well formed, their operands are in bounds and every path through
+// the code keeps the stack in the frame of the function
+fn verify(function: &Prototype) -> Result<(), LoadError> {
+    let chunk = &function.chunk;
+    let name = function.to_string();
+    let error = |offset: usize, reason: &str| corrupted(&format!("{reason} at {name}:{offset:04}"));
+
+    if chunk.lines.iter().map(|(_, count)| count).sum::<usize>() != chunk.code.len() {
+        return Err(corrupted(&format!(
+            "line table doesn't match code in {name}"
+        )));
+    }
+
+    if chunk.code.last() != Some(&(OpCode::Return as u8)) {
+        return Err(corrupted(&format!("{name} doesn't end with a return")));
+    }
+
+    let constant = |offset: usize| -> Result<&Value, LoadError> {
+        short(&chunk.code, offset)
+            .and_then(|index| chunk.constants.get(index))
+            .ok_or_else(|| error(offset, "constant index out of range"))
+    };
+
+    // opcode and length of the instruction starting at each offset
+    let mut instructions = vec![None; chunk.code.len()];
+
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let op =
+            OpCode::try_from(chunk.code[offset]).map_err(|_| error(offset, "unknown opcode"))?;
+
+        let length = match op {
+            OpCode::Constant => {
+                constant(offset + 1)?;
+                3
+            }
+            OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Class
+            | OpCode::Method
+            | OpCode::Import => {
+                if !matches!(constant(offset + 1)?, Value::String(_)) {
+                    return Err(error(offset, "name operand isn't a string"));
+                }
+                3
+            }
+            OpCode::GetUpvalue | OpCode::SetUpvalue => {
+                match short(&chunk.code, offset + 1) {
+                    Some(index) if index < function.upvalue_count => {}
+                    _ => return Err(error(offset, "upvalue index out of range")),
+                }
+                3
+            }
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::BuildList | OpCode::BuildMap => 3,
+            OpCode::Call => 2,
+            OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop | OpCode::PushHandler => {
+                let jump = match short(&chunk.code, offset + 1) {
+                    Some(jump) => jump,
+                    None => return Err(error(offset, "truncated jump")),
+                };
+                let in_range = if op == OpCode::Loop {
+                    jump <= offset + 3
+                } else {
+                    offset + 3 + jump <= chunk.code.len()
+                };
+                if !in_range {
+                    return Err(error(offset, "jump out of range"));
+                }
+                3
+            }
+            OpCode::Invoke | OpCode::SuperInvoke => {
+                if !matches!(constant(offset + 1)?, Value::String(_)) {
+                    return Err(error(offset, "name operand isn't a string"));
+                }
+                4
+            }
+            OpCode::Closure => match constant(offset + 1)? {
+                Value::Prototype(nested) => {
+                    // captured locals are checked against the stack height below
+                    for upvalue in 0..nested.upvalue_count {
+                        let pair = offset + 3 + upvalue * 3;
+                        let is_local = chunk.code.get(pair) == Some(&1);
+                        match short(&chunk.code, pair + 1) {
+                            Some(index) if is_local || index < function.upvalue_count => {}
+                            _ => return Err(error(offset, "upvalue index out of range")),
+                        }
+                    }
+                    3 + nested.upvalue_count * 3
+                }
+                _ => return Err(error(offset, "closure operand isn't a function")),
+            },
+            _ => 1,
+        };
+
+        instructions[offset] = Some((op, length));
+        offset += length;
+    }
+
+    if offset != chunk.code.len() {
+        return Err(corrupted(&format!("truncated instruction in {name}")));
+    }
+
+    verify_stack(function, &instructions).map_err(|(offset, reason)| error(offset, reason))
+}
+
+// values on the stack of a frame, starting with the callee and its arguments, and handlers pushed
+// by the function before an instruction
+#[derive(Clone, Copy, PartialEq)]
+struct FrameState {
+    height: usize,
+    handlers: usize,
+}
+
+// follows every path from the start of the function; paths which meet have to agree on the state,
+// so the stack height at each instruction is known and can't run below the frame
+fn verify_stack(
+    function: &Prototype,
+    instructions: &[Option<(OpCode, usize)>],
+) -> Result<(), (usize, &'static str)> {
+    let code = &function.chunk.code;
+    let mut states = vec![None; code.len()];
+    let mut pending = Vec::new();
+
+    let start = FrameState {
+        height: 1 + function.arity,
+        handlers: 0,
+    };
+    enter(instructions, &mut states, &mut pending, 0, start).map_err(|reason| (0, reason))?;
+
+    while let Some(offset) = pending.pop() {
+        let Some((op, length)) = instructions[offset] else {
+            unreachable!("Only instruction starts are entered.");
+        };
+        let state = states[offset].expect("Entered instructions have a state.");
+        let operand = || short(code, offset + 1).unwrap_or_default();
+        let next = offset + length;
+
+        // values an instruction reads from the top of the stack and values it leaves there
+        let (popped, pushed) = match op {
+            OpCode::Constant
+            | OpCode::Nil
+            | OpCode::True
+            | OpCode::False
+            | OpCode::GetLocal
+            | OpCode::GetGlobal
+            | OpCode::GetUpvalue
+            | OpCode::Closure
+            | OpCode::Class
+            | OpCode::Import => (0, 1),
+            OpCode::Pop | OpCode::DefineGlobal | OpCode::Print | OpCode::CloseUpvalue => (1, 0),
+            OpCode::Return | OpCode::Throw => (1, 0),
+            OpCode::Rethrow => (2, 0),
+            OpCode::SetLocal
+            | OpCode::SetGlobal
+            | OpCode::SetUpvalue
+            | OpCode::GetProperty
+            | OpCode::JumpIfFalse
+            | OpCode::Not
+            | OpCode::Negate => (1, 1),
+            OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Equal
+            | OpCode::NotEqual
+            | OpCode::Greater
+            | OpCode::GreaterEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide
+            | OpCode::GetIndex
+            | OpCode::Inherit
+            | OpCode::Method => (2, 1),
+            OpCode::SetIndex => (3, 1),
+            OpCode::Jump | OpCode::Loop | OpCode::PushHandler | OpCode::PopHandler => (0, 0),
+            OpCode::Call => (code[offset + 1] as usize + 1, 1),
+            OpCode::Invoke => (code[offset + 3] as usize + 1, 1),
+            // the superclass is on top of the receiver and the arguments
+            OpCode::SuperInvoke => (code[offset + 3] as usize + 2, 1),
+            OpCode::BuildList => (operand(), 1),
+            OpCode::BuildMap => (operand() * 2, 1),
+        };
+
+        if state.height < popped {
+            return Err((offset, "stack underflow"));
+        }
+        let mut after = FrameState {
+            height: state.height - popped + pushed,
+            handlers: state.handlers,
+        };
+
+        match op {
+            OpCode::GetLocal | OpCode::SetLocal if operand() >= state.height => {
+                return Err((offset, "local slot out of range"));
+            }
+            OpCode::Closure => {
+                let upvalue_count = (length - 3) / 3;
+                for upvalue in 0..upvalue_count {
+                    let pair = offset + 3 + upvalue * 3;
+                    let is_local = code[pair] == 1;
+                    // a local function captures itself from the slot its closure is pushed to
+                    if is_local && short(code, pair + 1).unwrap_or_default() > state.height {
+                        return Err((offset, "local slot out of range"));
+                    }
+                }
+            }
+            OpCode::PushHandler => {
+                after.handlers += 1;
+                // a caught error unwinds to the height of the handler and pushes itself and its line
+                let handler = FrameState {
+                    height: state.height + 2,
+                    handlers: state.handlers,
+                };
+                enter(
+                    instructions,
+                    &mut states,
+                    &mut pending,
+                    next + operand(),
+                    handler,
+                )
+                .map_err(|reason| (offset, reason))?;
+            }
+            OpCode::PopHandler if state.handlers == 0 => {
+                return Err((offset, "handler popped without being pushed"));
+            }
+            OpCode::PopHandler => after.handlers -= 1,
+            OpCode::Return if state.handlers != 0 => {
+                return Err((offset, "return with a handler left"));
+            }
+            _ => {}
+        }
+
+        let targets = match op {
+            OpCode::Return | OpCode::Throw | OpCode::Rethrow => vec![],
+            OpCode::Jump => vec![next + operand()],
+            OpCode::Loop => vec![next - operand()],
+            OpCode::JumpIfFalse => vec![next, next + operand()],
+            _ => vec![next],
+        };
+        for target in targets {
+            if target == code.len() {
+                return Err((offset, "execution runs past the end"));
+            }
+            enter(instructions, &mut states, &mut pending, target, after)
+                .map_err(|reason| (offset, reason))?;
+        }
+    }
+
+    Ok(())
+}
+
+// records the state of the path reaching `target`, which is followed later if it's new
+fn enter(
+    instructions: &[Option<(OpCode, usize)>],
+    states: &mut [Option<FrameState>],
+    pending: &mut Vec<usize>,
+    target: usize,
+    state: FrameState,
+) -> Result<(), &'static str> {
+    if instructions.get(target).copied().flatten().is_none() {
+        return Err("jump into the middle of an instruction");
+    }
+    match states[target] {
+        None => {
+            states[target] = Some(state);
+            pending.push(target);
+            Ok(())
+        }
+        Some(existing) if existing == state => Ok(()),
+        Some(_) => Err("paths disagree on the stack height"),
+    }
+}
+
+// operands other than call argument counts and upvalue kinds are two bytes, big endian
+fn short(code: &[u8], offset: usize) -> Option<usize> {
+    match (code.get(offset), code.get(offset + 1)) {
+        (Some(high), Some(low)) => Some(u16::from_be_bytes([*high, *low]) as usize),
+        _ => None,
+    }
+}
+
+// CRC-32 (IEEE 802.3), computed bit by bit since scripts are small
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,923 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use super::chunk::OpCode;
+use super::compiler::Compiler;
+use super::disassembler::Disassembler;
+use super::object::{Closure, Prototype, Upvalue};
+use crate::runtime::collection::{self, Entries, List, Map};
+use crate::runtime::error::{ErrorKind, RuntimeError, StackFrame};
+use crate::runtime::heap::Heap;
+use crate::runtime::module::{self, Module, ModuleLoader};
+use crate::runtime::native::{Native, NativeContext, Random};
+use crate::runtime::object::{BoundMethod, Class, Instance};
+use crate::runtime::sandbox::{self, Capability, Sandbox};
+use crate::runtime::value::{self, Value};
+use crate::symbol::Symbol;
+
+// catch or finally block which handles errors raised until its PopHandler is executed
+struct Handler {
+    frames: usize,
+    stack: usize,
+    ip: usize,
+}
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    // index of the stack slot holding the callee, locals of the function start there
+    slots: usize,
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    handlers: Vec<Handler>,
+    // module of the main script, the REPL keeps its globals between lines
+    module: Rc<Module>,
+    pub modules: ModuleLoader,
+    // sorted by stack slot, so closing upvalues only touches the tail
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    // receives every executed instruction together with the value stack, kept apart from `output`
+    pub trace: Option<Box<dyn Write>>,
+    pub heap: Heap,
+    // looked up on every instantiation, so it's interned once
+    init_string: Symbol,
+    random: Random,
+    // limits, including the number of calls below the script's own frame
+    pub sandbox: Sandbox,
+    // receives the output of `print`
+    pub output: Box<dyn Write>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let mut heap = Heap::new();
+        let module = Module::new(Symbol::from("script"), None, &mut heap);
+        let module = heap.allocate(module);
+
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            handlers: Vec::new(),
+            module,
+            modules: ModuleLoader::default(),
+            open_upvalues: Vec::new(),
+            trace: None,
+            heap,
+            init_string: Symbol::from("init"),
+            random: Random::new(),
+            sandbox: Sandbox::default(),
+            output: Box::new(io::stdout()),
+        }
+    }
+
+    // the script's result is the value of its last statement if that's an expression, otherwise nil
+    pub fn interpret(&mut self, function: Rc<Prototype>) -> Result<Value, RuntimeError> {
+        self.sandbox.start(&mut self.heap);
+        let closure = self.heap.allocate(Closure {
+            function,
+            upvalues: Vec::new(),
+            module: self.module.clone(),
+        });
+        self.stack.push(Value::Closure(closure.clone()));
+
+        let result = self
+            .call(closure, 0)
+            .and_then(|_| self.run(0))
+            .map(|()| self.pop());
+        if result.is_err() {
+            self.reset_stack();
+        }
+
+        result
+    }
+
+    // calls a function value on behalf of the host application, errors have no line of a call
+    pub fn call_function(
+        &mut self,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        self.sandbox.start(&mut self.heap);
+        let count = arguments.len();
+        self.push(callee.clone());
+        self.stack.extend(arguments);
+
+        // natives and classes without an initializer return without pushing a frame
+        let mut result = self
+            .call_value(callee, count)
+            .and_then(|()| match self.frames.is_empty() {
+                true => Ok(()),
+                false => self.run(0),
+            })
+            .map(|()| self.pop());
+        if let Err(error) = &mut result {
+            if error.frames.is_empty() {
+                error.frames = self.stack_trace();
+            }
+            self.reset_stack();
+        }
+
+        result
+    }
+
+    // globals of the main script
+    pub fn module(&self) -> &Rc<Module> {
+        &self.module
+    }
+
+    fn reset_stack(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.handlers.clear();
+        self.open_upvalues.clear();
+    }
+
+    // runs until the frame above `depth` returns, leaving its result on the stack; an imported
+    // module runs nested in the importing frame
+    fn run(&mut self, depth: usize) -> Result<(), RuntimeError> {
+        loop {
+            match self.execute(depth) {
+                Ok(()) => return Ok(()),
+                Err(error) => self.catch(error, depth)?,
+            }
+        }
+    }
+
+    // unwinds to the innermost handler, which gets the error value and its line on the stack;
+    // handlers of the frames below `depth` are left to the run which is nested in them, and a
+    // script stopped by the sandbox isn't handled at all
+    fn catch(&mut self, mut error: RuntimeError, depth: usize) -> Result<(), RuntimeError> {
+        if error.frames.is_empty() {
+            error.frames = self.stack_trace();
+        }
+        if error.kind.is_fatal() {
+            return Err(error);
+        }
+
+        let handler = match self.handlers.last() {
+            Some(handler) if handler.frames > depth => self.handlers.pop().unwrap(),
+            _ => return Err(error),
+        };
+
+        self.frames.truncate(handler.frames);
+        self.close_upvalues(handler.stack);
+        self.stack.truncate(handler.stack);
+
+        let line = error.line;
+        self.push(error.into_value());
+        self.push(Value::Number(line as f64));
+        self.frame_mut().ip = handler.ip;
+        Ok(())
+    }
+
+    fn execute(&mut self, depth: usize) -> Result<(), RuntimeError> {
+        loop {
+            if self.trace.is_some() {
+                self.trace_instruction();
+            }
+
+            let instruction = self.read_byte();
+            if let Err(kind) = self.sandbox.step(&mut self.heap) {
+                return Err(sandbox::exceeded(kind, self.line()));
+            }
+            let op = OpCode::try_from(instruction)
+                .map_err(|byte| self.runtime_error(format!("Unknown opcode {byte}.")))?;
+
+            match op {
+                OpCode::Constant => {
+                    let constant = self.read_constant();
+                    self.push(constant);
+                }
+                OpCode::Nil => self.push(Value::Nil),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = self.read_short() as usize;
+                    let base = self.frame().slots;
+                    self.push(self.stack[base + slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = self.read_short() as usize;
+                    let base = self.frame().slots;
+                    self.stack[base + slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = self.read_string();
+                    match self.frame().closure.module.get(&name) {
+                        Some(value) => self.push(value),
+                        None => {
+                            return Err(self.runtime_error(format!("Undefined variable '{name}'.")))
+                        }
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = self.read_string();
+                    let value = self.pop();
+                    let globals = &self.frame().closure.module.globals;
+                    globals.borrow_mut().define(&name, value);
+                }
+                OpCode::SetGlobal => {
+                    let name = self.read_string();
+                    let value = self.peek(0).clone();
+                    let globals = &self.frame().closure.module.globals;
+                    if !globals.borrow_mut().set(&name, value) {
+                        return Err(self.runtime_error(format!("Undefined variable '{name}'.")));
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let index = self.read_short() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = self.read_short() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = self.peek(0).clone();
+                    let mut upvalue = upvalue.borrow_mut();
+                    match &mut *upvalue {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    };
+                }
+                OpCode::GetProperty => {
+                    let name = self.read_string();
+                    let instance = match self.peek(0) {
+                        Value::Instance(instance) => instance.clone(),
+                        Value::List(_) | Value::Map(_) | Value::Cursor(_) => {
+                            self.bind_collection_method(&name)?;
+                            continue;
+                        }
+                        Value::Foreign(foreign) => {
+                            let foreign = foreign.clone();
+                            match foreign.get(&name) {
+                                Some(value) => {
+                                    self.pop();
+                                    self.push(value);
+                                }
+                                None => self.bind_native_method(foreign.method(&name), &name)?,
+                            }
+                            continue;
+                        }
+                        Value::Module(module) => {
+                            let value = module.get(&name).ok_or_else(|| {
+                                self.runtime_error(format!("Undefined property '{name}'."))
+                            })?;
+                            self.pop();
+                            self.push(value);
+                            continue;
+                        }
+                        Value::Exception(exception) => {
+                            let value = exception.property(&name).ok_or_else(|| {
+                                self.runtime_error(format!("Undefined property '{name}'."))
+                            })?;
+                            self.pop();
+                            self.push(value);
+                            continue;
+                        }
+                        object => {
+                            let message = collection::no_property(object, &name);
+                            return Err(self.runtime_error(message));
+                        }
+                    };
+
+                    let field = instance.fields.borrow().get(&name).cloned();
+                    match field {
+                        Some(value) => {
+                            self.pop();
+                            self.push(value);
+                        }
+                        None => self.bind_method(&instance.class, &name)?,
+                    }
+                }
+                OpCode::SetProperty => {
+                    let name = self.read_string();
+                    let instance = match self.peek(1) {
+                        Value::Instance(instance) => instance.clone(),
+                        Value::Foreign(foreign) => {
+                            let foreign = foreign.clone();
+                            let value = self.pop();
+                            foreign
+                                .set(&name, value.clone())
+                                .map_err(|message| self.runtime_error(message))?;
+                            self.pop();
+                            self.push(value);
+                            continue;
+                        }
+                        _ => return Err(self.runtime_error("Only instances have fields.")),
+                    };
+
+                    let value = self.pop();
+                    instance.fields.borrow_mut().insert(name, value.clone());
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::GetSuper => {
+                    let name = self.read_string();
+                    match self.pop() {
+                        Value::Class(superclass) => self.bind_method(&superclass, &name)?,
+                        // the compiler always puts a class here, a hand-made script may not
+                        _ => return Err(self.runtime_error("Superclass must be a class.")),
+                    }
+                }
+                OpCode::Equal => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.push(Value::Bool(a == b));
+                }
+                OpCode::NotEqual => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.push(Value::Bool(a != b));
+                }
+                OpCode::Greater => self.binary_op(|a, b| value::compare(a, b, f64::gt))?,
+                OpCode::GreaterEqual => self.binary_op(|a, b| value::compare(a, b, f64::ge))?,
+                OpCode::Less => self.binary_op(|a, b| value::compare(a, b, f64::lt))?,
+                OpCode::LessEqual => self.binary_op(|a, b| value::compare(a, b, f64::le))?,
+                OpCode::Add => {
+                    self.charge(value::concatenation_size(self.peek(1), self.peek(0)))?;
+                    self.binary_op(value::add)?
+                }
+                OpCode::Subtract => self.binary_op(|a, b| value::arithmetic(a, b, |a, b| a - b))?,
+                OpCode::Multiply => self.binary_op(|a, b| value::arithmetic(a, b, |a, b| a * b))?,
+                OpCode::Divide => self.binary_op(|a, b| value::arithmetic(a, b, |a, b| a / b))?,
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Value::Bool(!value.is_truthy()));
+                }
+                OpCode::Negate => {
+                    let result = value::negate(self.peek(0))
+                        .map_err(|message| self.runtime_error(message))?;
+                    self.pop();
+                    self.push(result);
+                }
+                OpCode::Print => {
+                    let value = self.pop();
+                    writeln!(self.output, "{value}")
+                        .map_err(|_| self.runtime_error("Can't write output."))?;
+                }
+                OpCode::Jump => {
+                    let offset = self.read_short() as usize;
+                    self.frame_mut().ip += offset;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = self.read_short() as usize;
+                    if !self.peek(0).is_truthy() {
+                        self.frame_mut().ip += offset;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = self.read_short() as usize;
+                    self.frame_mut().ip -= offset;
+                }
+                OpCode::Call => {
+                    let count = self.read_byte() as usize;
+                    self.call_value(self.peek(count).clone(), count)?;
+                }
+                OpCode::Invoke => {
+                    let name = self.read_string();
+                    let count = self.read_byte() as usize;
+                    self.invoke(&name, count)?;
+                }
+                OpCode::SuperInvoke => {
+                    let name = self.read_string();
+                    let count = self.read_byte() as usize;
+                    match self.pop() {
+                        Value::Class(superclass) => {
+                            self.invoke_from_class(&superclass, &name, count)?
+                        }
+                        _ => return Err(self.runtime_error("Superclass must be a class.")),
+                    }
+                }
+                OpCode::Closure => {
+                    let function = match self.read_constant() {
+                        Value::Prototype(function) => function,
+                        _ => unreachable!("Closure operand is not a function."),
+                    };
+
+                    let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                    for _ in 0..function.upvalue_count {
+                        let is_local = self.read_byte() == 1;
+                        let index = self.read_short() as usize;
+
+                        if is_local {
+                            let slot = self.frame().slots + index;
+                            upvalues.push(self.capture_upvalue(slot));
+                        } else {
+                            upvalues.push(self.frame().closure.upvalues[index].clone());
+                        }
+                    }
+
+                    let closure = self.heap.allocate(Closure {
+                        function,
+                        upvalues,
+                        module: self.frame().closure.module.clone(),
+                    });
+                    self.push(Value::Closure(closure));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("Returned without a call frame.");
+                    self.close_upvalues(frame.slots);
+
+                    self.stack.truncate(frame.slots);
+                    self.push(result);
+
+                    if self.frames.len() == depth {
+                        return Ok(());
+                    }
+                }
+                OpCode::Class => {
+                    let name = self.read_string();
+                    let class = self.heap.allocate(Class::new(name, None));
+                    self.push(Value::Class(class));
+                }
+                OpCode::Inherit => {
+                    let superclass = match self.peek(1) {
+                        Value::Class(superclass) => superclass.clone(),
+                        _ => return Err(self.runtime_error("Superclass must be a class.")),
+                    };
+
+                    if let Value::Class(subclass) = self.peek(0) {
+                        // copy-down inheritance: methods defined later in the subclass override these
+                        subclass
+                            .methods
+                            .borrow_mut()
+                            .extend(superclass.methods.borrow().clone());
+                    }
+                    self.pop();
+                }
+                OpCode::Method => {
+                    let name = self.read_string();
+                    let method = self.pop();
+                    if let Value::Class(class) = self.peek(0) {
+                        class.methods.borrow_mut().insert(name, method);
+                    }
+                }
+                OpCode::BuildList => {
+                    let count = self.read_short() as usize;
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    let list = self.heap.allocate(List::new(elements));
+                    self.push(Value::List(list));
+                }
+                OpCode::BuildMap => {
+                    let count = self.read_short() as usize;
+                    let values = self.stack.split_off(self.stack.len() - count * 2);
+
+                    let mut entries = Entries::default();
+                    let mut values = values.into_iter();
+                    while let (Some(key), Some(value)) = (values.next(), values.next()) {
+                        entries
+                            .insert(key, value)
+                            .map_err(|message| self.runtime_error(message))?;
+                    }
+
+                    let map = self.heap.allocate(Map::new(entries));
+                    self.push(Value::Map(map));
+                }
+                OpCode::GetIndex => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = collection::get_index(&object, &index)
+                        .map_err(|message| self.runtime_error(message))?;
+                    self.push(value);
+                }
+                OpCode::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+                    self.charge(collection::growth(&object, &index, &value))?;
+                    collection::set_index(&object, &index, value.clone())
+                        .map_err(|message| self.runtime_error(message))?;
+                    self.push(value);
+                }
+                OpCode::Throw => {
+                    let value = self.pop();
+                    return Err(RuntimeError::thrown(value, self.line()));
+                }
+                OpCode::Rethrow => {
+                    let line = match self.pop() {
+                        Value::Number(line) => line as usize,
+                        _ => return Err(self.runtime_error("Rethrown error without a line.")),
+                    };
+                    let value = self.pop();
+                    return Err(RuntimeError::thrown(value, line));
+                }
+                OpCode::PushHandler => {
+                    let offset = self.read_short() as usize;
+                    self.handlers.push(Handler {
+                        frames: self.frames.len(),
+                        stack: self.stack.len(),
+                        ip: self.frame().ip + offset,
+                    });
+                }
+                OpCode::PopHandler => {
+                    self.handlers.pop();
+                }
+                OpCode::Import => {
+                    let path = self.read_string();
+                    let module = self.import(&path)?;
+                    self.push(Value::Module(module));
+                }
+            }
+        }
+    }
+
+    fn trace_instruction(&mut self) {
+        let mut stack = " ".repeat(10);
+        for value in &self.stack {
+            stack += &format!("[ {value} ]");
+        }
+
+        let frame = self.frame();
+        let (instruction, _) =
+            Disassembler {}.disassemble_instruction(&frame.closure.function.chunk, frame.ip);
+
+        if let Some(trace) = &mut self.trace {
+            // a failing trace writer mustn't stop the script
+            let _ = writeln!(trace, "{stack}\n{instruction}");
+        }
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("No active call frame.")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("No active call frame.")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_short(&mut self) -> u16 {
+        let high = self.read_byte() as u16;
+        let low = self.read_byte() as u16;
+        (high << 8) | low
+    }
+
+    fn read_constant(&mut self) -> Value {
+        let index = self.read_short() as usize;
+        self.frame().closure.function.chunk.constants[index].clone()
+    }
+
+    fn read_string(&mut self) -> Symbol {
+        match self.read_constant() {
+            Value::String(name) => name,
+            _ => unreachable!("Name operand is not a string constant."),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("Value stack underflow.")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn binary_op(
+        &mut self,
+        operation: impl Fn(&Value, &Value) -> Result<Value, &'static str>,
+    ) -> Result<(), RuntimeError> {
+        let result =
+            operation(self.peek(1), self.peek(0)).map_err(|message| self.runtime_error(message))?;
+        self.pop();
+        self.pop();
+        self.push(result);
+        Ok(())
+    }
+
+    fn call_value(&mut self, callee: Value, count: usize) -> Result<(), RuntimeError> {
+        match callee {
+            Value::Closure(closure) => self.call(closure, count),
+            Value::BoundMethod(bound) => {
+                let receiver_slot = self.stack.len() - count - 1;
+                self.stack[receiver_slot] = bound.receiver.clone();
+                match &bound.method {
+                    Value::Native(native) => self.call_native(native, count, true),
+                    method => self.call_value(method.clone(), count),
+                }
+            }
+            Value::Class(class) => {
+                let receiver_slot = self.stack.len() - count - 1;
+                self.stack[receiver_slot] =
+                    Value::Instance(self.heap.allocate(Instance::new(class.clone())));
+
+                match class.find_method(&self.init_string) {
+                    Some(Value::Closure(initializer)) => self.call(initializer, count),
+                    _ if count != 0 => {
+                        Err(self.runtime_error(format!("Expected 0 arguments but got {count}.")))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Value::Native(native) => self.call_native(&native, count, false),
+            _ => Err(self.runtime_error("Can only call functions and classes.")),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, count: usize) -> Result<(), RuntimeError> {
+        if count != closure.function.arity {
+            return Err(self.runtime_error(format!(
+                "Expected {} arguments but got {count}.",
+                closure.function.arity
+            )));
+        }
+
+        if self.frames.len() > self.sandbox.limits.max_depth {
+            return Err(self
+                .runtime_error("Stack overflow.")
+                .with_kind(ErrorKind::StackOverflow));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            slots: self.stack.len() - count - 1,
+        });
+        Ok(())
+    }
+
+    // arguments are the top `count` values; a method also gets the receiver from the slot below them
+    fn call_native(
+        &mut self,
+        native: &Native,
+        count: usize,
+        is_method: bool,
+    ) -> Result<(), RuntimeError> {
+        self.sandbox.allow(native.capability, self.line())?;
+        let start = self.stack.len() - count - is_method as usize;
+        let arguments = self.stack.split_off(start);
+
+        let line = self.line();
+        let mut context = NativeContext::new(&mut self.heap, &mut self.random);
+        let result = if is_method {
+            native.call_method(&mut context, &arguments)
+        } else {
+            native.call(&mut context, &arguments)
+        }
+        .map_err(|message| context.error(message, line))?;
+
+        if !is_method {
+            // the callee
+            self.pop();
+        }
+        self.push(result);
+        Ok(())
+    }
+
+    fn invoke(&mut self, name: &Symbol, count: usize) -> Result<(), RuntimeError> {
+        let instance = match self.peek(count) {
+            Value::Instance(instance) => instance.clone(),
+            receiver @ (Value::List(_) | Value::Map(_) | Value::Cursor(_)) => {
+                return match collection::method(receiver, name) {
+                    Some(method) => self.call_native(&method, count, true),
+                    None => Err(self.runtime_error(format!("Undefined property '{name}'."))),
+                };
+            }
+            Value::Foreign(foreign) => {
+                let foreign = foreign.clone();
+                if let Some(value) = foreign.get(name) {
+                    let receiver_slot = self.stack.len() - count - 1;
+                    self.stack[receiver_slot] = value.clone();
+                    return self.call_value(value, count);
+                }
+
+                return match foreign.method(name) {
+                    Some(method) => self.call_native(&method, count, true),
+                    None => Err(self.runtime_error(format!("Undefined property '{name}'."))),
+                };
+            }
+            Value::Module(module) => {
+                let value = module
+                    .get(name)
+                    .ok_or_else(|| self.runtime_error(format!("Undefined property '{name}'.")))?;
+                let receiver_slot = self.stack.len() - count - 1;
+                self.stack[receiver_slot] = value.clone();
+                return self.call_value(value, count);
+            }
+            Value::Exception(exception) => {
+                let value = exception
+                    .property(name)
+                    .ok_or_else(|| self.runtime_error(format!("Undefined property '{name}'.")))?;
+                let receiver_slot = self.stack.len() - count - 1;
+                self.stack[receiver_slot] = value.clone();
+                return self.call_value(value, count);
+            }
+            object => {
+                let message = collection::no_property(object, name);
+                return Err(self.runtime_error(message));
+            }
+        };
+
+        let field = instance.fields.borrow().get(name).cloned();
+        if let Some(value) = field {
+            let receiver_slot = self.stack.len() - count - 1;
+            self.stack[receiver_slot] = value.clone();
+            return self.call_value(value, count);
+        }
+
+        self.invoke_from_class(&instance.class, name, count)
+    }
+
+    // compiles and runs a module on its first import, in a frame nested in the importing one
+    fn import(&mut self, path: &str) -> Result<Rc<Module>, RuntimeError> {
+        let line = self.line();
+        self.sandbox.allow(Some(Capability::Files), line)?;
+        let source = match self.modules.load(path, line)? {
+            module::Import::Loaded(module) => return Ok(module),
+            module::Import::Source(source) => source,
+        };
+
+        let function = match Compiler::new().compile(&source.script.statements) {
+            Some(function) => function,
+            None => {
+                self.modules.finish(None);
+                return Err(RuntimeError::new(
+                    format!("Can't compile module '{path}'."),
+                    line,
+                ));
+            }
+        };
+
+        let module = Module::new(source.name, Some(source.file), &mut self.heap);
+        let module = self.heap.allocate(module);
+        let closure = self.heap.allocate(Closure {
+            function,
+            upvalues: Vec::new(),
+            module: module.clone(),
+        });
+
+        let depth = self.frames.len();
+        self.push(Value::Closure(closure.clone()));
+        let result = self.call(closure, 0).and_then(|_| self.run(depth));
+        self.modules.finish(result.is_ok().then(|| module.clone()));
+
+        result.map(|()| {
+            self.pop();
+            module
+        })
+    }
+
+    fn invoke_from_class(
+        &mut self,
+        class: &Class,
+        name: &Symbol,
+        count: usize,
+    ) -> Result<(), RuntimeError> {
+        match class.find_method(name) {
+            Some(method) => self.call_value(method, count),
+            None => Err(self.runtime_error(format!("Undefined property '{name}'."))),
+        }
+    }
+
+    // replaces the instance on top of the stack with its method bound to it
+    fn bind_method(&mut self, class: &Class, name: &Symbol) -> Result<(), RuntimeError> {
+        let method = match class.find_method(name) {
+            Some(method) => method,
+            None => return Err(self.runtime_error(format!("Undefined property '{name}'."))),
+        };
+
+        let bound = BoundMethod {
+            receiver: self.pop(),
+            method,
+        };
+        let bound = self.heap.allocate(bound);
+        self.push(Value::BoundMethod(bound));
+        Ok(())
+    }
+
+    // replaces the collection on top of the stack with its built-in method bound to it
+    fn bind_collection_method(&mut self, name: &Symbol) -> Result<(), RuntimeError> {
+        self.bind_native_method(collection::method(self.peek(0), name), name)
+    }
+
+    // replaces the receiver on top of the stack with the native method bound to it
+    fn bind_native_method(
+        &mut self,
+        method: Option<Rc<Native>>,
+        name: &Symbol,
+    ) -> Result<(), RuntimeError> {
+        let method = match method {
+            Some(method) => method,
+            None => return Err(self.runtime_error(format!("Undefined property '{name}'."))),
+        };
+
+        let bound = BoundMethod {
+            receiver: self.pop(),
+            method: Value::Native(method),
+        };
+        let bound = self.heap.allocate(bound);
+        self.push(Value::BoundMethod(bound));
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self
+            .open_upvalues
+            .iter()
+            .rposition(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open <= slot));
+
+        if let Some(index) = position {
+            let upvalue = &self.open_upvalues[index];
+            if matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot) {
+                return upvalue.clone();
+            }
+        }
+
+        let upvalue = self.heap.allocate(RefCell::new(Upvalue::Open(slot)));
+        let insert_at = position.map_or(0, |index| index + 1);
+        self.open_upvalues.insert(insert_at, upvalue.clone());
+        upvalue
+    }
+
+    fn close_upvalues(&mut self, last: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let slot = match *upvalue.borrow() {
+                Upvalue::Open(slot) => slot,
+                Upvalue::Closed(_) => unreachable!("Closed upvalue in the open list."),
+            };
+
+            if slot < last {
+                break;
+            }
+
+            let value = self.stack[slot].clone();
+            *upvalue.borrow_mut() = Upvalue::Closed(value);
+            self.open_upvalues.pop();
+        }
+    }
+
+    // reserves heap bytes for a string or collection the instruction is about to create or grow
+    fn charge(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+        self.heap
+            .charge(bytes)
+            .map_err(|kind| sandbox::exceeded(kind, self.line()))
+    }
+
+    fn runtime_error(&self, message: impl Into<String>) -> RuntimeError {
+        RuntimeError::new(message, self.line())
+    }
+
+    // line of the instruction being executed, none while the host calls a native
+    fn line(&self) -> usize {
+        self.frames.last().map_or(0, frame_line)
+    }
+
+    fn stack_trace(&self) -> Vec<StackFrame> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                let function = &frame.closure.function;
+                let module = &frame.closure.module;
+                StackFrame {
+                    // top-level code is named after its module, the main one is the script
+                    function: if function.name.is_empty() {
+                        module.name.clone()
+                    } else {
+                        Symbol::from(function.name.as_str())
+                    },
+                    file: module.file.clone(),
+                    line: frame_line(frame),
+                }
+            })
+            .collect()
+    }
+}
+
+fn frame_line(frame: &CallFrame) -> usize {
+    frame
+        .closure
+        .function
+        .chunk
+        .get_line(frame.ip.saturating_sub(1))
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
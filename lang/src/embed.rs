@@ -0,0 +1,165 @@
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::ast;
+use crate::bytecode::compiler::Compiler;
+use crate::bytecode::vm::Vm;
+use crate::interpreter::Interpreter;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::module::Module;
+use crate::runtime::native::Native;
+use crate::runtime::sandbox::{CancelHandle, Capabilities, Limits, Sandbox};
+use crate::runtime::value::Value;
+use crate::symbol::Symbol;
+use crate::Diagnostic;
+
+#[derive(Debug)]
+pub enum Error {
+    // every error found while lexing, parsing, resolving and compiling, with its line and span
+    Compile(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Compile(errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            Error::Runtime(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Error::Runtime(error)
+    }
+}
+
+enum Backend {
+    Tree(Interpreter),
+    Vm(Vm),
+}
+
+// Interpreter hosted by a Rust application. Globals are kept between evaluations, like in the
+// REPL, so the host can define values and functions for scripts and read back their results.
+pub struct Lox {
+    backend: Backend,
+}
+
+impl Lox {
+    // evaluates scripts with the tree-walk interpreter
+    pub fn new() -> Self {
+        Self {
+            backend: Backend::Tree(Interpreter::new()),
+        }
+    }
+
+    // compiles scripts to bytecode and runs them on the virtual machine
+    pub fn with_vm() -> Self {
+        Self {
+            backend: Backend::Vm(Vm::new()),
+        }
+    }
+
+    // runs a script and returns the value of its last statement if that's an expression, otherwise nil
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let (script, errors) = crate::capture_errors(|| ast::parse(source.to_string()));
+        let script = script.ok_or(Error::Compile(errors))?;
+
+        let result = match &mut self.backend {
+            Backend::Tree(interpreter) => interpreter.interpret(&script),
+            Backend::Vm(vm) => {
+                let (function, errors) =
+                    crate::capture_errors(|| Compiler::new().compile(&script.statements));
+                let function = function.ok_or(Error::Compile(errors))?;
+                vm.interpret(function)
+            }
+        };
+        self.sandbox().finish();
+
+        Ok(result?)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.module().define(&Symbol::from(name), value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.module().get(&Symbol::from(name))
+    }
+
+    // calls a function, class or bound method value returned by a script
+    pub fn call(&mut self, function: &Value, arguments: &[Value]) -> Result<Value, Error> {
+        let arguments = arguments.to_vec();
+
+        let result = match &mut self.backend {
+            Backend::Tree(interpreter) => interpreter.call_value(function.clone(), arguments),
+            Backend::Vm(vm) => vm.call_function(function.clone(), arguments),
+        };
+        self.sandbox().finish();
+
+        Ok(result?)
+    }
+
+    // defines a global function implemented by the host; an error message is raised as a runtime
+    // error at the line of the call
+    pub fn register(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+    ) {
+        let native = Native::new(name, arity, move |_, arguments| function(arguments));
+        self.set_global(name, Value::Native(Rc::new(native)));
+    }
+
+    // sends the output of `print` to `output` instead of stdout
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        match &mut self.backend {
+            Backend::Tree(interpreter) => interpreter.output = Box::new(output),
+            Backend::Vm(vm) => vm.output = Box::new(output),
+        }
+    }
+
+    // limits of every following `eval` and `call`, exceeding one stops the script with an error
+    // of the corresponding kind which the script can't catch
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.sandbox().limits = limits;
+    }
+
+    // natives which need a disabled capability raise an error of the `Denied` kind
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.sandbox().capabilities = capabilities;
+    }
+
+    // stops the script which is running from any thread, or the next one if none is
+    pub fn cancel_handle(&mut self) -> CancelHandle {
+        self.sandbox().cancel_handle()
+    }
+
+    fn sandbox(&mut self) -> &mut Sandbox {
+        match &mut self.backend {
+            Backend::Tree(interpreter) => &mut interpreter.sandbox,
+            Backend::Vm(vm) => &mut vm.sandbox,
+        }
+    }
+
+    fn module(&self) -> &Rc<Module> {
+        match &self.backend {
+            Backend::Tree(interpreter) => interpreter.module(),
+            Backend::Vm(vm) => vm.module(),
+        }
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
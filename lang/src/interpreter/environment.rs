@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::lexical_analysis::Token;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::heap::{self, Trace};
+use crate::runtime::value::Value;
+use crate::symbol::Symbol;
+
+pub struct Environment {
+    values: HashMap<Symbol, Value>,
+    pub enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing,
+        }
+    }
+
+    pub fn define(&mut self, name: &Symbol, value: Value) {
+        self.values.insert(name.clone(), value);
+    }
+
+    // variable of this scope only, globals of a module are looked up by name
+    pub fn lookup(&self, name: &Symbol) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    // assigns a variable of this scope only, false when it isn't defined
+    pub fn set(&mut self, name: &Symbol, value: Value) -> bool {
+        match self.values.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    // the resolver guarantees that the variable exists exactly `distance` scopes away
+    pub fn get_at(environment: &Rc<RefCell<Environment>>, distance: usize, name: &Symbol) -> Value {
+        Self::ancestor(environment, distance)
+            .borrow()
+            .values
+            .get(name)
+            .cloned()
+            .unwrap_or(Value::Nil)
+    }
+
+    pub fn assign_at(
+        environment: &Rc<RefCell<Environment>>,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) {
+        Self::ancestor(environment, distance)
+            .borrow_mut()
+            .define(&name.lexeme, value);
+    }
+
+    fn ancestor(
+        environment: &Rc<RefCell<Environment>>,
+        distance: usize,
+    ) -> Rc<RefCell<Environment>> {
+        let mut environment = environment.clone();
+        for _ in 0..distance {
+            let enclosing = environment
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("Resolved scope is deeper than the environment chain.");
+            environment = enclosing;
+        }
+
+        environment
+    }
+}
+
+fn undefined_variable(name: &Token) -> RuntimeError {
+    RuntimeError::new(format!("Undefined variable '{}'.", name.lexeme), name.line)
+}
+
+impl Trace for RefCell<Environment> {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        if let Ok(environment) = self.try_borrow() {
+            if let Some(enclosing) = &environment.enclosing {
+                tracer(heap::address(enclosing));
+            }
+            environment
+                .values
+                .values()
+                .for_each(|value| heap::trace_value(value, tracer));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut environment) = self.try_borrow_mut() {
+            let values = std::mem::take(&mut environment.values);
+            let enclosing = environment.enclosing.take();
+            drop(environment);
+            drop((values, enclosing));
+        }
+    }
+
+    fn size(&self) -> usize {
+        let environment = self.try_borrow();
+        heap::object_size(
+            environment
+                .iter()
+                .flat_map(|environment| environment.values.values())
+                .map(heap::entry_size),
+        )
+    }
+}
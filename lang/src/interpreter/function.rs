@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::environment::Environment;
+use crate::ast::resolver::Depths;
+use crate::ast::statement;
+use crate::lexical_analysis::Token;
+use crate::runtime::heap::{self, Heap, Trace};
+use crate::runtime::module::Module;
+use crate::runtime::value::Value;
+use crate::symbol::Symbol;
+
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Rc<Vec<statement::Stmt>>,
+    pub closure: Rc<RefCell<Environment>>,
+    // module the function is declared in, its globals are used when the function is called
+    pub module: Rc<Module>,
+    // scope distances of the script the function is declared in
+    pub depths: Rc<Depths>,
+    pub is_initializer: bool,
+}
+
+impl Function {
+    pub fn new(
+        declaration: &statement::Function,
+        closure: Rc<RefCell<Environment>>,
+        module: Rc<Module>,
+        depths: Rc<Depths>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            name: declaration.name.clone(),
+            params: declaration.params.clone(),
+            body: declaration.body.clone(),
+            closure,
+            module,
+            depths,
+            is_initializer,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    // creates a copy of the method whose closure has `this` bound to the instance
+    pub fn bind(&self, instance: Value, heap: &mut Heap) -> Function {
+        let mut environment = Environment::new(Some(self.closure.clone()));
+        environment.define(&Symbol::from("this"), instance);
+
+        Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: self.body.clone(),
+            closure: heap.allocate(RefCell::new(environment)),
+            module: self.module.clone(),
+            depths: self.depths.clone(),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+impl Trace for Function {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        tracer(heap::address(&self.closure));
+        tracer(heap::address(&self.module));
+    }
+
+    // immutable, any cycle through it is broken by clearing its closure
+    fn clear(&self) {}
+}
@@ -0,0 +1,837 @@
+pub mod environment;
+pub mod function;
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::ast::expression::{
+    self, Assign, Binary, Call, Expr, Get, Grouping, Index, IndexSet, Literal, Logical, Set, Super,
+    This, Unary, Variable,
+};
+use crate::ast::node_id::NodeId;
+use crate::ast::resolver::Depths;
+use crate::ast::statement::{
+    self, Block, Break, Class, Continue, Expression, If, Import, Print, Return, Stmt, Throw, Try,
+    Var, While,
+};
+use crate::ast::Script;
+use crate::lexical_analysis::{Token, TokenType};
+use crate::runtime::collection::{self, Entries, List, Map};
+use crate::runtime::error::{ErrorKind, RuntimeError, StackFrame};
+use crate::runtime::heap::Heap;
+use crate::runtime::module::{self, Module, ModuleLoader};
+use crate::runtime::native::{NativeContext, Random};
+use crate::runtime::object::{self, BoundMethod, Instance};
+use crate::runtime::sandbox::{self, Capability, Sandbox};
+use crate::runtime::value::{self, Value};
+use crate::symbol::Symbol;
+use environment::Environment;
+use function::Function;
+
+// native stack an embedded tree-walker may use, well below the 2 MiB of a spawned Rust thread
+pub const DEFAULT_MAX_STACK: usize = 1024 * 1024;
+
+// reasons for leaving a statement early, propagated up through `?`
+pub enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+    Break,
+    Continue,
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+pub struct Interpreter {
+    // module being run, global variables are its globals
+    module: Rc<Module>,
+    environment: Rc<RefCell<Environment>>,
+    // scope distances of the local variables of the script or module the running code is from
+    depths: Rc<Depths>,
+    pub heap: Heap,
+    random: Random,
+    // functions being called, each with the line of its call
+    calls: Vec<StackFrame>,
+    // line of the statement being executed, kept for blocks which have none of their own
+    line: usize,
+    // bytes of native stack the nested calls may use, beyond which they're a stack overflow
+    pub max_stack: usize,
+    // stack position where the host called into the interpreter
+    stack_base: usize,
+    pub sandbox: Sandbox,
+    pub modules: ModuleLoader,
+    // receives the output of `print`
+    pub output: Box<dyn Write>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut heap = Heap::new();
+        let module = Module::new(Symbol::from("script"), None, &mut heap);
+        let module = heap.allocate(module);
+
+        Self {
+            environment: module.globals.clone(),
+            module,
+            depths: Rc::new(Depths::new()),
+            heap,
+            random: Random::new(),
+            calls: Vec::new(),
+            line: 0,
+            max_stack: DEFAULT_MAX_STACK,
+            stack_base: crate::stack_position(),
+            sandbox: Sandbox::default(),
+            modules: ModuleLoader::default(),
+            output: Box::new(io::stdout()),
+        }
+    }
+
+    // the script's result is the value of its last statement if that's an expression, otherwise nil
+    pub fn interpret(&mut self, script: &Script) -> Result<Value, RuntimeError> {
+        self.sandbox.start(&mut self.heap);
+        self.stack_base = crate::stack_position();
+        self.depths = script.depths.clone();
+        let (body, result) = match script.statements.split_last() {
+            Some((Stmt::Expression(last), body)) => (body, Some(&last.expression)),
+            _ => (script.statements.as_slice(), None),
+        };
+
+        for statement in body {
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(Unwind::Error(error)) => return Err(self.with_stack_trace(error)),
+                // the resolver rejects top-level returns and jumps out of loops
+                Err(Unwind::Return(_) | Unwind::Break | Unwind::Continue) => return Ok(Value::Nil),
+            }
+        }
+
+        match result {
+            Some(expression) => self
+                .evaluate(expression)
+                .map_err(|error| self.with_stack_trace(error)),
+            None => Ok(Value::Nil),
+        }
+    }
+
+    // calls a function value on behalf of the host application, errors have no line of a call
+    pub fn call_value(
+        &mut self,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let paren = Token {
+            token_type: TokenType::RightParen,
+            lexeme: Symbol::from(")"),
+            literal: Symbol::default(),
+            numeric_literal: 0.0,
+            line: 0,
+            offset: 0,
+        };
+
+        self.sandbox.start(&mut self.heap);
+        self.stack_base = crate::stack_position();
+        self.call(callee, arguments, &paren)
+            .map_err(|error| self.with_stack_trace(error))
+    }
+
+    // globals of the main script
+    pub fn module(&self) -> &Rc<Module> {
+        &self.module
+    }
+
+    fn execute(&mut self, statement: &Stmt) -> Result<(), Unwind> {
+        if let Some(line) = statement.line() {
+            self.line = line;
+        }
+        if let Err(kind) = self.sandbox.step(&mut self.heap) {
+            return Err(sandbox::exceeded(kind, self.line).into());
+        }
+
+        statement.accept(self)
+    }
+
+    // reserves heap bytes for a string or collection which is about to be created or to grow
+    fn charge(&mut self, bytes: usize, line: usize) -> Result<(), RuntimeError> {
+        self.heap
+            .charge(bytes)
+            .map_err(|kind| sandbox::exceeded(kind, line))
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        expr.accept(self)
+    }
+
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let mut result = Ok(());
+        for statement in statements {
+            result = self.execute(statement);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.environment = previous;
+        result
+    }
+
+    fn look_up_variable(&self, name: &Token, id: NodeId) -> Result<Value, RuntimeError> {
+        match self.depths.get(id) {
+            Some(distance) => Ok(Environment::get_at(
+                &self.environment,
+                distance,
+                &name.lexeme,
+            )),
+            None => self.module.globals.borrow().get(name),
+        }
+    }
+
+    fn call(
+        &mut self,
+        callee: Value,
+        arguments: Vec<Value>,
+        paren: &Token,
+    ) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Function(function) => {
+                check_arity(function.arity(), arguments.len(), paren)?;
+                self.call_function(&function, arguments, paren.line)
+            }
+            Value::Class(class) => {
+                let instance = Value::Instance(self.heap.allocate(Instance::new(class.clone())));
+
+                match class.find_method(&Symbol::from("init")) {
+                    Some(Value::Function(initializer)) => {
+                        check_arity(initializer.arity(), arguments.len(), paren)?;
+                        let initializer = initializer.bind(instance.clone(), &mut self.heap);
+                        self.call_function(&initializer, arguments, paren.line)?;
+                    }
+                    _ => check_arity(0, arguments.len(), paren)?,
+                }
+
+                Ok(instance)
+            }
+            Value::Native(native) => {
+                self.sandbox.allow(native.capability, paren.line)?;
+                let mut context = NativeContext::new(&mut self.heap, &mut self.random);
+                native
+                    .call(&mut context, &arguments)
+                    .map_err(|message| context.error(message, paren.line))
+            }
+            // the tree-walker binds only built-in methods of collections, methods of classes are bound functions
+            Value::BoundMethod(bound) => match &bound.method {
+                Value::Native(native) => {
+                    let arguments = [Vec::from([bound.receiver.clone()]), arguments].concat();
+                    let mut context = NativeContext::new(&mut self.heap, &mut self.random);
+                    native
+                        .call_method(&mut context, &arguments)
+                        .map_err(|message| context.error(message, paren.line))
+                }
+                method => self.call(method.clone(), arguments, paren),
+            },
+            _ => Err(RuntimeError::new(
+                "Can only call functions and classes.",
+                paren.line,
+            )),
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+        line: usize,
+    ) -> Result<Value, RuntimeError> {
+        // deep recursion would overflow the Rust stack of the tree-walker, which may be the small
+        // stack of whichever thread the host runs it on
+        let stack = crate::stack_position().abs_diff(self.stack_base);
+        if self.calls.len() == self.sandbox.limits.max_depth || stack > self.max_stack {
+            return Err(
+                RuntimeError::new("Stack overflow.", line).with_kind(ErrorKind::StackOverflow)
+            );
+        }
+
+        let mut environment = Environment::new(Some(function.closure.clone()));
+        for (param, argument) in function.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        let environment = self.heap.allocate(RefCell::new(environment));
+        self.calls.push(StackFrame {
+            function: function.name.lexeme.clone(),
+            file: function.module.file.clone(),
+            line,
+        });
+        let module = std::mem::replace(&mut self.module, function.module.clone());
+        let depths = std::mem::replace(&mut self.depths, function.depths.clone());
+        let result = self.execute_block(&function.body, environment);
+        self.module = module;
+        self.depths = depths;
+
+        let result = match result {
+            // the resolver rejects jumps out of a loop outside of the function
+            Ok(()) | Err(Unwind::Break | Unwind::Continue) => Value::Nil,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(error)) => {
+                let error = self.with_stack_trace(error);
+                self.calls.pop();
+                return Err(error);
+            }
+        };
+        self.calls.pop();
+
+        if function.is_initializer {
+            return Ok(Environment::get_at(
+                &function.closure,
+                0,
+                &Symbol::from("this"),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    // runs a module on its first import, in its own globals; the module is listed in stack traces
+    // like a function called from the import
+    fn import(&mut self, path: &Token) -> Result<Rc<Module>, RuntimeError> {
+        self.sandbox.allow(Some(Capability::Files), path.line)?;
+        let source = match self.modules.load(&path.literal, path.line)? {
+            module::Import::Loaded(module) => return Ok(module),
+            module::Import::Source(source) => source,
+        };
+
+        let module = Module::new(source.name, Some(source.file), &mut self.heap);
+        let module = self.heap.allocate(module);
+
+        let importing = std::mem::replace(&mut self.module, module.clone());
+        let environment = std::mem::replace(&mut self.environment, module.globals.clone());
+        self.calls.push(StackFrame {
+            function: module.name.clone(),
+            file: module.file.clone(),
+            line: path.line,
+        });
+
+        let depths = std::mem::replace(&mut self.depths, source.script.depths.clone());
+        let mut result = Ok(());
+        for statement in &source.script.statements {
+            // the resolver rejects top-level returns and jumps out of loops
+            if let Err(Unwind::Error(error)) = self.execute(statement) {
+                result = Err(self.with_stack_trace(error));
+                break;
+            }
+        }
+
+        self.calls.pop();
+        self.module = importing;
+        self.environment = environment;
+        self.depths = depths;
+        self.modules.finish(result.is_ok().then(|| module.clone()));
+
+        result.map(|()| module)
+    }
+
+    // records the call stack in an error leaving a function or caught by a script, unless it already
+    // has the stack from where it was raised
+    fn with_stack_trace(&self, mut error: RuntimeError) -> RuntimeError {
+        if !error.frames.is_empty() {
+            return error;
+        }
+
+        let mut line = error.line;
+        for call in self.calls.iter().rev() {
+            error.frames.push(StackFrame {
+                function: call.function.clone(),
+                file: call.file.clone(),
+                line,
+            });
+            line = call.line;
+        }
+        error.frames.push(StackFrame::script(line));
+
+        error
+    }
+
+    fn bind_method(
+        &mut self,
+        instance: &Value,
+        class: &object::Class,
+        name: &Token,
+    ) -> Result<Value, RuntimeError> {
+        match class.find_method(&name.lexeme) {
+            Some(Value::Function(method)) => {
+                let method = method.bind(instance.clone(), &mut self.heap);
+                Ok(Value::Function(self.heap.allocate(method)))
+            }
+            _ => Err(RuntimeError::new(
+                format!("Undefined property '{}'.", name.lexeme),
+                name.line,
+            )),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_fatal(result: &Result<(), Unwind>) -> bool {
+    matches!(result, Err(Unwind::Error(error)) if error.kind.is_fatal())
+}
+
+fn module_property(module: &Module, name: &Token) -> Result<Value, RuntimeError> {
+    module.get(&name.lexeme).ok_or_else(|| {
+        RuntimeError::new(format!("Undefined property '{}'.", name.lexeme), name.line)
+    })
+}
+
+fn check_arity(arity: usize, count: usize, paren: &Token) -> Result<(), RuntimeError> {
+    if arity != count {
+        return Err(RuntimeError::new(
+            format!("Expected {arity} arguments but got {count}."),
+            paren.line,
+        ));
+    }
+
+    Ok(())
+}
+
+// address of a local of the caller, the distance between two of them is the stack used in between
+impl statement::Visitor<Result<(), Unwind>> for Interpreter {
+    fn visit_block_stmt(&mut self, stmt: &Block) -> Result<(), Unwind> {
+        let environment = Environment::new(Some(self.environment.clone()));
+        let environment = self.heap.allocate(RefCell::new(environment));
+        self.execute_block(&stmt.statements, environment)
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Class) -> Result<(), Unwind> {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => match self.look_up_variable(&superclass.name, superclass.id)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(RuntimeError::new(
+                        "Superclass must be a class.",
+                        superclass.name.line,
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(&stmt.name.lexeme, Value::Nil);
+
+        let enclosing = self.environment.clone();
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new(Some(self.environment.clone()));
+            environment.define(&Symbol::from("super"), Value::Class(superclass.clone()));
+            self.environment = self.heap.allocate(RefCell::new(environment));
+        }
+
+        let class = object::Class::new(stmt.name.lexeme.clone(), superclass.clone());
+        for method in &stmt.methods {
+            let function = Function::new(
+                method,
+                self.environment.clone(),
+                self.module.clone(),
+                self.depths.clone(),
+                method.name.lexeme == "init",
+            );
+            class.methods.borrow_mut().insert(
+                method.name.lexeme.clone(),
+                Value::Function(self.heap.allocate(function)),
+            );
+        }
+
+        self.environment = enclosing;
+        self.environment
+            .borrow_mut()
+            .assign(&stmt.name, Value::Class(self.heap.allocate(class)))?;
+
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) -> Result<(), Unwind> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &statement::Function) -> Result<(), Unwind> {
+        let function = Function::new(
+            stmt,
+            self.environment.clone(),
+            self.module.clone(),
+            self.depths.clone(),
+            false,
+        );
+        self.environment.borrow_mut().define(
+            &stmt.name.lexeme,
+            Value::Function(self.heap.allocate(function)),
+        );
+        Ok(())
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) -> Result<(), Unwind> {
+        let module = self.import(&stmt.path)?;
+
+        if let Some(name) = &stmt.name {
+            self.environment
+                .borrow_mut()
+                .define(&name.lexeme, Value::Module(module));
+            return Ok(());
+        }
+
+        for name in &stmt.names {
+            let value = module_property(&module, name)?;
+            self.environment.borrow_mut().define(&name.lexeme, value);
+        }
+
+        Ok(())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &If) -> Result<(), Unwind> {
+        if self.evaluate(&stmt.condition)?.is_truthy() {
+            self.execute(&stmt.then_branch)?;
+        } else if let Some(else_branch) = &stmt.else_branch {
+            self.execute(else_branch)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) -> Result<(), Unwind> {
+        let value = self.evaluate(&stmt.expression)?;
+        writeln!(self.output, "{value}")
+            .map_err(|_| RuntimeError::new("Can't write output.", stmt.keyword.line))?;
+        Ok(())
+    }
+
+    fn visit_break_stmt(&mut self, _: &Break) -> Result<(), Unwind> {
+        Err(Unwind::Break)
+    }
+
+    fn visit_continue_stmt(&mut self, _: &Continue) -> Result<(), Unwind> {
+        Err(Unwind::Continue)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Return) -> Result<(), Unwind> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Throw) -> Result<(), Unwind> {
+        let value = self.evaluate(&stmt.value)?;
+        Err(Unwind::Error(RuntimeError::thrown(
+            value,
+            stmt.keyword.line,
+        )))
+    }
+
+    // returns, breaks and errors leaving the try or catch block run the finally block first,
+    // unless the finally block itself is left early; a script stopped by the sandbox runs neither
+    fn visit_try_stmt(&mut self, stmt: &Try) -> Result<(), Unwind> {
+        let environment = Environment::new(Some(self.environment.clone()));
+        let environment = self.heap.allocate(RefCell::new(environment));
+        let mut result = self.execute_block(&stmt.body, environment);
+        if is_fatal(&result) {
+            return result;
+        }
+
+        if let Some(name) = &stmt.catch_name {
+            if let Err(Unwind::Error(error)) = result {
+                let error = self.with_stack_trace(error);
+                let mut environment = Environment::new(Some(self.environment.clone()));
+                environment.define(&name.lexeme, error.into_value());
+                let environment = self.heap.allocate(RefCell::new(environment));
+                result = self.execute_block(&stmt.catch_body, environment);
+                if is_fatal(&result) {
+                    return result;
+                }
+            }
+        }
+
+        if let Some(finally_body) = &stmt.finally_body {
+            let environment = Environment::new(Some(self.environment.clone()));
+            let environment = self.heap.allocate(RefCell::new(environment));
+            self.execute_block(finally_body, environment)?;
+        }
+
+        result
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) -> Result<(), Unwind> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(&stmt.name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) -> Result<(), Unwind> {
+        while self.evaluate(&stmt.condition)?.is_truthy() {
+            match self.execute(&stmt.body) {
+                Ok(()) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+
+            if let Some(increment) = &stmt.increment {
+                self.evaluate(increment)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl expression::Visitor<Result<Value, RuntimeError>> for Interpreter {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(&expr.value)?;
+
+        match self.depths.get(expr.id) {
+            Some(distance) => {
+                Environment::assign_at(&self.environment, distance, &expr.name, value.clone())
+            }
+            None => self
+                .module
+                .globals
+                .borrow_mut()
+                .assign(&expr.name, value.clone())?,
+        }
+
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+
+        let result = match expr.operator.token_type {
+            TokenType::BangEqual => Ok(Value::Bool(left != right)),
+            TokenType::EqualEqual => Ok(Value::Bool(left == right)),
+            TokenType::Greater => value::compare(&left, &right, f64::gt),
+            TokenType::GreaterEqual => value::compare(&left, &right, f64::ge),
+            TokenType::Less => value::compare(&left, &right, f64::lt),
+            TokenType::LessEqual => value::compare(&left, &right, f64::le),
+            TokenType::Minus => value::arithmetic(&left, &right, |a, b| a - b),
+            TokenType::Plus => {
+                self.charge(value::concatenation_size(&left, &right), expr.operator.line)?;
+                value::add(&left, &right)
+            }
+            TokenType::Slash => value::arithmetic(&left, &right, |a, b| a / b),
+            TokenType::Star => value::arithmetic(&left, &right, |a, b| a * b),
+            _ => unreachable!("Parser produced an unknown binary operator."),
+        };
+
+        result.map_err(|message| RuntimeError::new(message, expr.operator.line))
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut arguments = Vec::with_capacity(expr.arguments.len());
+        for argument in &expr.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        self.call(callee, arguments, &expr.paren)
+    }
+
+    fn visit_get_expr(&mut self, expr: &Get) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+
+        match &object {
+            Value::Instance(instance) => {
+                if let Some(value) = instance.fields.borrow().get(&expr.name.lexeme) {
+                    return Ok(value.clone());
+                }
+
+                self.bind_method(&object, &instance.class, &expr.name)
+            }
+            Value::List(_) | Value::Map(_) | Value::Cursor(_) => {
+                match collection::method(&object, &expr.name.lexeme) {
+                    Some(method) => Ok(Value::BoundMethod(self.heap.allocate(BoundMethod {
+                        receiver: object.clone(),
+                        method: Value::Native(method),
+                    }))),
+                    None => Err(RuntimeError::new(
+                        format!("Undefined property '{}'.", expr.name.lexeme),
+                        expr.name.line,
+                    )),
+                }
+            }
+            Value::Module(module) => module_property(module, &expr.name),
+            Value::Foreign(foreign) => {
+                if let Some(value) = foreign.get(&expr.name.lexeme) {
+                    return Ok(value);
+                }
+
+                match foreign.method(&expr.name.lexeme) {
+                    Some(method) => Ok(Value::BoundMethod(self.heap.allocate(BoundMethod {
+                        receiver: object.clone(),
+                        method: Value::Native(method),
+                    }))),
+                    None => Err(RuntimeError::new(
+                        format!("Undefined property '{}'.", expr.name.lexeme),
+                        expr.name.line,
+                    )),
+                }
+            }
+            Value::Exception(exception) => match exception.property(&expr.name.lexeme) {
+                Some(value) => Ok(value),
+                None => Err(RuntimeError::new(
+                    format!("Undefined property '{}'.", expr.name.lexeme),
+                    expr.name.line,
+                )),
+            },
+            object => Err(RuntimeError::new(
+                collection::no_property(object, &expr.name.lexeme),
+                expr.name.line,
+            )),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> Result<Value, RuntimeError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_index_expr(&mut self, expr: &Index) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+
+        collection::get_index(&object, &index)
+            .map_err(|message| RuntimeError::new(message, expr.bracket.line))
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSet) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        let value = self.evaluate(&expr.value)?;
+
+        let growth = collection::growth(&object, &index, &value);
+        self.charge(growth, expr.bracket.line)?;
+        collection::set_index(&object, &index, value.clone())
+            .map_err(|message| RuntimeError::new(message, expr.bracket.line))?;
+        Ok(value)
+    }
+
+    fn visit_list_expr(&mut self, expr: &expression::List) -> Result<Value, RuntimeError> {
+        let mut elements = Vec::with_capacity(expr.elements.len());
+        for element in &expr.elements {
+            elements.push(self.evaluate(element)?);
+        }
+
+        Ok(Value::List(self.heap.allocate(List::new(elements))))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> Result<Value, RuntimeError> {
+        Ok(Value::from(&expr.value))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Logical) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(&expr.left)?;
+
+        if expr.operator.token_type == TokenType::Or {
+            if left.is_truthy() {
+                return Ok(left);
+            }
+        } else if !left.is_truthy() {
+            return Ok(left);
+        }
+
+        self.evaluate(&expr.right)
+    }
+
+    fn visit_map_expr(&mut self, expr: &expression::Map) -> Result<Value, RuntimeError> {
+        let mut entries = Entries::default();
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            let key = self.evaluate(key)?;
+            let value = self.evaluate(value)?;
+            entries
+                .insert(key, value)
+                .map_err(|message| RuntimeError::new(message, expr.brace.line))?;
+        }
+
+        Ok(Value::Map(self.heap.allocate(Map::new(entries))))
+    }
+
+    fn visit_set_expr(&mut self, expr: &Set) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let value = self.evaluate(&expr.value)?;
+
+        match object {
+            Value::Instance(instance) => {
+                instance
+                    .fields
+                    .borrow_mut()
+                    .insert(expr.name.lexeme.clone(), value.clone());
+                Ok(value)
+            }
+            Value::Foreign(foreign) => {
+                foreign
+                    .set(&expr.name.lexeme, value.clone())
+                    .map_err(|message| RuntimeError::new(message, expr.name.line))?;
+                Ok(value)
+            }
+            _ => Err(RuntimeError::new(
+                "Only instances have fields.",
+                expr.name.line,
+            )),
+        }
+    }
+
+    fn visit_super_expr(&mut self, expr: &Super) -> Result<Value, RuntimeError> {
+        let distance = self.depths.get(expr.id).unwrap_or(0);
+        let superclass = Environment::get_at(&self.environment, distance, &Symbol::from("super"));
+        // `this` is always bound one scope closer than `super`
+        let instance = Environment::get_at(
+            &self.environment,
+            distance.saturating_sub(1),
+            &Symbol::from("this"),
+        );
+
+        match superclass {
+            Value::Class(superclass) => self.bind_method(&instance, &superclass, &expr.method),
+            _ => unreachable!("Resolver bound 'super' to a non-class value."),
+        }
+    }
+
+    fn visit_this_expr(&mut self, expr: &This) -> Result<Value, RuntimeError> {
+        self.look_up_variable(&expr.keyword, expr.id)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> Result<Value, RuntimeError> {
+        let right = self.evaluate(&expr.right)?;
+
+        match expr.operator.token_type {
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            TokenType::Minus => value::negate(&right)
+                .map_err(|message| RuntimeError::new(message, expr.operator.line)),
+            _ => unreachable!("Parser produced an unknown unary operator."),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> Result<Value, RuntimeError> {
+        self.look_up_variable(&expr.name, expr.id)
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+
+// JSON document, used by the tools which talk to other programs; objects keep the order of their keys
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(entries: [(&str, Json); N]) -> Self {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser {
+            chars: text.chars().collect(),
+            current: 0,
+        };
+
+        let value = parser.value()?;
+        parser.whitespace();
+        match parser.current < parser.chars.len() {
+            true => Err(parser.error("Expect end of JSON text.")),
+            false => Ok(value),
+        }
+    }
+
+    // value of the key of an object, null for a missing key or a value which isn't an object
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map_or(&Json::Null, |(_, value)| value),
+            _ => &Json::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|value| value.fract() == 0.0 && *value >= 0.0)
+            .map(|value| value as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(values: Vec<Json>) -> Self {
+        Json::Array(values)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+// compact text without any whitespace
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            // JSON has no infinity or NaN
+            Json::Number(value) if !value.is_finite() => write!(f, "null"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            character if (character as u32) < 0x20 => write!(f, "\\u{:04x}", character as u32)?,
+            character => write!(f, "{character}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some(character) if character == '-' || character.is_ascii_digit() => self.number(),
+            _ => Err(self.error("Expect a value.")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.current += 1;
+        let mut entries = Vec::new();
+
+        self.whitespace();
+        if self.matches('}') {
+            return Ok(Json::Object(entries));
+        }
+
+        loop {
+            self.whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expect a string key."));
+            }
+            let key = self.string()?;
+
+            self.whitespace();
+            if !self.matches(':') {
+                return Err(self.error("Expect ':' after key."));
+            }
+            entries.push((key, self.value()?));
+
+            self.whitespace();
+            if self.matches('}') {
+                return Ok(Json::Object(entries));
+            }
+            if !self.matches(',') {
+                return Err(self.error("Expect ',' or '}' after value."));
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.current += 1;
+        let mut values = Vec::new();
+
+        self.whitespace();
+        if self.matches(']') {
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.whitespace();
+            if self.matches(']') {
+                return Ok(Json::Array(values));
+            }
+            if !self.matches(',') {
+                return Err(self.error("Expect ',' or ']' after value."));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.current += 1;
+        let mut value = String::new();
+
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.advance() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => value.push(self.unicode_escape()?),
+                    _ => return Err(self.error("Invalid escape sequence.")),
+                },
+                Some(character) if (character as u32) < 0x20 => {
+                    return Err(self.error("Unescaped control character in string."))
+                }
+                Some(character) => value.push(character),
+                None => return Err(self.error("Unterminated string.")),
+            }
+        }
+    }
+
+    // characters outside the basic plane are escaped as a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("Invalid unicode escape."));
+        }
+
+        if !(self.matches('\\') && self.matches('u')) {
+            return Err(self.error("Expect low surrogate after high surrogate."));
+        }
+        let low = self.hex()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(self.error("Invalid low surrogate."));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+            .ok_or_else(|| self.error("Invalid unicode escape."))
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.iter().skip(self.current).take(4).collect();
+        if digits.len() != 4 {
+            return Err(self.error("Expect four hex digits."));
+        }
+
+        self.current += 4;
+        u32::from_str_radix(&digits, 16).map_err(|_| self.error("Expect four hex digits."))
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.current;
+        while self
+            .peek()
+            .is_some_and(|character| "+-.eE".contains(character) || character.is_ascii_digit())
+        {
+            self.current += 1;
+        }
+
+        let text: String = self.chars[start..self.current].iter().collect();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("Invalid number."))
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        for expected in keyword.chars() {
+            if !self.matches(expected) {
+                return Err(self.error("Expect a value."));
+            }
+        }
+        Ok(value)
+    }
+
+    fn whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|character| matches!(character, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.current += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let character = self.peek();
+        self.current += 1;
+        character
+    }
+
+    fn matches(&mut self, expected: char) -> bool {
+        if self.peek() != Some(expected) {
+            return false;
+        }
+
+        self.current += 1;
+        true
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{message} at character {}", self.current)
+    }
+}
@@ -1,6 +1,10 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use crate::error;
+use std::ops::Range;
+
+use crate::report_span;
+use crate::symbol::Symbol;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenType {
@@ -8,6 +12,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -33,73 +40,116 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    As,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
+    From,
     If,
+    Import,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     White,
     While,
     Eof,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
-    pub literal: String, // originally it was Object; likely that this type should be changed to something else once I figure out what it exactly is;
+    pub lexeme: Symbol,
+    pub literal: Symbol, // originally it was Object; likely that this type should be changed to something else once I figure out what it exactly is;
     // turns out it needs to be String for some literals and i32 for others;
-    pub numeric_literal: f32,
-    pub line: u8,
+    pub numeric_literal: f64,
+    pub line: usize,
+    // index of the first character in the source; tokens which the parser generates when desugaring
+    // take the position of the code they stand for
+    pub offset: usize,
 }
 
 impl Token {
-    pub fn to_string(&self) -> String {
-        self.lexeme.to_owned() + " " + &self.literal.to_owned() // it should start with "self.token_type +" but I didn't manage to parse TokenType to String yet
+    // characters of the source which the token was lexed from
+    pub fn span(&self) -> Range<usize> {
+        self.offset..self.offset + self.lexeme.chars().count()
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {} {}", self.token_type, self.lexeme, self.literal)
     }
 }
 
+// comments aren't tokens, the formatter puts them back between the statements
+pub struct Comment {
+    pub text: String,
+    pub line: usize,
+    pub offset: usize,
+    // follows code on the same line
+    pub trailing: bool,
+}
+
 pub struct Lexer {
-    source: String,
+    source: Vec<char>,
     pub tokens: Vec<Token>,
-    start: u8,
-    current: u8,
-    line: u8,
+    pub comments: Vec<Comment>,
+    pub had_error: bool,
+    start: usize,
+    current: usize,
+    line: usize,
     keywords: HashMap<&'static str, TokenType>, // not sure about &'static, maybe it needs to be <String, TokenType>
 }
 
 impl Lexer {
     pub fn new(source: String) -> Self {
         Self {
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
+            comments: Vec::new(),
+            had_error: false,
             start: 0,
             current: 0,
             line: 1,
             keywords: HashMap::from([
                 ("and", TokenType::And),
+                ("as", TokenType::As),
+                ("break", TokenType::Break),
+                ("catch", TokenType::Catch),
                 ("class", TokenType::Class),
+                ("continue", TokenType::Continue),
                 ("else", TokenType::Else),
                 ("false", TokenType::False),
+                ("finally", TokenType::Finally),
                 ("for", TokenType::For),
+                ("from", TokenType::From),
                 ("fun", TokenType::Fun),
                 ("if", TokenType::If),
+                ("import", TokenType::Import),
+                ("in", TokenType::In),
                 ("nil", TokenType::Nil),
                 ("or", TokenType::Or),
                 ("print", TokenType::Print),
                 ("return", TokenType::Return),
                 ("super", TokenType::Super),
                 ("this", TokenType::This),
+                ("throw", TokenType::Throw),
                 ("true", TokenType::True),
+                ("try", TokenType::Try),
                 ("var", TokenType::Var),
                 ("while", TokenType::While),
             ]),
@@ -107,9 +157,9 @@ impl Lexer {
     }
 
     pub fn scan_tokens(&mut self) -> &Vec<Token> {
-        while !Self::is_at_end(self) {
+        while !self.is_at_end() {
             self.start = self.current;
-            Self::scan_token(self);
+            self.scan_token();
         }
 
         self.append_eof_token();
@@ -120,22 +170,16 @@ impl Lexer {
     fn append_eof_token(&mut self) {
         self.tokens.push(Token {
             token_type: TokenType::Eof,
-            lexeme: "".to_owned(),
-            literal: "".to_owned(), // originally Null
-            numeric_literal: 0.0,   // a stub value
+            lexeme: Symbol::default(),
+            literal: Symbol::default(), // originally Null
+            numeric_literal: 0.0,       // a stub value
             line: self.line,
+            offset: self.current,
         });
     }
 
     fn is_at_end(&self) -> bool {
-        self.current >= self.source.len() as u8
-            || self
-                .source
-                .as_str()
-                .chars()
-                .nth((self.current).into())
-                .unwrap()
-                == '\0'
+        self.current >= self.source.len() || self.source[self.current] == '\0'
     }
 
     fn scan_token(&mut self) {
@@ -145,6 +189,9 @@ impl Lexer {
             ')' => Self::add_empty_token(self, TokenType::RightParen),
             '{' => Self::add_empty_token(self, TokenType::LeftBrace),
             '}' => Self::add_empty_token(self, TokenType::RightBrace),
+            '[' => Self::add_empty_token(self, TokenType::LeftBracket),
+            ']' => Self::add_empty_token(self, TokenType::RightBracket),
+            ':' => Self::add_empty_token(self, TokenType::Colon),
             ',' => Self::add_empty_token(self, TokenType::Comma),
             '.' => Self::add_empty_token(self, TokenType::Dot),
             '-' => Self::add_empty_token(self, TokenType::Minus),
@@ -169,14 +216,14 @@ impl Lexer {
                 if Self::match_character(self, '=') {
                     Self::add_empty_token(self, TokenType::LessEqual)
                 } else {
-                    Self::add_empty_token(self, TokenType::Equal)
+                    Self::add_empty_token(self, TokenType::Less)
                 }
             }
             '>' => {
                 if Self::match_character(self, '=') {
                     Self::add_empty_token(self, TokenType::GreaterEqual)
                 } else {
-                    Self::add_empty_token(self, TokenType::Equal)
+                    Self::add_empty_token(self, TokenType::Greater)
                 }
             }
             '/' => {
@@ -184,6 +231,7 @@ impl Lexer {
                     while Self::peek(self) != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_comment();
                 } else {
                     Self::add_empty_token(self, TokenType::Slash)
                 }
@@ -200,18 +248,14 @@ impl Lexer {
                 Self::identifier(self);
             }
             _ => {
-                error(self.line, String::from("Unexpected character"));
+                self.had_error = true;
+                self.error("Unexpected character.");
             }
         };
     }
 
     fn advance(&mut self) -> char {
-        let next_character = self
-            .source
-            .as_str()
-            .chars()
-            .nth((self.current).into())
-            .unwrap_or_default();
+        let next_character = self.source.get(self.current).copied().unwrap_or_default();
 
         self.current += 1;
         next_character
@@ -225,32 +269,48 @@ impl Lexer {
         &mut self,
         token_type: TokenType,
         literal: Option<String>,
-        numeric_literal: Option<f32>,
+        numeric_literal: Option<f64>,
     ) {
-        let text: Option<&str> = self.source.get(self.start.into()..self.current.into());
+        let text = self.text(self.start, self.current);
 
         self.tokens.push(Token {
             token_type,
-            lexeme: match text {
-                Some(text) => text.to_owned(),
-                None => "".to_owned(), // maybe it should fail in more explicit manner
-            },
-            literal: literal.unwrap_or_default().to_owned(), // it makes that literal always have a value; maybe it's not desired behavior
+            lexeme: Symbol::from(text),
+            literal: Symbol::from(literal.unwrap_or_default()), // it makes that literal always have a value; maybe it's not desired behavior
             numeric_literal: numeric_literal.unwrap_or_default().to_owned(), // it makes that numeric_literal always have a value; maybe it's not desired behavior
             line: self.line,
+            offset: self.start,
         })
     }
 
+    fn error(&self, message: &str) {
+        let span = Some(self.start..self.current);
+        report_span(self.line, span, "".to_owned(), message.to_owned());
+    }
+
+    fn add_comment(&mut self) {
+        let text = self.text(self.start, self.current);
+
+        self.comments.push(Comment {
+            text: text.trim_end().to_string(),
+            line: self.line,
+            offset: self.start,
+            trailing: self
+                .tokens
+                .last()
+                .is_some_and(|token| token.line == self.line),
+        });
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        match self.source.get(start..end) {
+            Some(text) => text.iter().collect(),
+            None => "".to_owned(), // maybe it should fail in more explicit manner
+        }
+    }
+
     fn match_character(&mut self, character: char) -> bool {
-        if self.is_at_end()
-            || self
-                .source
-                .as_str()
-                .chars()
-                .nth(self.current.into())
-                .unwrap()
-                != character
-        {
+        if self.is_at_end() || self.source[self.current] != character {
             return false;
         }
 
@@ -264,11 +324,7 @@ impl Lexer {
             return '\0';
         }
 
-        self.source
-            .as_str()
-            .chars()
-            .nth(self.current.into())
-            .unwrap()
+        self.source[self.current]
     }
 
     fn string(&mut self) {
@@ -280,16 +336,15 @@ impl Lexer {
         }
 
         if self.is_at_end() {
-            error(self.line, "Unterminated string.".to_owned());
+            self.had_error = true;
+            self.error("Unterminated string.");
             return;
         }
 
         self.advance();
 
-        let text = match self.source.get(self.start.into()..self.current.into()) {
-            Some(text) => text.to_owned(),
-            None => "".to_owned(), // handle it better way; it's still an error situation
-        };
+        // the literal value doesn't include the surrounding quotes
+        let text = self.text(self.start + 1, self.current - 1);
 
         self.add_token(TokenType::String, Some(text), None);
     }
@@ -307,42 +362,29 @@ impl Lexer {
             }
         }
 
-        let number = match self.source.get(self.start.into()..self.current.into()) {
-            Some(text) => text.to_owned(),
-            None => "".to_owned(), // handle it better way; it's still an error situation
-        };
+        let number = self.text(self.start, self.current);
 
         self.add_token(
             TokenType::Number,
-            None,
-            Some(number.parse::<f32>().expect("Invalid number")), // handle a failure of parsing a number in better way which wouldn't stop a compiler
+            Some(number.clone()),
+            Some(number.parse::<f64>().expect("Invalid number")), // handle a failure of parsing a number in better way which wouldn't stop a compiler
         );
     }
 
     fn is_digit(character: char) -> bool {
-        match character {
-            '0'..='9' => true,
-            _ => false,
-        }
+        character.is_ascii_digit()
     }
 
     fn peek_next(&mut self) -> char {
-        if self.current + 1 >= self.source.len() as u8 {
+        if self.current + 1 >= self.source.len() {
             return '\0';
         }
 
-        self.source
-            .as_str()
-            .chars()
-            .nth((self.current + 1).into())
-            .unwrap()
+        self.source[self.current + 1]
     }
 
     fn is_alpha(character: char) -> bool {
-        match character {
-            'a'..='z' | 'A'..='Z' | '_' => true,
-            _ => false,
-        }
+        matches!(character, 'a'..='z' | 'A'..='Z' | '_')
     }
 
     fn is_alphanumeric(character: char) -> bool {
@@ -354,12 +396,9 @@ impl Lexer {
             self.advance();
         }
 
-        let text = match self.source.get(self.start.into()..self.current.into()) {
-            Some(text) => text,
-            None => "", // handle it better way; it's still an error situation
-        };
+        let text = self.text(self.start, self.current);
 
-        let token_type = match self.keywords.get(text) {
+        let token_type = match self.keywords.get(text.as_str()) {
             Some(token_type) => *token_type,
             None => TokenType::Identifier,
         };
@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::hint;
+use std::ops::Range;
+
+pub mod ast;
+pub mod bytecode;
+pub mod embed;
+pub mod interpreter;
+pub mod json;
+pub mod lexical_analysis;
+pub mod lsp;
+pub mod runtime;
+pub mod symbol;
+
+pub use embed::{Error, Lox};
+pub use runtime::error::ErrorKind;
+pub use runtime::foreign::{Foreign, NativeClass};
+pub use runtime::sandbox::{CancelHandle, Capabilities, Limits};
+pub use runtime::value::Value;
+
+thread_local! {
+    // compile errors of an embedded script are collected for the host instead of being printed
+    static CAPTURED_ERRORS: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+}
+
+// compile error found while lexing, parsing, resolving or compiling a script
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub line: usize,
+    // characters of the source which the error is about, when they're known
+    pub span: Option<Range<usize>>,
+    // i.e. " at 'x'"
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[line: {}] Error{}: {}",
+            self.line, self.location, self.message
+        )
+    }
+}
+
+pub fn error(line: usize, message: String) {
+    report(line, "".to_owned(), message);
+}
+
+pub fn report(line: usize, source: String, message: String) {
+    report_span(line, None, source, message);
+}
+
+pub fn report_span(line: usize, span: Option<Range<usize>>, source: String, message: String) {
+    let diagnostic = Diagnostic {
+        line,
+        span,
+        location: source,
+        message,
+    };
+
+    CAPTURED_ERRORS.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(errors) => errors.push(diagnostic),
+        None => eprintln!("{diagnostic}"),
+    });
+}
+
+// runs `f` with compile errors collected instead of printed
+pub(crate) fn capture_errors<T>(f: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+    let previous = CAPTURED_ERRORS.with(|captured| captured.replace(Some(Vec::new())));
+    let result = f();
+    let errors = CAPTURED_ERRORS.with(|captured| captured.replace(previous));
+
+    (result, errors.unwrap_or_default())
+}
+
+// address on the native stack of the caller, which recursive code compares with the address where
+// it started to know how much of the stack it's using
+pub(crate) fn stack_position() -> usize {
+    let marker = 0u8;
+    hint::black_box(&marker) as *const u8 as usize
+}
@@ -0,0 +1,104 @@
+use std::ops::Range;
+
+use crate::ast::analysis::Analysis;
+use crate::ast::parser::Parser;
+use crate::ast::resolver::Resolver;
+use crate::json::Json;
+use crate::lexical_analysis::Lexer;
+use crate::Diagnostic;
+
+// open script with the results of lexing, parsing and resolving its latest text
+pub struct Document {
+    chars: Vec<char>,
+    // character offsets where lines start
+    line_starts: Vec<usize>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub analysis: Analysis,
+}
+
+impl Document {
+    // a script with syntax errors is analyzed as far as the parser recovered
+    pub fn new(text: &str) -> Self {
+        let (analysis, diagnostics) = crate::capture_errors(|| {
+            let mut lexer = Lexer::new(text.to_string());
+            lexer.scan_tokens();
+
+            let mut parser = Parser::new(lexer.tokens.clone());
+            let statements = parser.parse();
+
+            if !lexer.had_error && !parser.had_error {
+                Resolver::new().resolve(&statements);
+            }
+            Analysis::new(&statements)
+        });
+
+        let chars: Vec<char> = text.chars().collect();
+        let line_starts = std::iter::once(0)
+            .chain(
+                chars
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, character)| **character == '\n')
+                    .map(|(index, _)| index + 1),
+            )
+            .collect();
+
+        Self {
+            chars,
+            line_starts,
+            diagnostics,
+            analysis,
+        }
+    }
+
+    // positions count lines from zero and characters in UTF-16 code units
+    pub fn position(&self, offset: usize) -> Json {
+        let offset = offset.min(self.chars.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let character: usize = self.chars[self.line_starts[line]..offset]
+            .iter()
+            .map(|character| character.len_utf16())
+            .sum();
+
+        Json::object([("line", line.into()), ("character", character.into())])
+    }
+
+    pub fn offset(&self, position: &Json) -> Option<usize> {
+        let line = position.get("line").as_usize()?;
+        let character = position.get("character").as_usize()?;
+        let mut offset = *self.line_starts.get(line)?;
+
+        let mut units = 0;
+        while units < character && self.chars.get(offset).is_some_and(|c| *c != '\n') {
+            units += self.chars[offset].len_utf16();
+            offset += 1;
+        }
+        Some(offset)
+    }
+
+    pub fn range(&self, span: Range<usize>) -> Json {
+        Json::object([
+            ("start", self.position(span.start)),
+            ("end", self.position(span.end)),
+        ])
+    }
+
+    // a diagnostic without a span covers its whole line
+    pub fn diagnostic_range(&self, diagnostic: &Diagnostic) -> Json {
+        match &diagnostic.span {
+            Some(span) => self.range(span.clone()),
+            None => {
+                let line = diagnostic
+                    .line
+                    .saturating_sub(1)
+                    .min(self.line_starts.len() - 1);
+                let start = self.line_starts[line];
+                let end = self
+                    .line_starts
+                    .get(line + 1)
+                    .map_or(self.chars.len(), |next| next - 1);
+                self.range(start..end)
+            }
+        }
+    }
+}
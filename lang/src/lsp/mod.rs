@@ -0,0 +1,364 @@
+mod document;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::ast::analysis::DeclarationKind;
+use crate::json::Json;
+use crate::lexical_analysis::{Lexer, TokenType};
+
+use self::document::Document;
+
+const PARSE_ERROR: f64 = -32700.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const INVALID_PARAMS: f64 = -32602.0;
+
+// Language server speaking the Language Server Protocol over stdin and stdout. Documents are
+// synchronized as a whole and analyzed again on every change. Returns the exit code.
+pub fn run() -> io::Result<i32> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    Server::default().serve(&mut input, &mut output)
+}
+
+type Response = Result<Json, (f64, String)>;
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn serve(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+        // the client has to ask for a shutdown before it exits
+        while let Some(content) = read_message(input)? {
+            let message = match Json::parse(&content) {
+                Ok(message) => message,
+                Err(error) => {
+                    send(output, error_response(Json::Null, PARSE_ERROR, error))?;
+                    continue;
+                }
+            };
+
+            let params = message.get("params");
+            let id = message.get("id");
+            match message.get("method").as_str() {
+                Some("exit") => return Ok(if self.shutdown { 0 } else { 1 }),
+                // responses to requests of the server, which doesn't send any
+                None => {}
+                Some(method) if *id == Json::Null => self.notify(method, params, output)?,
+                Some(method) => {
+                    let response = match self.request(method, params) {
+                        Ok(result) => Json::object([
+                            ("jsonrpc", "2.0".into()),
+                            ("id", id.clone()),
+                            ("result", result),
+                        ]),
+                        Err((code, message)) => error_response(id.clone(), code, message),
+                    };
+                    send(output, response)?;
+                }
+            }
+        }
+        Ok(1)
+    }
+
+    fn notify(&mut self, method: &str, params: &Json, output: &mut impl Write) -> io::Result<()> {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .get("text")
+                    .as_str()
+                    .unwrap_or("");
+                self.documents.insert(uri.to_string(), Document::new(text));
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").as_array().unwrap_or(&[]);
+                let Some(text) = changes
+                    .last()
+                    .and_then(|change| change.get("text").as_str())
+                else {
+                    return Ok(());
+                };
+                self.documents.insert(uri.to_string(), Document::new(text));
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+            }
+            _ => return Ok(()),
+        }
+
+        let diagnostics = self.documents.get(uri).map_or(Vec::new(), |document| {
+            document
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    Json::object([
+                        ("range", document.diagnostic_range(diagnostic)),
+                        ("severity", 1.0.into()),
+                        ("source", "lox".into()),
+                        ("message", diagnostic.message.as_str().into()),
+                    ])
+                })
+                .collect()
+        });
+
+        send(
+            output,
+            Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("method", "textDocument/publishDiagnostics".into()),
+                (
+                    "params",
+                    Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+                ),
+            ]),
+        )
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Response {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+        let document = self.documents.get(uri);
+
+        match method {
+            "initialize" => Ok(initialize()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => {
+                Ok(document.map_or(Json::Null, |document| definition(uri, document, params)))
+            }
+            "textDocument/hover" => {
+                Ok(document.map_or(Json::Null, |document| hover(document, params)))
+            }
+            "textDocument/documentSymbol" => {
+                Ok(document.map_or(Json::Null, |document| symbols(document, None).into()))
+            }
+            "textDocument/references" => {
+                Ok(document.map_or(Json::Null, |document| references(uri, document, params)))
+            }
+            "textDocument/rename" => {
+                document.map_or(Ok(Json::Null), |document| rename(uri, document, params))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'."))),
+        }
+    }
+}
+
+fn initialize() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // documents are sent in full on every change
+                ("textDocumentSync", 1.0.into()),
+                ("definitionProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                ("referencesProvider", true.into()),
+                ("renameProvider", true.into()),
+            ]),
+        ),
+        ("serverInfo", Json::object([("name", "0x6b73746b".into())])),
+    ])
+}
+
+// declaration of the name under the cursor
+fn declaration_at(document: &Document, params: &Json) -> Option<usize> {
+    let offset = document.offset(params.get("position"))?;
+    document
+        .analysis
+        .name_at(offset)
+        .map(|(_, declaration)| declaration)
+}
+
+fn location(uri: &str, document: &Document, span: std::ops::Range<usize>) -> Json {
+    Json::object([("uri", uri.into()), ("range", document.range(span))])
+}
+
+fn definition(uri: &str, document: &Document, params: &Json) -> Json {
+    declaration_at(document, params).map_or(Json::Null, |declaration| {
+        let name = &document.analysis.declarations[declaration].name;
+        location(uri, document, name.span())
+    })
+}
+
+fn hover(document: &Document, params: &Json) -> Json {
+    let Some(offset) = document.offset(params.get("position")) else {
+        return Json::Null;
+    };
+    let Some((name, declaration)) = document.analysis.name_at(offset) else {
+        return Json::Null;
+    };
+
+    let detail = &document.analysis.declarations[declaration].detail;
+    Json::object([
+        (
+            "contents",
+            Json::object([
+                ("kind", "markdown".into()),
+                ("value", format!("```lox\n{detail}\n```").into()),
+            ]),
+        ),
+        ("range", document.range(name.span())),
+    ])
+}
+
+// functions, classes and methods with the declarations nested in them, and global variables
+fn symbols(document: &Document, parent: Option<usize>) -> Vec<Json> {
+    let declarations = &document.analysis.declarations;
+
+    declarations
+        .iter()
+        .enumerate()
+        .filter(|(_, declaration)| declaration.parent == parent)
+        .filter_map(|(index, declaration)| {
+            let kind = match declaration.kind {
+                DeclarationKind::Class => 5.0,
+                DeclarationKind::Method if &*declaration.name.lexeme == "init" => 9.0,
+                DeclarationKind::Method => 6.0,
+                DeclarationKind::Function => 12.0,
+                DeclarationKind::Module if declaration.global => 2.0,
+                DeclarationKind::Variable if declaration.global => 13.0,
+                _ => return None,
+            };
+
+            Some(Json::object([
+                ("name", (*declaration.name.lexeme).into()),
+                ("detail", declaration.detail.as_str().into()),
+                ("kind", kind.into()),
+                ("range", document.range(declaration.span.clone())),
+                ("selectionRange", document.range(declaration.name.span())),
+                ("children", symbols(document, Some(index)).into()),
+            ]))
+        })
+        .collect()
+}
+
+fn references(uri: &str, document: &Document, params: &Json) -> Json {
+    let Some(declaration) = declaration_at(document, params) else {
+        return Json::Null;
+    };
+
+    let include_declaration = params
+        .get("context")
+        .get("includeDeclaration")
+        .as_bool()
+        .unwrap_or(true);
+    let name = &document.analysis.declarations[declaration].name;
+
+    let locations: Vec<Json> = include_declaration
+        .then_some(name)
+        .into_iter()
+        .chain(
+            document
+                .analysis
+                .references_to(declaration)
+                .map(|reference| &reference.name),
+        )
+        .map(|name| location(uri, document, name.span()))
+        .collect();
+    locations.into()
+}
+
+fn rename(uri: &str, document: &Document, params: &Json) -> Response {
+    let new_name = params.get("newName").as_str().unwrap_or("");
+    if !is_identifier(new_name) {
+        return Err((INVALID_PARAMS, format!("'{new_name}' isn't a valid name.")));
+    }
+
+    let Some(declaration) = declaration_at(document, params) else {
+        return Ok(Json::Null);
+    };
+
+    let name = &document.analysis.declarations[declaration].name;
+    let edits: Vec<Json> = std::iter::once(name)
+        .chain(
+            document
+                .analysis
+                .references_to(declaration)
+                .map(|reference| &reference.name),
+        )
+        .map(|name| {
+            Json::object([
+                ("range", document.range(name.span())),
+                ("newText", new_name.into()),
+            ])
+        })
+        .collect();
+
+    Ok(Json::object([(
+        "changes",
+        Json::Object(vec![(uri.to_string(), edits.into())]),
+    )]))
+}
+
+// a name is a single identifier, and not a keyword
+fn is_identifier(name: &str) -> bool {
+    let (tokens, errors) = crate::capture_errors(|| {
+        let mut lexer = Lexer::new(name.to_string());
+        lexer.scan_tokens();
+        lexer.tokens
+    });
+
+    errors.is_empty()
+        && matches!(&tokens[..], [token, _] if token.token_type == TokenType::Identifier
+            && token.lexeme.len() == name.len())
+}
+
+fn error_response(id: Json, code: f64, message: String) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([("code", code.into()), ("message", message.into())]),
+        ),
+    ])
+}
+
+// messages are framed by a Content-Length header
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing Content-Length header.",
+        ));
+    };
+
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn send(output: &mut impl Write, message: Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
@@ -1,78 +1,444 @@
 use std::env;
-use std::fs::read_to_string;
-use std::io::{stdin, stdout, Write};
+use std::fs;
+use std::io::{stderr, stdin, stdout, Write};
+use std::path::Path;
+use std::process;
+use std::thread;
 
-use crate::ast::ast_printer::AstPrinter;
-use crate::ast::generate_ast::generate_ast;
-use crate::ast::parser::Parser;
-use ast::expression::{Binary, Grouping, Literal, Unary};
-use lexical_analysis::{Lexer, Token, TokenType};
+use lang::ast;
+use lang::ast::cfg;
+use lang::ast::export;
+use lang::ast::formatter;
+use lang::ast::generate_ast;
+use lang::ast::highlighter;
+use lang::ast::linter;
+use lang::ast::statement::Stmt;
+use lang::ast::Script;
+use lang::bytecode::compiler::Compiler;
+use lang::bytecode::disassembler::Disassembler;
+use lang::bytecode::object::Prototype;
+use lang::bytecode::serialization;
+use lang::bytecode::vm::Vm;
+use lang::interpreter::Interpreter;
+use lang::lsp;
+use lang::runtime::error::{RuntimeError, DEFAULT_MAX_DEPTH};
+use lang::runtime::heap::Heap;
+use lang::runtime::module::ModuleLoader;
+use std::rc::Rc;
 
-pub mod ast;
-pub mod lexical_analysis;
+// exit codes from sysexits.h, same as in the reference Lox implementations
+const EXIT_USAGE: i32 = 64;
+const EXIT_DATA_ERROR: i32 = 65;
+const EXIT_SOFTWARE: i32 = 70;
+const EXIT_IO_ERROR: i32 = 74;
+// `fmt --check` found a script which isn't formatted, `gen-ast --check` an outdated module or
+// `lint` problems
+const EXIT_UNFORMATTED: i32 = 1;
+
+const USAGE: &str = "Usage: 0x6b73746b [--backend=tree|vm] [--disassemble] [--trace] [--max-depth=N] [--module-path=DIRS] [gc options] [[run] script]\n--disassemble and --trace apply to the vm backend; compiled scripts always run on the vm\n--max-depth=N sets the number of nested calls which is reported as a stack overflow\n--module-path=DIRS lists directories searched for imported modules which aren't next to the importing file\nGarbage collector: --gc-stress collects on every allocation, --gc-stats prints statistics on exit,\n--gc-threshold=N sets the number of objects which triggers the first collection, --gc-growth=N how much the heap grows before the next one\nAhead-of-time compilation: 0x6b73746b compile [script] [output file]\nFormatting: 0x6b73746b fmt [--check] [scripts], --check lists scripts which aren't formatted instead of rewriting them\nLinting: 0x6b73746b lint [scripts], rules are disabled with `// lint: disable rule` comments\nHighlighting: 0x6b73746b highlight [--format=ansi|html|json] [script], prints the script coloured for terminals, as HTML or as JSON semantic tokens\nLanguage server: 0x6b73746b lsp, speaks the Language Server Protocol over stdin and stdout\nAST export: 0x6b73746b ast [--format=json|sexp] [script], prints the parse tree with spans\nGraphs: 0x6b73746b parse --dot[=ast|cfg] [script], prints the parse tree or the control-flow graphs of the functions in Graphviz DOT\nAST generation: 0x6b73746b gen-ast [--check] [output directory], --check lists modules which differ from src/ast/nodes.grammar instead of writing them";
+
+// Rust stack reserved for every nested call, the tree-walker recurses through several visitor
+// methods per call of a script function
+const STACK_PER_CALL: usize = 64 * 1024;
+const BASE_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// name of the script in stack traces of errors in the REPL
+const REPL_NAME: &str = "repl";
+
+// extension of files written by the `compile` subcommand
+const COMPILED_EXTENSION: &str = "loxc";
+
+// tree-walk interpreter evaluates the AST directly, vm compiles it to bytecode first
+enum Backend {
+    Tree(Interpreter),
+    Vm { vm: Vm, disassemble: bool },
+}
+
+enum RunError {
+    Compile,
+    Runtime(RuntimeError),
+}
+
+// graph which `parse --dot` prints
+#[derive(Clone, Copy)]
+enum Graph {
+    Ast,
+    Cfg,
+}
+
+impl Backend {
+    fn execute(&mut self, script: &Script) -> Result<(), RunError> {
+        match self {
+            Backend::Tree(interpreter) => interpreter
+                .interpret(script)
+                .map(|_| ())
+                .map_err(RunError::Runtime),
+            Backend::Vm { .. } => {
+                let function = compile(&script.statements).ok_or(RunError::Compile)?;
+                self.execute_compiled(function)
+            }
+        }
+    }
+
+    fn execute_compiled(&mut self, function: Rc<Prototype>) -> Result<(), RunError> {
+        if let Backend::Tree(interpreter) = self {
+            let mut vm = Vm::new();
+            vm.heap = std::mem::take(&mut interpreter.heap);
+            vm.sandbox.limits = interpreter.sandbox.limits;
+            vm.modules = std::mem::take(&mut interpreter.modules);
+            *self = Backend::Vm {
+                vm,
+                disassemble: false,
+            };
+        }
+
+        match self {
+            Backend::Vm { vm, disassemble } => {
+                if *disassemble {
+                    print!("{}", Disassembler {}.disassemble(&function));
+                }
+
+                vm.interpret(function)
+                    .map(|_| ())
+                    .map_err(RunError::Runtime)
+            }
+            Backend::Tree(_) => unreachable!("Compiled scripts run on the vm backend."),
+        }
+    }
+
+    fn set_max_depth(&mut self, max_depth: usize) {
+        match self {
+            Backend::Tree(interpreter) => {
+                interpreter.sandbox.limits.max_depth = max_depth;
+                // the thread's stack reserves room for every call, the rest is left to the host
+                interpreter.max_stack =
+                    max_depth.saturating_mul(STACK_PER_CALL) + BASE_STACK_SIZE / 2;
+            }
+            Backend::Vm { vm, .. } => vm.sandbox.limits.max_depth = max_depth,
+        }
+    }
+
+    fn modules(&mut self) -> &mut ModuleLoader {
+        match self {
+            Backend::Tree(interpreter) => &mut interpreter.modules,
+            Backend::Vm { vm, .. } => &mut vm.modules,
+        }
+    }
+
+    fn heap(&mut self) -> &mut Heap {
+        match self {
+            Backend::Tree(interpreter) => &mut interpreter.heap,
+            Backend::Vm { vm, .. } => &mut vm.heap,
+        }
+    }
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    let sample_expression = Binary {
-        left: Box::new(Unary {
-            operator: Token {
-                token_type: TokenType::Minus,
-                lexeme: "-".to_string(),
-                literal: "".to_string(),
-                numeric_literal: 0.0,
-                line: 0,
-            },
-            right: Box::new(Literal {
-                value: "123".to_string(),
-            }),
-        }),
-        operator: Token {
-            token_type: TokenType::Star,
-            lexeme: "*".to_string(),
-            literal: "".to_string(),
-            numeric_literal: 0.0,
-            line: 0,
-        },
-        right: Box::new(Grouping {
-            expression: Box::new(Literal {
-                value: "45".to_string(),
-            }),
-        }),
+    let mut use_vm = false;
+    let mut disassemble = false;
+    let mut trace = false;
+    let mut gc_stress = false;
+    let mut gc_stats = false;
+    let mut gc_threshold = None;
+    let mut gc_growth = None;
+    let mut max_depth = DEFAULT_MAX_DEPTH;
+    let mut module_path = Vec::new();
+    let mut check = false;
+    let mut format = None;
+    let mut dot = None;
+    let mut args: Vec<String> = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--backend=tree" => use_vm = false,
+            "--backend=vm" => use_vm = true,
+            "--disassemble" => disassemble = true,
+            "--trace" => trace = true,
+            "--gc-stress" => gc_stress = true,
+            "--gc-stats" => gc_stats = true,
+            "--check" => check = true,
+            "--dot" | "--dot=ast" => dot = Some(Graph::Ast),
+            "--dot=cfg" => dot = Some(Graph::Cfg),
+            flag if flag.starts_with("--format=") => {
+                format = Some(flag["--format=".len()..].to_string());
+            }
+            flag if flag.starts_with("--gc-threshold=") => {
+                gc_threshold = Some(numeric_flag(&flag["--gc-threshold=".len()..]))
+            }
+            flag if flag.starts_with("--gc-growth=") => {
+                gc_growth = Some(numeric_flag(&flag["--gc-growth=".len()..]))
+            }
+            flag if flag.starts_with("--max-depth=") => {
+                max_depth = numeric_flag(&flag["--max-depth=".len()..])
+            }
+            flag if flag.starts_with("--module-path=") => {
+                module_path = env::split_paths(&flag["--module-path=".len()..]).collect()
+            }
+            flag if flag.starts_with("--") => {
+                println!("{USAGE}");
+                process::exit(EXIT_USAGE);
+            }
+            _ => args.push(arg),
+        }
+    }
+
+    // the script runs on a thread whose stack fits the maximum depth of calls
+    let stack_size = BASE_STACK_SIZE.saturating_add(max_depth.saturating_mul(STACK_PER_CALL));
+    let runner = thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(move || {
+            let mut backend = if use_vm {
+                let mut vm = Vm::new();
+                if trace {
+                    vm.trace = Some(Box::new(stderr()));
+                }
+                Backend::Vm { vm, disassemble }
+            } else {
+                Backend::Tree(Interpreter::new())
+            };
+            backend.set_max_depth(max_depth);
+            backend.modules().search_path = module_path;
+
+            let heap = backend.heap();
+            heap.stress = gc_stress;
+            if let Some(threshold) = gc_threshold {
+                heap.set_threshold(threshold);
+            }
+            if let Some(growth) = gc_growth {
+                heap.growth_factor = growth;
+            }
+
+            match args.as_slice() {
+                [] => run_prompt(&mut backend, gc_stats),
+                [command] if command == "lsp" => run_language_server(),
+                [path] => run_file(path, &mut backend, gc_stats),
+                [command, path] if command == "run" => run_file(path, &mut backend, gc_stats),
+                [command, path] if command == "compile" => {
+                    compile_file(path, &Path::new(path).with_extension(COMPILED_EXTENSION))
+                }
+                [command, path, output] if command == "compile" => {
+                    compile_file(path, Path::new(output))
+                }
+                [command, path] if command == "ast" => {
+                    export_ast(path, format.as_deref().unwrap_or("json"))
+                }
+                [command, path] if command == "parse" => match dot {
+                    Some(graph) => parse_file(path, graph),
+                    None => println!("{USAGE}"),
+                },
+                [command, output_directory] if command == "gen-ast" => {
+                    generate_ast(output_directory, check)
+                }
+                [command, paths @ ..] if command == "fmt" && !paths.is_empty() => {
+                    format_files(paths, check)
+                }
+                [command, path] if command == "highlight" => {
+                    highlight_file(path, format.as_deref().unwrap_or("ansi"))
+                }
+                [command, paths @ ..] if command == "lint" && !paths.is_empty() => {
+                    lint_files(paths)
+                }
+                _ => println!("{USAGE}"),
+            }
+        })
+        .unwrap_or_else(|_| {
+            eprintln!("Error: Can't reserve a stack for {max_depth} nested calls.");
+            process::exit(EXIT_USAGE);
+        });
+
+    // a panic has already been reported by the runner thread
+    if runner.join().is_err() {
+        process::exit(EXIT_SOFTWARE);
+    }
+}
+
+fn numeric_flag(value: &str) -> usize {
+    value.parse().unwrap_or_else(|_| {
+        println!("{USAGE}");
+        process::exit(EXIT_USAGE);
+    })
+}
+
+// a file starting with the bytecode magic header is loaded directly, anything else is treated as source code
+fn run_file(path: &String, backend: &mut Backend, gc_stats: bool) {
+    let bytes = fs::read(path).expect("Failed to read from file.");
+    backend.modules().set_script(Path::new(path));
+
+    let result = if serialization::is_bytecode(&bytes) {
+        match serialization::deserialize(&bytes) {
+            Ok(function) => backend.execute_compiled(function),
+            Err(error) => {
+                eprintln!("Error: {error}");
+                process::exit(EXIT_DATA_ERROR);
+            }
+        }
+    } else {
+        let source = String::from_utf8(bytes).expect("Failed to read from file.");
+        run(source, backend)
     };
 
-    let ast_printer = AstPrinter {};
+    if gc_stats {
+        eprintln!("{}", backend.heap().gc_stats());
+    }
+
+    match result {
+        Ok(()) => {}
+        Err(RunError::Compile) => process::exit(EXIT_DATA_ERROR),
+        Err(RunError::Runtime(error)) => {
+            let script = Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            eprint!("{error}\n{}", error.stack_trace(&script));
+            process::exit(EXIT_SOFTWARE);
+        }
+    }
+}
+
+fn compile_file(path: &String, output: &Path) {
+    let source = fs::read_to_string(path).expect("Failed to read from file.");
 
-    println!(
-        "Sample expression AST print: {}",
-        ast_printer.print(Box::new(sample_expression))
-    );
+    let function = ast::parse(source).and_then(|script| compile(&script.statements));
 
-    match args.len() {
-        0 | 1 => run_prompt(),
-        2 => run_file(&args[1]),
-        3 => generate_ast(&args[2]),
-        _ => println!(
-            "Usage: 0x6b73746b [script]\nAST generation: 0x6b73746b ast [output directory]"
-        ),
+    match function {
+        Some(function) => fs::write(output, serialization::serialize(&function))
+            .expect("Failed to write compiled script."),
+        None => process::exit(EXIT_DATA_ERROR),
     }
 }
 
-fn run_file(path: &String) {
-    run(read_to_string(path).expect("Failed to read from file."));
+// rewrites the scripts in the canonical layout, or only reports the ones which aren't in it
+fn format_files(paths: &[String], check: bool) {
+    let mut unformatted = false;
+
+    for path in paths {
+        let source = fs::read_to_string(path).expect("Failed to read from file.");
+        let formatted =
+            formatter::format(&source).unwrap_or_else(|| process::exit(EXIT_DATA_ERROR));
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{path}");
+            unformatted = true;
+        } else {
+            fs::write(path, formatted).expect("Failed to write formatted script.");
+        }
+    }
+
+    if unformatted {
+        process::exit(EXIT_UNFORMATTED);
+    }
+}
+
+// Modules of the AST written from `nodes.grammar`, whose errors are reported like compile errors.
+// With `--check` the modules which differ from the grammar are listed instead.
+fn generate_ast(output_directory: &str, check: bool) {
+    let modules = generate_ast::generate_modules().unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(EXIT_DATA_ERROR);
+    });
+
+    if check {
+        let outdated = generate_ast::outdated_modules(output_directory, &modules);
+        for module in &outdated {
+            println!(
+                "{}",
+                Path::new(output_directory)
+                    .join(&module.file_name)
+                    .display()
+            );
+        }
+        if !outdated.is_empty() {
+            process::exit(EXIT_UNFORMATTED);
+        }
+        return;
+    }
+
+    if let Err(error) = generate_ast::write_modules(output_directory, &modules) {
+        eprintln!("Error: {error}");
+        process::exit(EXIT_IO_ERROR);
+    }
+}
+
+fn parse_file(path: &str, graph: Graph) {
+    let source = fs::read_to_string(path).expect("Failed to read from file.");
+    let script = ast::parse(source).unwrap_or_else(|| process::exit(EXIT_DATA_ERROR));
+
+    match graph {
+        Graph::Ast => print!("{}", export::dot(&script)),
+        Graph::Cfg => print!("{}", cfg::dot(&script.statements)),
+    }
+}
+
+fn export_ast(path: &str, format: &str) {
+    let source = fs::read_to_string(path).expect("Failed to read from file.");
+    let script = ast::parse(source).unwrap_or_else(|| process::exit(EXIT_DATA_ERROR));
+
+    match format {
+        "json" => println!("{}", export::json(&script)),
+        "sexp" => print!("{}", export::sexp(&script)),
+        _ => {
+            println!("{USAGE}");
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+fn highlight_file(path: &str, format: &str) {
+    let source = fs::read_to_string(path).expect("Failed to read from file.");
+
+    match format {
+        "ansi" => print!("{}", highlighter::ansi(&source)),
+        "html" => print!("{}", highlighter::html(&source)),
+        "json" => println!("{}", highlighter::json(&source)),
+        _ => {
+            println!("{USAGE}");
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+fn lint_files(paths: &[String]) {
+    let mut found = false;
+
+    for path in paths {
+        let source = fs::read_to_string(path).expect("Failed to read from file.");
+        let lints = linter::lint(&source).unwrap_or_else(|| process::exit(EXIT_DATA_ERROR));
+
+        for lint in &lints {
+            println!("{path}: {lint}");
+        }
+        found |= !lints.is_empty();
+    }
+
+    if found {
+        process::exit(EXIT_UNFORMATTED);
+    }
+}
+
+fn run_language_server() {
+    match lsp::run() {
+        Ok(code) => process::exit(code),
+        Err(error) => {
+            eprintln!("Error: {error}");
+            process::exit(EXIT_IO_ERROR);
+        }
+    }
 }
 
-fn run(source: String) {
-    let mut lexer = Lexer::new(source);
-    lexer.scan_tokens();
-    println!("Lexical analysis: {:?}", lexer.tokens);
-    let mut parser = Parser::new(lexer.tokens);
-    let expression = parser.parse();
-    let ast_printer = AstPrinter {};
-    println!("{:?}", ast_printer.print(expression));
+fn compile(statements: &[Stmt]) -> Option<Rc<Prototype>> {
+    Compiler::new().compile(statements)
 }
 
-fn run_prompt() {
+fn run(source: String, backend: &mut Backend) -> Result<(), RunError> {
+    let script = ast::parse(source).ok_or(RunError::Compile)?;
+    backend.execute(&script)
+}
+
+fn run_prompt(backend: &mut Backend, gc_stats: bool) {
     let input = stdin();
     let mut reader = stdout();
 
@@ -82,16 +448,16 @@ fn run_prompt() {
 
         let mut line = String::new();
         match input.read_line(&mut line) {
-            Ok(_) => run(line),
-            Err(_) => break,
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if let Err(RunError::Runtime(error)) = run(line, backend) {
+                    eprint!("{error}\n{}", error.stack_trace(REPL_NAME));
+                }
+            }
         }
     }
-}
 
-pub fn error(line: u8, message: String) {
-    report(line, "".to_owned(), message);
-}
-
-fn report(line: u8, source: String, message: String) {
-    println!("[line: {}] Error {}: {}", line, source, message);
+    if gc_stats {
+        eprintln!("{}", backend.heap().gc_stats());
+    }
 }
@@ -0,0 +1,496 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::heap::{self, Trace};
+use super::native::{Native, NativeContext};
+use super::value::Value;
+use crate::symbol::Symbol;
+
+pub struct List {
+    pub elements: RefCell<Vec<Value>>,
+}
+
+impl List {
+    pub fn new(elements: Vec<Value>) -> Self {
+        Self {
+            elements: RefCell::new(elements),
+        }
+    }
+}
+
+// keeps insertion order, so printing a map and iterating its keys is deterministic
+pub struct Map {
+    pub entries: RefCell<Entries>,
+}
+
+impl Map {
+    pub fn new(entries: Entries) -> Self {
+        Self {
+            entries: RefCell::new(entries),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Entries {
+    positions: HashMap<HashKey, usize>,
+    pairs: Vec<(Value, Value)>,
+}
+
+impl Entries {
+    pub fn get(&self, key: &Value) -> Result<Option<Value>, &'static str> {
+        let position = self.positions.get(&HashKey::try_from(key)?);
+        Ok(position.map(|position| self.pairs[*position].1.clone()))
+    }
+
+    pub fn insert(&mut self, key: Value, value: Value) -> Result<(), &'static str> {
+        match self.positions.get(&HashKey::try_from(&key)?) {
+            Some(position) => self.pairs[*position].1 = value,
+            None => {
+                self.positions
+                    .insert(HashKey::try_from(&key)?, self.pairs.len());
+                self.pairs.push((key, value));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.pairs.iter()
+    }
+
+    pub fn key_at(&self, position: usize) -> Option<Value> {
+        self.pairs.get(position).map(|(key, _)| key.clone())
+    }
+}
+
+// built-in iterator over a list, the keys of a map or a range of numbers; its `type_of` is "iterator".
+// Lists and maps are read at the current position on every step, so elements pushed while
+// iterating are visited too.
+pub struct Cursor {
+    pub position: RefCell<Position>,
+}
+
+impl Cursor {
+    pub fn new(position: Position) -> Self {
+        Self {
+            position: RefCell::new(position),
+        }
+    }
+
+    fn has_next(&self) -> bool {
+        match &*self.position.borrow() {
+            Position::List { list, index } => *index < list.elements.borrow().len(),
+            Position::Keys { map, index } => *index < map.entries.borrow().len(),
+            Position::Range { start, end, step } => {
+                if *step > 0.0 {
+                    start < end
+                } else {
+                    start > end
+                }
+            }
+        }
+    }
+
+    fn next(&self) -> Option<Value> {
+        if !self.has_next() {
+            return None;
+        }
+
+        match &mut *self.position.borrow_mut() {
+            Position::List { list, index } => {
+                *index += 1;
+                Some(list.elements.borrow()[*index - 1].clone())
+            }
+            Position::Keys { map, index } => {
+                *index += 1;
+                map.entries.borrow().key_at(*index - 1)
+            }
+            Position::Range { start, step, .. } => {
+                let value = *start;
+                *start += *step;
+                Some(Value::Number(value))
+            }
+        }
+    }
+}
+
+pub enum Position {
+    List { list: Rc<List>, index: usize },
+    Keys { map: Rc<Map>, index: usize },
+    Range { start: f64, end: f64, step: f64 },
+}
+
+// only values compared by content can be map keys, objects compare by identity and may change
+#[derive(PartialEq, Eq, Hash)]
+enum HashKey {
+    Nil,
+    Bool(bool),
+    Number(u64),
+    String(Symbol),
+}
+
+impl TryFrom<&Value> for HashKey {
+    type Error = &'static str;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Nil => Ok(HashKey::Nil),
+            Value::Bool(value) => Ok(HashKey::Bool(*value)),
+            // -0 and 0 are equal, so they have to be the same key
+            Value::Number(value) if *value == 0.0 => Ok(HashKey::Number(0)),
+            Value::Number(value) => Ok(HashKey::Number(value.to_bits())),
+            Value::String(value) => Ok(HashKey::String(value.clone())),
+            _ => Err("Map keys must be numbers, strings, booleans or nil."),
+        }
+    }
+}
+
+// indexing shared by both backends, so they report identical errors
+
+pub fn get_index(object: &Value, index: &Value) -> Result<Value, String> {
+    match object {
+        Value::List(list) => {
+            let elements = list.elements.borrow();
+            let position = list_position(index, elements.len())?;
+            Ok(elements[position].clone())
+        }
+        Value::Map(map) => Ok(map.entries.borrow().get(index)?.unwrap_or(Value::Nil)),
+        _ => Err("Only lists and maps can be indexed.".to_string()),
+    }
+}
+
+pub fn set_index(object: &Value, index: &Value, value: Value) -> Result<(), String> {
+    match object {
+        Value::List(list) => {
+            let mut elements = list.elements.borrow_mut();
+            let position = list_position(index, elements.len())?;
+            elements[position] = value;
+            Ok(())
+        }
+        Value::Map(map) => Ok(map.entries.borrow_mut().insert(index.clone(), value)?),
+        _ => Err("Only lists and maps can be indexed.".to_string()),
+    }
+}
+
+fn list_position(index: &Value, length: usize) -> Result<usize, String> {
+    match index {
+        Value::Number(index) if index.fract() == 0.0 => {
+            if *index < 0.0 || *index >= length as f64 {
+                Err(format!(
+                    "List index {index} out of range for length {length}."
+                ))
+            } else {
+                Ok(*index as usize)
+            }
+        }
+        _ => Err("List index must be an integer.".to_string()),
+    }
+}
+
+thread_local! {
+    static LIST_METHODS: HashMap<Symbol, Rc<Native>> = methods(Vec::from([
+        Native::new("push", 2, list_push),
+        Native::new("pop", 1, list_pop),
+        Native::new("len", 1, list_len),
+        Native::new("iter", 1, iter),
+    ]));
+    static MAP_METHODS: HashMap<Symbol, Rc<Native>> = methods(Vec::from([
+        Native::new("keys", 1, map_keys),
+        Native::new("len", 1, map_len),
+        Native::new("iter", 1, iter),
+    ]));
+    static CURSOR_METHODS: HashMap<Symbol, Rc<Native>> = methods(Vec::from([
+        Native::new("has_next", 1, cursor_has_next),
+        Native::new("next", 1, cursor_next),
+        Native::new("iter", 1, iter),
+    ]));
+}
+
+fn methods(natives: Vec<Native>) -> HashMap<Symbol, Rc<Native>> {
+    natives
+        .into_iter()
+        .map(|native| (native.name.clone(), Rc::new(native)))
+        .collect()
+}
+
+// built-in method of a collection; natives take the receiver as their first argument
+pub fn method(object: &Value, name: &Symbol) -> Option<Rc<Native>> {
+    match object {
+        Value::List(_) => LIST_METHODS.with(|methods| methods.get(name).cloned()),
+        Value::Map(_) => MAP_METHODS.with(|methods| methods.get(name).cloned()),
+        Value::Cursor(_) => CURSOR_METHODS.with(|methods| methods.get(name).cloned()),
+        _ => None,
+    }
+}
+
+// bytes `set_index` adds when it stores a new key in a map, charged before the map grows
+pub fn growth(object: &Value, index: &Value, value: &Value) -> usize {
+    match object {
+        Value::Map(map) if matches!(map.entries.borrow().get(index), Ok(None)) => {
+            heap::value_size(index) + heap::entry_size(value)
+        }
+        _ => 0,
+    }
+}
+
+// error of getting a property of a value which has none; `for ... in` asks the iterable for its
+// `iter` method, so missing that one means the value can't be iterated
+pub fn no_property(object: &Value, name: &str) -> String {
+    match (object, name) {
+        (Value::Nil, "iter") => "Can't iterate over nil.".to_string(),
+        (object, "iter") => {
+            let type_name = object.type_name();
+            let article = match type_name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                true => "an",
+                false => "a",
+            };
+            format!("Can't iterate over {article} {type_name}.")
+        }
+        _ => "Only instances have properties.".to_string(),
+    }
+}
+
+fn list_push(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    if let Value::List(list) = &arguments[0] {
+        context.charge(heap::value_size(&arguments[1]))?;
+        list.elements.borrow_mut().push(arguments[1].clone());
+    }
+    Ok(Value::Nil)
+}
+
+fn list_pop(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::List(list) => list
+            .elements
+            .borrow_mut()
+            .pop()
+            .ok_or_else(|| "Can't pop from an empty list.".to_string()),
+        _ => Ok(Value::Nil),
+    }
+}
+
+fn list_len(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::List(list) => Ok(Value::Number(list.elements.borrow().len() as f64)),
+        _ => Ok(Value::Nil),
+    }
+}
+
+fn map_keys(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::Map(map) => {
+            context.charge(map.entries.borrow().len() * heap::VALUE_SIZE)?;
+            let keys = map
+                .entries
+                .borrow()
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect();
+            Ok(Value::List(context.heap.allocate(List::new(keys))))
+        }
+        _ => Ok(Value::Nil),
+    }
+}
+
+fn map_len(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::Map(map) => Ok(Value::Number(map.entries.borrow().len() as f64)),
+        _ => Ok(Value::Nil),
+    }
+}
+
+// the iterator protocol used by `for (x in iterable)`: iterable.iter() returns an object
+// with has_next() and next() methods; an iterator is iterable itself
+fn iter(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let position = match &arguments[0] {
+        Value::List(list) => Position::List {
+            list: list.clone(),
+            index: 0,
+        },
+        Value::Map(map) => Position::Keys {
+            map: map.clone(),
+            index: 0,
+        },
+        iterator => return Ok(iterator.clone()),
+    };
+
+    Ok(Value::Cursor(context.heap.allocate(Cursor::new(position))))
+}
+
+fn cursor_has_next(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::Cursor(cursor) => Ok(Value::Bool(cursor.has_next())),
+        _ => Ok(Value::Nil),
+    }
+}
+
+fn cursor_next(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::Cursor(cursor) => cursor
+            .next()
+            .ok_or_else(|| "Iterator is exhausted.".to_string()),
+        _ => Ok(Value::Nil),
+    }
+}
+
+impl Trace for List {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        if let Ok(elements) = self.elements.try_borrow() {
+            elements
+                .iter()
+                .for_each(|element| heap::trace_value(element, tracer));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut elements) = self.elements.try_borrow_mut() {
+            let elements = std::mem::take(&mut *elements);
+            drop(elements);
+        }
+    }
+
+    fn size(&self) -> usize {
+        let elements = self.elements.try_borrow();
+        heap::object_size(
+            elements
+                .iter()
+                .flat_map(|elements| elements.iter())
+                .map(heap::value_size),
+        )
+    }
+}
+
+impl Trace for Map {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        if let Ok(entries) = self.entries.try_borrow() {
+            entries
+                .iter()
+                .for_each(|(_, value)| heap::trace_value(value, tracer));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut entries) = self.entries.try_borrow_mut() {
+            let entries = std::mem::take(&mut *entries);
+            drop(entries);
+        }
+    }
+
+    fn size(&self) -> usize {
+        let entries = self.entries.try_borrow();
+        heap::object_size(
+            entries
+                .iter()
+                .flat_map(|entries| entries.iter())
+                .map(|(key, value)| heap::value_size(key) + heap::entry_size(value)),
+        )
+    }
+}
+
+impl Trace for Cursor {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        if let Ok(position) = self.position.try_borrow() {
+            match &*position {
+                Position::List { list, .. } => tracer(heap::address(list)),
+                Position::Keys { map, .. } => tracer(heap::address(map)),
+                Position::Range { .. } => {}
+            }
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut position) = self.position.try_borrow_mut() {
+            let position = std::mem::replace(
+                &mut *position,
+                Position::Range {
+                    start: 0.0,
+                    end: 0.0,
+                    step: 1.0,
+                },
+            );
+            drop(position);
+        }
+    }
+}
+
+thread_local! {
+    // collections being printed, a collection containing itself is printed as `[...]` or `{...}`
+    static PRINTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn write_list(f: &mut fmt::Formatter, list: &Rc<List>) -> fmt::Result {
+    if !enter(heap::address(list)) {
+        return write!(f, "[...]");
+    }
+
+    let result = (|| {
+        write!(f, "[")?;
+        for (position, element) in list.elements.borrow().iter().enumerate() {
+            if position > 0 {
+                write!(f, ", ")?;
+            }
+            write_element(f, element)?;
+        }
+        write!(f, "]")
+    })();
+
+    leave();
+    result
+}
+
+pub fn write_map(f: &mut fmt::Formatter, map: &Rc<Map>) -> fmt::Result {
+    if !enter(heap::address(map)) {
+        return write!(f, "{{...}}");
+    }
+
+    let result = (|| {
+        write!(f, "{{")?;
+        for (position, (key, value)) in map.entries.borrow().iter().enumerate() {
+            if position > 0 {
+                write!(f, ", ")?;
+            }
+            write_element(f, key)?;
+            write!(f, ": ")?;
+            write_element(f, value)?;
+        }
+        write!(f, "}}")
+    })();
+
+    leave();
+    result
+}
+
+// strings inside collections are quoted, so `["1"]` and `[1]` print differently
+fn write_element(f: &mut fmt::Formatter, value: &Value) -> fmt::Result {
+    match value {
+        Value::String(string) => write!(f, "\"{string}\""),
+        value => write!(f, "{value}"),
+    }
+}
+
+fn enter(address: usize) -> bool {
+    PRINTING.with(|printing| {
+        let mut printing = printing.borrow_mut();
+        if printing.contains(&address) {
+            return false;
+        }
+        printing.push(address);
+        true
+    })
+}
+
+fn leave() {
+    PRINTING.with(|printing| printing.borrow_mut().pop());
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::value::Value;
+use crate::symbol::Symbol;
+
+// nested calls allowed by both backends before reporting a stack overflow
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+// Errors raised by the runtime itself are distinguished, so a host application can tell a script
+// which failed from one which was stopped by the sandbox. Stopped scripts can't catch the error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    // raised by the script or by an operation it attempted
+    Runtime,
+    StackOverflow,
+    // a native function whose capability is disabled was called
+    Denied,
+    StepLimit,
+    Timeout,
+    HeapLimit,
+    Cancelled,
+}
+
+impl ErrorKind {
+    pub fn is_fatal(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::StepLimit | ErrorKind::Timeout | ErrorKind::HeapLimit | ErrorKind::Cancelled
+        )
+    }
+}
+
+// function running when an error was raised, and the line it was executing
+#[derive(Clone, Debug)]
+pub struct StackFrame {
+    pub function: Symbol,
+    // file of an imported module, frames of the script itself have none
+    pub file: Option<Symbol>,
+    pub line: usize,
+}
+
+impl StackFrame {
+    // top-level code of the script, below every call
+    pub fn script(line: usize) -> Self {
+        Self {
+            function: Symbol::from("script"),
+            file: None,
+            line,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub line: usize,
+    // value passed to `throw`, errors raised by the runtime itself have none
+    pub value: Option<Value>,
+    // call stack where the error was raised, innermost call first; filled in by the backend
+    pub frames: Vec<StackFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>, line: usize) -> Self {
+        Self {
+            kind: ErrorKind::Runtime,
+            message: message.into(),
+            line,
+            value: None,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    // a thrown error object keeps the message, line and stack trace of the place it was raised at
+    pub fn thrown(value: Value, line: usize) -> Self {
+        let (message, line, frames) = match &value {
+            Value::Exception(exception) => (
+                exception.message.to_string(),
+                exception.line,
+                exception.frames.clone(),
+            ),
+            value => (value.to_string(), line, Vec::new()),
+        };
+
+        Self {
+            kind: ErrorKind::Runtime,
+            message,
+            line,
+            value: Some(value),
+            frames,
+        }
+    }
+
+    // value bound to the variable of a `catch` clause
+    pub fn into_value(self) -> Value {
+        match self.value {
+            Some(value) => value,
+            None => Value::Exception(Rc::new(Exception {
+                message: Symbol::from(self.message),
+                line: self.line,
+                frames: self.frames,
+            })),
+        }
+    }
+
+    // one `at function (file:line)` line per frame, runs of a recursive call are collapsed
+    pub fn stack_trace(&self, script: &str) -> String {
+        let mut trace = String::new();
+        let mut frames = self.frames.iter().peekable();
+
+        while let Some(frame) = frames.next() {
+            let file = frame.file.as_deref().unwrap_or(script);
+            trace += &format!("    at {} ({file}:{})\n", frame.function, frame.line);
+
+            let mut repeated = 0;
+            while frames
+                .next_if(|next| {
+                    next.function == frame.function
+                        && next.file == frame.file
+                        && next.line == frame.line
+                })
+                .is_some()
+            {
+                repeated += 1;
+            }
+            if repeated > 0 {
+                trace += &format!("    ... repeated {repeated} more times\n");
+            }
+        }
+
+        trace
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[line: {}] Error: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+// runtime error caught by a script, its `type_of` is "error"
+pub struct Exception {
+    pub message: Symbol,
+    pub line: usize,
+    pub frames: Vec<StackFrame>,
+}
+
+impl Exception {
+    pub fn property(&self, name: &Symbol) -> Option<Value> {
+        match name.as_str() {
+            "message" => Some(Value::String(self.message.clone())),
+            "line" => Some(Value::Number(self.line as f64)),
+            _ => None,
+        }
+    }
+}
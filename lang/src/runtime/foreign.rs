@@ -0,0 +1,88 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::native::Native;
+use super::value::Value;
+use crate::symbol::Symbol;
+
+// Rust type whose values a host application hands to scripts. Scripts read and write its
+// properties and call its methods like those of an instance; values stored in a native object
+// aren't traced by the garbage collector, so a cycle through one is never freed.
+pub trait NativeClass: Any {
+    // reported by `type_of` and shown when the object is printed
+    fn class_name(&self) -> &'static str;
+
+    // value of `object.name`, none when the object has no such property
+    fn get(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    // handles `object.name = value`, an error message is raised as a runtime error
+    fn set(&mut self, name: &str, _value: Value) -> Result<(), String> {
+        Err(format!("Undefined property '{name}'."))
+    }
+
+    // number of arguments of the method `name`, none when the object has no such method
+    fn arity(&self, _name: &str) -> Option<usize> {
+        None
+    }
+
+    // called only with the number of arguments given by `arity`
+    fn call(&mut self, name: &str, arguments: &[Value]) -> Result<Value, String>;
+}
+
+// native object wrapped as a runtime value
+pub struct Foreign {
+    pub class_name: &'static str,
+    object: RefCell<Box<dyn NativeClass>>,
+}
+
+impl Foreign {
+    pub fn new(object: impl NativeClass) -> Self {
+        Self {
+            class_name: object.class_name(),
+            object: RefCell::new(Box::new(object)),
+        }
+    }
+
+    // properties shadow methods, like fields of an instance
+    pub fn get(&self, name: &Symbol) -> Option<Value> {
+        self.object.try_borrow().ok()?.get(name)
+    }
+
+    pub fn set(&self, name: &Symbol, value: Value) -> Result<(), String> {
+        self.object
+            .try_borrow_mut()
+            .map_err(|_| in_use(name))?
+            .set(name, value)
+    }
+
+    // method as a native taking the receiver as its first argument, like built-in methods of collections
+    pub fn method(&self, name: &Symbol) -> Option<Rc<Native>> {
+        let arity = self.object.try_borrow().ok()?.arity(name)?;
+        let method = name.clone();
+
+        let native = Native::new(name, arity + 1, move |_, arguments| match &arguments[0] {
+            Value::Foreign(foreign) => foreign
+                .object
+                .try_borrow_mut()
+                .map_err(|_| in_use(&method))?
+                .call(&method, &arguments[1..]),
+            _ => unreachable!("Method of a native object bound to another value."),
+        });
+        Some(Rc::new(native))
+    }
+
+    // the wrapped object if it's of type `T`
+    pub fn with<T: NativeClass, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut object = self.object.try_borrow_mut().ok()?;
+        let object: &mut dyn Any = object.as_mut();
+        object.downcast_mut::<T>().map(f)
+    }
+}
+
+// a method got the object it belongs to as an argument and tries to use both
+fn in_use(name: &str) -> String {
+    format!("Can't use '{name}' of an object which is already in use.")
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::rc::{Rc, Weak};
+
+use super::error::ErrorKind;
+use super::value::Value;
+
+pub const DEFAULT_THRESHOLD: usize = 1024;
+pub const DEFAULT_GROWTH_FACTOR: usize = 2;
+
+// approximate sizes in bytes the heap limit is counted in: the reference counts, registry entry and
+// fields of any object, and the hash table slot of a map entry, field or variable besides its value
+const OBJECT_SIZE: usize = 64;
+const ENTRY_SIZE: usize = 16;
+pub const VALUE_SIZE: usize = mem::size_of::<Value>();
+
+// runtime object which may reference other objects and therefore take part in a cycle
+pub trait Trace {
+    // calls `tracer` with the address of every object referenced from this one
+    fn trace(&self, tracer: &mut dyn FnMut(usize));
+    // drops references held in the object's mutable state, called only on unreachable objects to break cycles
+    fn clear(&self);
+    // approximate bytes of the object with the values and strings it holds, but not the objects
+    // they reference
+    fn size(&self) -> usize {
+        OBJECT_SIZE
+    }
+}
+
+// a value in a list or on the stack, with the characters of a string
+pub fn value_size(value: &Value) -> usize {
+    let characters = match value {
+        Value::String(string) => string.len(),
+        _ => 0,
+    };
+    VALUE_SIZE + characters
+}
+
+// a field, variable or, together with the size of its key, map entry
+pub fn entry_size(value: &Value) -> usize {
+    ENTRY_SIZE + value_size(value)
+}
+
+// an object holding values of the given sizes; objects borrowed while they're measured hold none
+pub fn object_size(values: impl Iterator<Item = usize>) -> usize {
+    OBJECT_SIZE + values.sum::<usize>()
+}
+
+pub fn address<T: ?Sized>(object: &Rc<T>) -> usize {
+    Rc::as_ptr(object) as *const () as usize
+}
+
+pub fn trace_value(value: &Value, tracer: &mut dyn FnMut(usize)) {
+    match value {
+        Value::Function(function) => tracer(address(function)),
+        Value::Closure(closure) => tracer(address(closure)),
+        Value::Class(class) => tracer(address(class)),
+        Value::Instance(instance) => tracer(address(instance)),
+        Value::BoundMethod(bound) => tracer(address(bound)),
+        Value::List(list) => tracer(address(list)),
+        Value::Map(map) => tracer(address(map)),
+        Value::Cursor(cursor) => tracer(address(cursor)),
+        Value::Module(module) => tracer(address(module)),
+        Value::Nil
+        | Value::Bool(_)
+        | Value::Number(_)
+        | Value::String(_)
+        | Value::Prototype(_)
+        | Value::Native(_)
+        | Value::Exception(_)
+        | Value::Foreign(_) => {}
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct GcStats {
+    pub collections: usize,
+    pub allocated: usize,
+    pub freed: usize,
+    pub live: usize,
+    pub next_gc: usize,
+}
+
+impl fmt::Display for GcStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "gc: {} collections, {} objects allocated, {} freed, {} live, next collection at {} objects",
+            self.collections, self.allocated, self.freed, self.live, self.next_gc
+        )
+    }
+}
+
+// Registry of every object that can form a reference cycle, with a mark-and-sweep collector.
+// Objects are still reference counted, so acyclic garbage is freed immediately; the collector only
+// reclaims cycles. Roots are all objects referenced from outside the heap - the value stack, call
+// frames, globals, environments in use and values held by Rust code in the middle of evaluation.
+// They're found by comparing the reference count of an object with the number of references to it
+// from other heap objects, so backends don't have to register temporaries.
+pub struct Heap {
+    objects: Vec<Weak<dyn Trace>>,
+    // approximate bytes of live objects, measured by the last collection, and of every object and
+    // string allocated or grown since, which may have been freed already
+    bytes: usize,
+    // bytes which the running script may use, reset by the sandbox when a run starts
+    pub limit: Option<usize>,
+    threshold: usize,
+    next_gc: usize,
+    // collects on every allocation, to find objects which aren't reachable from roots when they should be
+    pub stress: bool,
+    pub growth_factor: usize,
+    stats: GcStats,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            bytes: 0,
+            limit: None,
+            threshold: DEFAULT_THRESHOLD,
+            next_gc: DEFAULT_THRESHOLD,
+            stress: false,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            stats: GcStats::default(),
+        }
+    }
+
+    // number of live objects which triggers the first collection; later ones happen when the heap
+    // grows by `growth_factor` since the last collection, but never below the threshold
+    pub fn set_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold.max(1);
+        self.next_gc = self.threshold;
+    }
+
+    pub fn allocate<T: Trace + 'static>(&mut self, object: T) -> Rc<T> {
+        if self.stress || self.objects.len() >= self.next_gc {
+            self.collect();
+        }
+
+        self.bytes += object.size();
+        let object = Rc::new(object);
+        let weak: Weak<dyn Trace> = Rc::downgrade(&object) as Weak<dyn Trace>;
+        self.objects.push(weak);
+        self.stats.allocated += 1;
+
+        object
+    }
+
+    pub fn collect(&mut self) {
+        let objects: Vec<Rc<dyn Trace>> = self.objects.iter().filter_map(Weak::upgrade).collect();
+        let freed_by_counting = self.objects.len() - objects.len();
+
+        let index: HashMap<usize, usize> = objects
+            .iter()
+            .enumerate()
+            .map(|(position, object)| (address(object), position))
+            .collect();
+
+        let mut internal = vec![0; objects.len()];
+        for object in &objects {
+            object.trace(&mut |child| {
+                if let Some(position) = index.get(&child) {
+                    internal[*position] += 1;
+                }
+            });
+        }
+
+        // one strong reference is held by `objects` above
+        let mut marked = vec![false; objects.len()];
+        let mut gray: Vec<usize> = (0..objects.len())
+            .filter(|position| Rc::strong_count(&objects[*position]) - 1 > internal[*position])
+            .collect();
+        for position in &gray {
+            marked[*position] = true;
+        }
+
+        while let Some(position) = gray.pop() {
+            objects[position].trace(&mut |child| {
+                if let Some(child) = index.get(&child) {
+                    if !marked[*child] {
+                        marked[*child] = true;
+                        gray.push(*child);
+                    }
+                }
+            });
+        }
+
+        let mut freed_by_sweep = 0;
+        self.bytes = 0;
+        for (object, marked) in objects.iter().zip(&marked) {
+            if *marked {
+                self.bytes += object.size();
+            } else {
+                object.clear();
+                freed_by_sweep += 1;
+            }
+        }
+
+        self.objects = objects
+            .iter()
+            .zip(&marked)
+            .filter(|(_, marked)| **marked)
+            .map(|(object, _)| Rc::downgrade(object))
+            .collect();
+        drop(objects);
+
+        self.next_gc = (self.objects.len() * self.growth_factor.max(1)).max(self.threshold);
+        self.stats.collections += 1;
+        self.stats.freed += freed_by_counting + freed_by_sweep;
+    }
+
+    // reserves bytes for a string or collection which is about to be created or to grow, so an
+    // operation which would exceed the limit fails before it allocates anything
+    pub fn charge(&mut self, bytes: usize) -> Result<(), ErrorKind> {
+        if let Some(limit) = self.limit {
+            if self.bytes.saturating_add(bytes) > limit {
+                self.collect();
+            }
+            if self.bytes.saturating_add(bytes) > limit {
+                return Err(ErrorKind::HeapLimit);
+            }
+        }
+
+        self.bytes += bytes;
+        Ok(())
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        let live = self
+            .objects
+            .iter()
+            .filter(|object| object.strong_count() > 0)
+            .count();
+
+        GcStats {
+            live,
+            freed: self.stats.freed + (self.objects.len() - live),
+            next_gc: self.next_gc,
+            ..self.stats
+        }
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,9 @@
+pub mod collection;
+pub mod error;
+pub mod foreign;
+pub mod heap;
+pub mod module;
+pub mod native;
+pub mod object;
+pub mod sandbox;
+pub mod value;
@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::error::RuntimeError;
+use super::heap::{self, Heap, Trace};
+use super::native;
+use super::value::Value;
+use crate::ast::{self, Script};
+use crate::interpreter::environment::Environment;
+use crate::symbol::Symbol;
+
+// Namespace of a script file. Each file has its own global variables, functions keep using the
+// globals of the file they're declared in when called from another one.
+pub struct Module {
+    pub name: Symbol,
+    // file name shown in stack traces, the main script's is given by the caller
+    pub file: Option<Symbol>,
+    pub globals: Rc<RefCell<Environment>>,
+}
+
+impl Module {
+    // globals start with the standard library
+    pub fn new(name: Symbol, file: Option<Symbol>, heap: &mut Heap) -> Self {
+        let mut globals = Environment::new(None);
+        for native in native::standard_library() {
+            globals.define(&native.name.clone(), Value::Native(native));
+        }
+
+        Self {
+            name,
+            file,
+            globals: heap.allocate(RefCell::new(globals)),
+        }
+    }
+
+    pub fn get(&self, name: &Symbol) -> Option<Value> {
+        self.globals.borrow().lookup(name)
+    }
+
+    pub fn define(&self, name: &Symbol, value: Value) {
+        self.globals.borrow_mut().define(name, value);
+    }
+}
+
+impl Trace for Module {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        tracer(heap::address(&self.globals));
+    }
+
+    // immutable, any cycle through it is broken by clearing its globals
+    fn clear(&self) {}
+}
+
+// module which has to run before it can be imported
+pub struct Source {
+    pub name: Symbol,
+    pub file: Symbol,
+    pub script: Script,
+}
+
+pub enum Import {
+    Loaded(Rc<Module>),
+    Source(Source),
+}
+
+// Finds, parses and caches imported files, shared by both backends. A module runs once, on its
+// first import; every `load` returning a source has to be followed by `finish` once it has run.
+#[derive(Default)]
+pub struct ModuleLoader {
+    // directories searched when a module isn't found next to the importing file
+    pub search_path: Vec<PathBuf>,
+    // directories of the files being run, the innermost one is the importing file's
+    directories: Vec<PathBuf>,
+    modules: HashMap<PathBuf, Rc<Module>>,
+    // files being run, importing one of them again is a cycle
+    loading: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    // imports of the main script are relative to its directory, otherwise to the working directory;
+    // the main script runs for as long as the program does, so importing it is a cycle too
+    pub fn set_script(&mut self, path: &Path) {
+        self.directories = Vec::from([directory(path)]);
+        self.loading = path.canonicalize().into_iter().collect();
+    }
+
+    pub fn load(&mut self, path: &str, line: usize) -> Result<Import, RuntimeError> {
+        let file = self.find(path, line)?;
+
+        if let Some(module) = self.modules.get(&file) {
+            return Ok(Import::Loaded(module.clone()));
+        }
+        if self.loading.contains(&file) {
+            return Err(RuntimeError::new(
+                format!("Circular import of module '{path}'."),
+                line,
+            ));
+        }
+
+        let source = fs::read_to_string(&file)
+            .map_err(|_| RuntimeError::new(format!("Can't read module '{path}'."), line))?;
+        let script = ast::parse(source)
+            .ok_or_else(|| RuntimeError::new(format!("Can't compile module '{path}'."), line))?;
+        let name = file
+            .file_stem()
+            .map(|name| Symbol::from(name.to_string_lossy().as_ref()))
+            .unwrap_or_default();
+        let file_name = file
+            .file_name()
+            .map(|name| Symbol::from(name.to_string_lossy().as_ref()))
+            .unwrap_or_default();
+
+        self.directories.push(directory(&file));
+        self.loading.push(file);
+
+        Ok(Import::Source(Source {
+            name,
+            file: file_name,
+            script,
+        }))
+    }
+
+    // caches the module which has run, a failed one is loaded again by the next import
+    pub fn finish(&mut self, module: Option<Rc<Module>>) {
+        self.directories.pop();
+        let file = self.loading.pop().expect("No module is being loaded.");
+
+        if let Some(module) = module {
+            self.modules.insert(file, module);
+        }
+    }
+
+    fn find(&self, path: &str, line: usize) -> Result<PathBuf, RuntimeError> {
+        let base = match self.directories.last() {
+            Some(directory) => directory.clone(),
+            None => PathBuf::from("."),
+        };
+
+        std::iter::once(&base)
+            .chain(&self.search_path)
+            .map(|directory| directory.join(path))
+            .find(|candidate| candidate.is_file())
+            // the same file reached through different paths is one module
+            .and_then(|file| file.canonicalize().ok())
+            .ok_or_else(|| RuntimeError::new(format!("Can't find module '{path}'."), line))
+    }
+}
+
+fn directory(file: &Path) -> PathBuf {
+    match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
@@ -0,0 +1,372 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::collection::{Cursor, List, Position};
+use super::error::{ErrorKind, RuntimeError};
+use super::heap::{self, Heap};
+use super::sandbox::{self, Capability};
+use super::value::Value;
+use crate::symbol::Symbol;
+
+// state of the running backend which natives may use
+pub struct NativeContext<'a> {
+    pub heap: &'a mut Heap,
+    pub random: &'a mut Random,
+    // a charge was refused, so the call stops the script instead of raising a catchable error
+    exhausted: bool,
+}
+
+impl<'a> NativeContext<'a> {
+    pub fn new(heap: &'a mut Heap, random: &'a mut Random) -> Self {
+        Self {
+            heap,
+            random,
+            exhausted: false,
+        }
+    }
+
+    // reserves bytes for a string or collection the native is about to create or grow
+    pub fn charge(&mut self, bytes: usize) -> Result<(), String> {
+        self.heap.charge(bytes).map_err(|kind| {
+            self.exhausted = true;
+            sandbox::exceeded(kind, 0).message
+        })
+    }
+
+    // error of a failed call made at `line`
+    pub fn error(&self, message: String, line: usize) -> RuntimeError {
+        match self.exhausted {
+            true => sandbox::exceeded(ErrorKind::HeapLimit, line),
+            false => RuntimeError::new(message, line),
+        }
+    }
+}
+
+// errors are reported by the backend together with the line of the call
+pub type NativeFn = dyn Fn(&mut NativeContext, &[Value]) -> Result<Value, String>;
+
+// function implemented in Rust and callable from scripts
+pub struct Native {
+    pub name: Symbol,
+    pub arity: usize,
+    // number of arguments which may follow the required ones
+    pub optional: usize,
+    // access to the host system the function needs, checked by the backend before calling it
+    pub capability: Option<Capability>,
+    // a closure, so a host application embedding the interpreter can register its own functions
+    pub function: Box<NativeFn>,
+}
+
+impl Native {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        function: impl Fn(&mut NativeContext, &[Value]) -> Result<Value, String> + 'static,
+    ) -> Self {
+        Self {
+            name: Symbol::from(name),
+            arity,
+            optional: 0,
+            capability: None,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn with_optional(mut self, optional: usize) -> Self {
+        self.optional = optional;
+        self
+    }
+
+    pub fn with_capability(mut self, capability: Capability) -> Self {
+        self.capability = Some(capability);
+        self
+    }
+
+    pub fn call(&self, context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+        self.check_arity(self.arity, arguments.len())?;
+        (self.function)(context, arguments)
+    }
+
+    // built-in methods get the receiver as their first argument, it isn't counted in arity errors
+    pub fn call_method(
+        &self,
+        context: &mut NativeContext,
+        arguments: &[Value],
+    ) -> Result<Value, String> {
+        self.check_arity(self.arity - 1, arguments.len() - 1)?;
+        (self.function)(context, arguments)
+    }
+
+    fn check_arity(&self, arity: usize, count: usize) -> Result<(), String> {
+        if count < arity || count > arity + self.optional {
+            return Err(if self.optional == 0 {
+                format!("Expected {arity} arguments but got {count}.")
+            } else {
+                format!(
+                    "Expected {arity} to {} arguments but got {count}.",
+                    arity + self.optional
+                )
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Native {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+// natives defined as globals by both backends
+pub fn standard_library() -> Vec<Rc<Native>> {
+    Vec::from([
+        Native::new("clock", 0, clock).with_capability(Capability::Clock),
+        Native::new("sqrt", 1, sqrt),
+        Native::new("floor", 1, floor),
+        Native::new("pow", 2, pow),
+        Native::new("random", 0, random),
+        Native::new("seed", 1, seed),
+        Native::new("len", 1, len),
+        Native::new("substr", 3, substr),
+        Native::new("split", 2, split),
+        Native::new("upper", 1, upper),
+        Native::new("trim", 1, trim),
+        Native::new("to_number", 1, to_number),
+        Native::new("to_string", 1, to_string),
+        Native::new("type_of", 1, type_of),
+        Native::new("gc_stats", 0, gc_stats),
+        Native::new("range", 2, range).with_optional(1),
+        Native::new("read_file", 1, read_file).with_capability(Capability::Files),
+        Native::new("write_file", 2, write_file).with_capability(Capability::Files),
+        Native::new("env", 1, env).with_capability(Capability::Environment),
+    ])
+    .into_iter()
+    .map(Rc::new)
+    .collect()
+}
+
+// xorshift64*, good enough for scripts and reproducible once seeded
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(nanos)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        // the state can't be zero, so the seed is mixed first
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^= state >> 31;
+
+        Self {
+            state: state.max(1),
+        }
+    }
+
+    // uniformly distributed in [0, 1)
+    pub fn float(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let bits = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        bits as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn number(arguments: &[Value], index: usize, native: &str) -> Result<f64, String> {
+    match &arguments[index] {
+        Value::Number(value) => Ok(*value),
+        _ => Err(format!("Argument of '{native}' must be a number.")),
+    }
+}
+
+fn string<'a>(arguments: &'a [Value], index: usize, native: &str) -> Result<&'a Symbol, String> {
+    match &arguments[index] {
+        Value::String(value) => Ok(value),
+        _ => Err(format!("Argument of '{native}' must be a string.")),
+    }
+}
+
+fn clock(_: &mut NativeContext, _: &[Value]) -> Result<Value, String> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs_f64())
+        .unwrap_or_default();
+    Ok(Value::Number(seconds))
+}
+
+fn sqrt(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number(arguments, 0, "sqrt")?.sqrt()))
+}
+
+fn floor(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number(arguments, 0, "floor")?.floor()))
+}
+
+fn pow(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let base = number(arguments, 0, "pow")?;
+    let exponent = number(arguments, 1, "pow")?;
+    Ok(Value::Number(base.powf(exponent)))
+}
+
+fn random(context: &mut NativeContext, _: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(context.random.float()))
+}
+
+fn seed(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let seed = number(arguments, 0, "seed")?;
+    *context.random = Random::with_seed(seed.to_bits());
+    Ok(Value::Nil)
+}
+
+fn len(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let length = match &arguments[0] {
+        Value::String(string) => string.chars().count(),
+        Value::List(list) => list.elements.borrow().len(),
+        Value::Map(map) => map.entries.borrow().len(),
+        _ => return Err("Argument of 'len' must be a string, list or map.".to_string()),
+    };
+    Ok(Value::Number(length as f64))
+}
+
+// substr(string, start, length), counted in characters
+fn substr(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let string = string(arguments, 0, "substr")?;
+    let start = number(arguments, 1, "substr")?;
+    let length = number(arguments, 2, "substr")?;
+
+    let count = string.chars().count() as f64;
+    if start.fract() != 0.0 || length.fract() != 0.0 {
+        return Err("Substring bounds must be integers.".to_string());
+    }
+    if start < 0.0 || length < 0.0 || start + length > count {
+        return Err("Substring out of range.".to_string());
+    }
+
+    let substring = string.chars().skip(start as usize).take(length as usize);
+    context.charge(substring.clone().map(char::len_utf8).sum())?;
+    Ok(Value::String(Symbol::from(substring.collect::<String>())))
+}
+
+// an empty separator splits the string into characters
+fn split(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let string = string(arguments, 0, "split")?;
+    let separator = self::string(arguments, 1, "split")?;
+
+    let count = match separator.is_empty() {
+        true => string.chars().count(),
+        false => string.matches(separator.as_str()).count() + 1,
+    };
+    context.charge(count * heap::VALUE_SIZE + string.len())?;
+
+    let parts: Vec<Value> = if separator.is_empty() {
+        string
+            .chars()
+            .map(|character| Value::String(Symbol::from(character.to_string())))
+            .collect()
+    } else {
+        string
+            .split(separator.as_str())
+            .map(|part| Value::String(Symbol::from(part)))
+            .collect()
+    };
+    Ok(Value::List(context.heap.allocate(List::new(parts))))
+}
+
+fn upper(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let string = string(arguments, 0, "upper")?;
+    context.charge(string.len())?;
+    Ok(Value::String(Symbol::from(string.to_uppercase())))
+}
+
+fn trim(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let string = string(arguments, 0, "trim")?;
+    context.charge(string.trim().len())?;
+    Ok(Value::String(Symbol::from(string.trim())))
+}
+
+// nil when the string isn't a number
+fn to_number(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let string = string(arguments, 0, "to_number")?;
+    Ok(match string.trim().parse::<f64>() {
+        Ok(value) => Value::Number(value),
+        Err(_) => Value::Nil,
+    })
+}
+
+fn to_string(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let string = arguments[0].to_string();
+    context.charge(string.len())?;
+    Ok(Value::String(Symbol::from(string)))
+}
+
+fn read_file(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let path = string(arguments, 0, "read_file")?;
+    if let Ok(metadata) = fs::metadata(path.as_str()) {
+        context.charge(metadata.len() as usize)?;
+    }
+    fs::read_to_string(path.as_str())
+        .map(|contents| Value::String(Symbol::from(contents)))
+        .map_err(|_| format!("Can't read file '{path}'."))
+}
+
+fn write_file(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let path = string(arguments, 0, "write_file")?;
+    let contents = string(arguments, 1, "write_file")?;
+    fs::write(path.as_str(), contents.as_str())
+        .map(|()| Value::Nil)
+        .map_err(|_| format!("Can't write file '{path}'."))
+}
+
+// value of an environment variable, nil when it isn't set
+fn env(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let name = string(arguments, 0, "env")?;
+    Ok(env::var(name.as_str())
+        .map(|value| Value::String(Symbol::from(value)))
+        .unwrap_or(Value::Nil))
+}
+
+fn type_of(_: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::String(Symbol::from(arguments[0].type_name())))
+}
+
+// range(start, end, step) iterates from start up to, but not including, end
+fn range(context: &mut NativeContext, arguments: &[Value]) -> Result<Value, String> {
+    let start = number(arguments, 0, "range")?;
+    let end = number(arguments, 1, "range")?;
+    let step = match arguments.get(2) {
+        Some(_) => number(arguments, 2, "range")?,
+        None => 1.0,
+    };
+
+    if step == 0.0 {
+        return Err("Range step can't be zero.".to_string());
+    }
+
+    let cursor = Cursor::new(Position::Range { start, end, step });
+    Ok(Value::Cursor(context.heap.allocate(cursor)))
+}
+
+fn gc_stats(context: &mut NativeContext, _: &[Value]) -> Result<Value, String> {
+    let stats = context.heap.gc_stats();
+    Ok(Value::String(Symbol::from(stats.to_string())))
+}
@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::heap::{self, Trace};
+use super::value::Value;
+use crate::symbol::Symbol;
+
+// classes and instances are shared by both backends; methods are either tree-walk functions or bytecode closures
+pub struct Class {
+    pub name: Symbol,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: RefCell<HashMap<Symbol, Value>>,
+}
+
+impl Class {
+    pub fn new(name: Symbol, superclass: Option<Rc<Class>>) -> Self {
+        Self {
+            name,
+            superclass,
+            methods: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn find_method(&self, name: &Symbol) -> Option<Value> {
+        if let Some(method) = self.methods.borrow().get(name) {
+            return Some(method.clone());
+        }
+
+        match &self.superclass {
+            Some(superclass) => superclass.find_method(name),
+            None => None,
+        }
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<HashMap<Symbol, Value>>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: Value,
+}
+
+impl Trace for Class {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        if let Some(superclass) = &self.superclass {
+            tracer(heap::address(superclass));
+        }
+        if let Ok(methods) = self.methods.try_borrow() {
+            methods
+                .values()
+                .for_each(|method| heap::trace_value(method, tracer));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut methods) = self.methods.try_borrow_mut() {
+            let methods = std::mem::take(&mut *methods);
+            drop(methods);
+        }
+    }
+}
+
+impl Trace for Instance {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        tracer(heap::address(&self.class));
+        if let Ok(fields) = self.fields.try_borrow() {
+            fields
+                .values()
+                .for_each(|field| heap::trace_value(field, tracer));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut fields) = self.fields.try_borrow_mut() {
+            let fields = std::mem::take(&mut *fields);
+            drop(fields);
+        }
+    }
+
+    fn size(&self) -> usize {
+        let fields = self.fields.try_borrow();
+        heap::object_size(
+            fields
+                .iter()
+                .flat_map(|fields| fields.values())
+                .map(heap::entry_size),
+        )
+    }
+}
+
+impl Trace for BoundMethod {
+    fn trace(&self, tracer: &mut dyn FnMut(usize)) {
+        heap::trace_value(&self.receiver, tracer);
+        heap::trace_value(&self.method, tracer);
+    }
+
+    // immutable, any cycle through it is broken by clearing the receiver or the method's closure
+    fn clear(&self) {}
+}
@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::error::{ErrorKind, RuntimeError, DEFAULT_MAX_DEPTH};
+use super::heap::Heap;
+
+// the clock and the cancellation flag are checked once per this many steps
+const CHECK_INTERVAL: u64 = 1024;
+
+// Limits of a single run of a script, i.e. one `eval` of an embedded interpreter. A step is an
+// executed instruction on the vm and an executed statement on the tree-walker.
+#[derive(Clone, Copy)]
+pub struct Limits {
+    pub max_steps: Option<u64>,
+    pub timeout: Option<Duration>,
+    // nested calls
+    pub max_depth: usize,
+    // approximate bytes of live objects, strings and the elements of lists and maps
+    pub max_heap: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            timeout: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_heap: None,
+        }
+    }
+}
+
+// access to the host system granted to native functions, everything is allowed by default
+#[derive(Clone, Copy)]
+pub struct Capabilities {
+    pub files: bool,
+    pub environment: bool,
+    pub clock: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            files: true,
+            environment: true,
+            clock: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Files,
+    Environment,
+    Clock,
+}
+
+// stops the running script from another thread
+#[derive(Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// limits and capabilities of a backend, with the budget of the run in progress
+#[derive(Default)]
+pub struct Sandbox {
+    pub limits: Limits,
+    pub capabilities: Capabilities,
+    cancelled: CancelHandle,
+    steps: u64,
+    next_check: u64,
+    deadline: Option<Instant>,
+}
+
+impl Sandbox {
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancelled.clone()
+    }
+
+    // a cancellation requested before the run starts stops it at its first check
+    pub fn start(&mut self, heap: &mut Heap) {
+        heap.limit = self.limits.max_heap;
+        self.steps = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        self.schedule();
+    }
+
+    // a cancellation requested from now on is for the next run
+    pub fn finish(&mut self) {
+        self.cancelled.0.store(false, Ordering::Relaxed);
+    }
+
+    // counts a step, the error is reported by the backend with the line being executed
+    #[inline]
+    pub fn step(&mut self, heap: &mut Heap) -> Result<(), ErrorKind> {
+        self.steps += 1;
+
+        if self.steps < self.next_check {
+            return Ok(());
+        }
+        self.check(heap)
+    }
+
+    fn check(&mut self, heap: &mut Heap) -> Result<(), ErrorKind> {
+        if self.limits.max_steps.is_some_and(|max| self.steps > max) {
+            return Err(ErrorKind::StepLimit);
+        }
+        // whether the objects allocated so far fit, collecting first if they may not
+        heap.charge(0)?;
+        if self.cancelled.0.load(Ordering::Relaxed) {
+            return Err(ErrorKind::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() > deadline)
+        {
+            return Err(ErrorKind::Timeout);
+        }
+
+        self.schedule();
+        Ok(())
+    }
+
+    // the heap is checked after every step, the step limit exactly when it's reached
+    fn schedule(&mut self) {
+        self.next_check = match self.limits.max_heap {
+            Some(_) => self.steps + 1,
+            None => self.steps + CHECK_INTERVAL,
+        };
+        if let Some(max) = self.limits.max_steps {
+            self.next_check = self.next_check.min(max + 1);
+        }
+    }
+
+    pub fn allow(&self, capability: Option<Capability>, line: usize) -> Result<(), RuntimeError> {
+        let denied = match capability {
+            Some(Capability::Files) if !self.capabilities.files => "File access is disabled.",
+            Some(Capability::Environment) if !self.capabilities.environment => {
+                "Environment access is disabled."
+            }
+            Some(Capability::Clock) if !self.capabilities.clock => "Clock access is disabled.",
+            _ => return Ok(()),
+        };
+
+        Err(RuntimeError::new(denied, line).with_kind(ErrorKind::Denied))
+    }
+}
+
+// error stopping a script which ran out of its budget
+pub fn exceeded(kind: ErrorKind, line: usize) -> RuntimeError {
+    let message = match kind {
+        ErrorKind::StepLimit => "Step limit exceeded.",
+        ErrorKind::Timeout => "Time limit exceeded.",
+        ErrorKind::HeapLimit => "Heap limit exceeded.",
+        ErrorKind::Cancelled => "Script was cancelled.",
+        _ => unreachable!("Budget exceeded with a non-fatal error."),
+    };
+
+    RuntimeError::new(message, line).with_kind(kind)
+}
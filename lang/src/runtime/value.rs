@@ -0,0 +1,200 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::collection::{self, Cursor, List, Map};
+use super::error::Exception;
+use super::foreign::Foreign;
+use super::module::Module;
+use super::native::Native;
+use super::object::{BoundMethod, Class, Instance};
+use crate::ast::literal_value::LiteralValue;
+use crate::bytecode::object::{Closure, Prototype};
+use crate::interpreter::function::Function;
+use crate::symbol::Symbol;
+
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Symbol),
+    // tree-walk interpreter function
+    Function(Rc<Function>),
+    // compiled function before it's wrapped into a closure; lives only in a constant pool
+    Prototype(Rc<Prototype>),
+    Closure(Rc<Closure>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    BoundMethod(Rc<BoundMethod>),
+    Native(Rc<Native>),
+    List(Rc<List>),
+    Map(Rc<Map>),
+    Cursor(Rc<Cursor>),
+    Exception(Rc<Exception>),
+    Module(Rc<Module>),
+    // object of a native class provided by the host application
+    Foreign(Rc<Foreign>),
+}
+
+impl Value {
+    // nil and false are falsey, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_)
+            | Value::Prototype(_)
+            | Value::Closure(_)
+            | Value::BoundMethod(_)
+            | Value::Native(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Cursor(_) => "iterator",
+            Value::Exception(_) => "error",
+            Value::Module(_) => "module",
+            Value::Foreign(foreign) => foreign.class_name,
+        }
+    }
+}
+
+impl From<&LiteralValue> for Value {
+    fn from(literal: &LiteralValue) -> Self {
+        match literal {
+            LiteralValue::Nil => Value::Nil,
+            LiteralValue::Bool(value) => Value::Bool(*value),
+            LiteralValue::Number(value) => Value::Number(*value),
+            LiteralValue::String(value) => Value::String(value.clone()),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Prototype(a), Value::Prototype(b)) => Rc::ptr_eq(a, b),
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::BoundMethod(a), Value::BoundMethod(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Cursor(a), Value::Cursor(b)) => Rc::ptr_eq(a, b),
+            (Value::Exception(a), Value::Exception(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::Foreign(a), Value::Foreign(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Number(value) => write!(f, "{value}"),
+            Value::String(value) => write!(f, "{value}"),
+            Value::Function(function) => write!(f, "<fn {}>", function.name.lexeme),
+            Value::Prototype(function) => write!(f, "{function}"),
+            Value::Closure(closure) => write!(f, "{}", closure.function),
+            Value::Class(class) => write!(f, "{}", class.name),
+            Value::Instance(instance) => write!(f, "{} instance", instance.class.name),
+            Value::BoundMethod(bound) => write!(f, "{}", bound.method),
+            Value::Native(native) => write!(f, "{native}"),
+            Value::List(list) => collection::write_list(f, list),
+            Value::Map(map) => collection::write_map(f, map),
+            Value::Cursor(_) => write!(f, "<iterator>"),
+            Value::Exception(exception) => write!(f, "<error: {}>", exception.message),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+            Value::Foreign(foreign) => write!(f, "{} instance", foreign.class_name),
+        }
+    }
+}
+
+// the variant and the printed value, i.e. `String("abc")` or `Closure(<fn f>)`; objects aren't
+// taken apart
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variant = match self {
+            Value::Nil => return write!(f, "Nil"),
+            Value::Bool(value) => return write!(f, "Bool({value})"),
+            Value::Number(value) => return write!(f, "Number({value:?})"),
+            Value::String(value) => return write!(f, "String({value:?})"),
+            Value::Function(_) => "Function",
+            Value::Prototype(_) => "Prototype",
+            Value::Closure(_) => "Closure",
+            Value::Class(_) => "Class",
+            Value::Instance(_) => "Instance",
+            Value::BoundMethod(_) => "BoundMethod",
+            Value::Native(_) => "Native",
+            Value::List(_) => "List",
+            Value::Map(_) => "Map",
+            Value::Cursor(_) => "Cursor",
+            Value::Exception(_) => "Exception",
+            Value::Module(_) => "Module",
+            Value::Foreign(_) => "Foreign",
+        };
+        write!(f, "{variant}({self})")
+    }
+}
+
+// operators shared by both backends, so they report identical errors
+
+// bytes of the string `add` builds from two strings, charged before it's built
+pub fn concatenation_size(left: &Value, right: &Value) -> usize {
+    match (left, right) {
+        (Value::String(a), Value::String(b)) => a.len() + b.len(),
+        _ => 0,
+    }
+}
+
+pub fn add(left: &Value, right: &Value) -> Result<Value, &'static str> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+        (Value::String(a), Value::String(b)) => Ok(Value::String(Symbol::from(format!("{a}{b}")))),
+        _ => Err("Operands must be two numbers or two strings."),
+    }
+}
+
+pub fn arithmetic(
+    left: &Value,
+    right: &Value,
+    operation: fn(f64, f64) -> f64,
+) -> Result<Value, &'static str> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(operation(*a, *b))),
+        _ => Err("Operands must be numbers."),
+    }
+}
+
+pub fn compare(
+    left: &Value,
+    right: &Value,
+    operation: fn(&f64, &f64) -> bool,
+) -> Result<Value, &'static str> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => Ok(Value::Bool(operation(a, b))),
+        _ => Err("Operands must be numbers."),
+    }
+}
+
+pub fn negate(operand: &Value) -> Result<Value, &'static str> {
+    match operand {
+        Value::Number(value) => Ok(Value::Number(-value)),
+        _ => Err("Operand must be a number."),
+    }
+}
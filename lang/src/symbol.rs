@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+// size of the interner table which triggers the first purge of unused strings
+const PURGE_THRESHOLD: usize = 1024;
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner {
+        strings: HashSet::new(),
+        next_purge: PURGE_THRESHOLD,
+    });
+}
+
+// Every identifier and string value is stored once, so comparing and hashing symbols only looks at
+// the pointer instead of the characters.
+struct Interner {
+    strings: HashSet<Rc<str>>,
+    next_purge: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return interned.clone();
+        }
+
+        if self.strings.len() >= self.next_purge {
+            // strings referenced only by the table aren't used by any symbol anymore
+            self.strings.retain(|string| Rc::strong_count(string) > 1);
+            self.next_purge = (self.strings.len() * 2).max(PURGE_THRESHOLD);
+        }
+
+        let interned: Rc<str> = Rc::from(string);
+        self.strings.insert(interned.clone());
+        interned
+    }
+}
+
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    pub fn intern(string: &str) -> Self {
+        Symbol(INTERNER.with(|interner| interner.borrow_mut().intern(string)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for Symbol {
+    fn default() -> Self {
+        Symbol::intern("")
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(string: &str) -> Self {
+        Symbol::intern(string)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(string: String) -> Self {
+        Symbol::intern(&string)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Rc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", &*self.0)
+    }
+}
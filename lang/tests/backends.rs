@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use lang::ast;
+use lang::bytecode::compiler::Compiler;
+use lang::bytecode::vm::Vm;
+use lang::Lox;
+
+// writer shared with the test while the interpreter owns it
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// output of a script followed by the error which stopped it, if any
+fn run(mut lox: Lox, source: &str) -> String {
+    let output = Output::default();
+    lox.set_output(output.clone());
+
+    let result = lox.eval(source);
+    let mut printed = String::from_utf8(output.0.borrow().clone()).unwrap();
+    if let Err(error) = result {
+        printed += &format!("error: {error}\n");
+    }
+    printed
+}
+
+fn assert_same(source: &str) {
+    let tree = run(Lox::new(), source);
+    let vm = run(Lox::with_vm(), source);
+    assert_eq!(tree, vm, "backends disagree on:\n{source}");
+}
+
+const SCRIPTS: &[&str] = &[
+    "print 1 + 2 * 3; print \"a\" + \"b\"; print !nil; print 7 / 2; print -(3 - 5);",
+    "var a = 1; { var a = 2; print a; } print a;",
+    "fun counter() { var n = 0; fun next() { n = n + 1; return n; } return next; }
+     var next = counter(); next(); print next();",
+    "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } print fib(15);",
+    "class A { init(x) { this.x = x; } get() { return this.x; } }
+     class B < A { get() { return super.get() * 2; } }
+     print B(21).get(); print B; print B(1);",
+    "var total = 0; for (var i = 0; i < 10; i = i + 1) { if (i == 3) continue; if (i == 8) break; total = total + i; } print total;",
+    "var list = [1, 2, 3]; list[1] = 5; print list; print list[1];
+     var map = {\"a\": 1}; map[\"b\"] = 2; print map[\"b\"];",
+    "for (x in [1, 2, 3]) print x * x;",
+    "try { throw \"boom\"; } catch (e) { print \"caught \" + e; } finally { print \"done\"; }",
+    "print undefined;",
+    "var a = \"text\"; a();",
+    "fun f(a, b) {} f(1);",
+    "for (x in \"abc\") print x;",
+    "for (x in nil) print x;",
+    "for (x in 1) print x;",
+];
+
+#[test]
+fn scripts_agree() {
+    for script in SCRIPTS {
+        assert_same(script);
+    }
+}
+
+#[test]
+fn iterating_a_value_without_elements_names_its_type() {
+    for lox in [Lox::new(), Lox::with_vm()] {
+        let output = run(lox, "for (x in \"abc\") print x;");
+        assert!(output.contains("Can't iterate over a string."), "{output}");
+    }
+}
+
+// more globals, constants and locals than fit in a byte operand
+#[test]
+fn wide_operands_agree() {
+    let globals: String = (0..300)
+        .map(|index| format!("var g{index} = {index};\n"))
+        .collect();
+    assert_same(&(globals + "print g0 + g150 + g299;"));
+
+    let literals: Vec<String> = (0..300).map(|index| format!("{index}.5")).collect();
+    assert_same(&format!("print {};", literals.join(" + ")));
+
+    let locals: String = (0..300)
+        .map(|index| format!("var l{index} = {index};\n"))
+        .collect();
+    assert_same(&format!(
+        "fun f() {{\n{locals}fun inner() {{ return l299; }}\nreturn l0 + l299 + inner();\n}}\nprint f();"
+    ));
+
+    let elements: Vec<String> = (0..300).map(|index| index.to_string()).collect();
+    assert_same(&format!(
+        "var list = [{}]; print list[299];",
+        elements.join(", ")
+    ));
+}
+
+#[test]
+fn vm_trace_is_kept_apart_from_output() {
+    let statements = ast::parse("print 1 + 2;".to_string()).unwrap().statements;
+    let function = Compiler::new().compile(&statements).unwrap();
+
+    let output = Output::default();
+    let trace = Output::default();
+    let mut vm = Vm::new();
+    vm.output = Box::new(output.clone());
+    vm.trace = Some(Box::new(trace.clone()));
+    vm.interpret(function).ok().unwrap();
+
+    assert_eq!(String::from_utf8(output.0.borrow().clone()).unwrap(), "3\n");
+    let trace = String::from_utf8(trace.0.borrow().clone()).unwrap();
+    assert!(trace.contains("OP_ADD"), "{trace}");
+    assert!(!trace.contains("\n3\n"), "{trace}");
+}
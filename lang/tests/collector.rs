@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use lang::ast;
+use lang::bytecode::compiler::Compiler;
+use lang::bytecode::vm::Vm;
+use lang::interpreter::Interpreter;
+use lang::runtime::heap::Heap;
+
+// writer shared with the test while the interpreter owns it
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// the backends are driven directly, the embedding API doesn't give access to their heaps
+enum Backend {
+    Tree(Interpreter, Output),
+    Vm(Vm, Output),
+}
+
+impl Backend {
+    fn both() -> [Backend; 2] {
+        let mut interpreter = Interpreter::new();
+        let output = Output::default();
+        interpreter.output = Box::new(output.clone());
+        let tree = Backend::Tree(interpreter, output);
+
+        let mut vm = Vm::new();
+        let output = Output::default();
+        vm.output = Box::new(output.clone());
+        [tree, Backend::Vm(vm, output)]
+    }
+
+    fn heap(&mut self) -> &mut Heap {
+        match self {
+            Backend::Tree(interpreter, _) => &mut interpreter.heap,
+            Backend::Vm(vm, _) => &mut vm.heap,
+        }
+    }
+
+    // output of the script followed by the error which stopped it, if any
+    fn run(&mut self, source: &str) -> String {
+        let script = ast::parse(source.to_string()).unwrap();
+        let (result, output) = match self {
+            Backend::Tree(interpreter, output) => (interpreter.interpret(&script), output),
+            Backend::Vm(vm, output) => {
+                let function = Compiler::new().compile(&script.statements).unwrap();
+                (vm.interpret(function), output)
+            }
+        };
+
+        let mut printed = String::from_utf8(output.0.take()).unwrap();
+        if let Err(error) = result {
+            printed += &format!("error: {error}\n");
+        }
+        printed
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Backend::Tree(..) => "tree",
+            Backend::Vm(..) => "vm",
+        }
+    }
+}
+
+// every call leaves an instance, a list and a closure behind which only refer to themselves
+const CYCLES: &str = "
+class Node { init() { this.me = this; } }
+fun garbage() {
+  var node = Node();
+  var list = [];
+  list.push(list);
+  var closure;
+  fun capture() { return closure; }
+  closure = capture;
+}
+";
+
+#[test]
+fn cycles_are_freed_by_the_collector() {
+    for (mut baseline, mut backend) in Backend::both().into_iter().zip(Backend::both()) {
+        let name = backend.name().to_string();
+        // nothing is collected until the test asks for it
+        baseline.heap().set_threshold(usize::MAX);
+        backend.heap().set_threshold(usize::MAX);
+
+        assert_eq!(baseline.run(CYCLES), "");
+        baseline.heap().collect();
+        let live = baseline.heap().gc_stats().live;
+
+        let loop_source = "for (var i = 0; i < 50; i = i + 1) garbage();";
+        assert_eq!(backend.run(&(CYCLES.to_string() + loop_source)), "");
+        let before = backend.heap().gc_stats();
+        assert_eq!(before.collections, 0, "{name}");
+        assert!(before.live >= live + 150, "{name}: {before}");
+
+        backend.heap().collect();
+        let after = backend.heap().gc_stats();
+        assert_eq!(after.live, live, "{name}: {after}");
+        assert!(after.freed >= before.freed + 150, "{name}: {after}");
+    }
+}
+
+#[test]
+fn live_data_survives_stress_collections() {
+    let first = "
+class Counter {
+  init() { this.count = 0; this.seen = []; }
+  add(x) { this.count = this.count + x; this.seen.push(x); return this; }
+}
+fun make_adder(n) {
+  var box = {\"n\": n};
+  fun add(x) { return box[\"n\"] + x; }
+  return add;
+}
+var counter = Counter();
+var add2 = make_adder(2);
+for (x in [1, 2, 3]) counter.add(add2(x));
+for (k in {\"key\": 1}) counter.seen.push(k);
+try {
+  throw [counter.count, \"thrown\"];
+} catch (error) {
+  print error[1] + \" \" + to_string(error[0]);
+}
+print counter.seen;
+";
+    // globals, instances and closures of the first run are used by the second
+    let second = "
+var iterator = range(0, 3).iter();
+var sum = 0;
+while (iterator.has_next()) sum = sum + counter.add(iterator.next()).count;
+print sum;
+fun guarded() {
+  var kept = [add2];
+  try { return kept; } finally { kept.push(add2(40)); }
+}
+print guarded()[1];
+print counter.seen.len();
+";
+    for mut backend in Backend::both() {
+        backend.heap().stress = true;
+        let name = backend.name().to_string();
+        assert_eq!(
+            backend.run(first),
+            "thrown 12\n[3, 4, 5, \"key\"]\n",
+            "{name}"
+        );
+        assert_eq!(backend.run(second), "40\n42\n7\n", "{name}");
+        assert!(backend.heap().gc_stats().collections > 0, "{name}");
+    }
+}
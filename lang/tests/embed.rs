@@ -0,0 +1,44 @@
+use std::error::Error;
+
+use lang::{Lox, Value};
+
+fn add(source: &str) -> Result<Value, Box<dyn Error>> {
+    let mut lox = Lox::new();
+    lox.eval("fun add(a, b) { return a + b; }")?;
+    Ok(lox.eval(source)?)
+}
+
+#[test]
+fn errors_convert_into_boxed_errors() {
+    assert!(matches!(add("add(1, 2);").unwrap(), Value::Number(sum) if sum == 3.0));
+
+    let error = add("add(1, nil);").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line: 1] Error: Operands must be two numbers or two strings."
+    );
+}
+
+#[test]
+fn compile_errors_have_lines_and_spans() {
+    let source = "var a = 1;\nvar b = ;";
+    match Lox::with_vm().eval(source) {
+        Err(lang::Error::Compile(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].line, 2);
+            let span = errors[0].span.clone().expect("The error has no span.");
+            assert_eq!(&source[span], ";");
+        }
+        result => panic!("expected a compile error, got {result:?}"),
+    }
+}
+
+#[test]
+fn values_are_debug_printed_with_their_variant() {
+    let mut lox = Lox::new();
+    let value = lox.eval("\"a\" + \"b\";").unwrap();
+    assert_eq!(format!("{value:?}"), "String(\"ab\")");
+
+    let value = lox.eval("fun f() {} f;").unwrap();
+    assert_eq!(format!("{value:?}"), "Function(<fn f>)");
+}
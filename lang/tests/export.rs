@@ -0,0 +1,54 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+// parse tree of the source as the `ast` command prints it
+fn export(name: &str, source: &str, format: &str) -> String {
+    let path = env::temp_dir().join(format!("lox-export-{}-{name}.lox", std::process::id()));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_0x6b73746b"))
+        .arg("ast")
+        .arg(format!("--format={format}"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// every change of these is a change of the schema, which has to raise its version
+#[test]
+fn json_export_is_stable() {
+    let source = "fun add(a, b) {\n  return (a + b);\n}\nprint [add(1, 2)];\n";
+    let expected = r#"{"version":3,"statements":[{"type":"Function","span":{"line":1,"start":0,"end":35},"id":5,"keyword":{"lexeme":"fun","line":1,"start":0,"end":3},"name":{"lexeme":"add","line":1,"start":4,"end":7},"params":[{"lexeme":"a","line":1,"start":8,"end":9},{"lexeme":"b","line":1,"start":11,"end":12}],"body":[{"type":"Return","span":{"line":2,"start":18,"end":33},"id":4,"keyword":{"lexeme":"return","line":2,"start":18,"end":24},"value":{"type":"Grouping","span":{"line":2,"start":25,"end":32},"id":3,"paren":{"lexeme":"(","line":2,"start":25,"end":26},"expression":{"type":"Binary","span":{"line":2,"start":26,"end":31},"id":2,"left":{"type":"Variable","span":{"line":2,"start":26,"end":27},"id":0,"name":{"lexeme":"a","line":2,"start":26,"end":27},"depth":0},"operator":{"lexeme":"+","line":2,"start":28,"end":29},"right":{"type":"Variable","span":{"line":2,"start":30,"end":31},"id":1,"name":{"lexeme":"b","line":2,"start":30,"end":31},"depth":0}},"closing_paren":{"lexeme":")","line":2,"start":31,"end":32}},"semicolon":{"lexeme":";","line":2,"start":32,"end":33}}],"closing_brace":{"lexeme":"}","line":3,"start":34,"end":35}},{"type":"Print","span":{"line":4,"start":36,"end":54},"id":11,"keyword":{"lexeme":"print","line":4,"start":36,"end":41},"expression":{"type":"List","span":{"line":4,"start":42,"end":53},"id":10,"bracket":{"lexeme":"[","line":4,"start":42,"end":43},"elements":[{"type":"Call","span":{"line":4,"start":43,"end":52},"id":9,"callee":{"type":"Variable","span":{"line":4,"start":43,"end":46},"id":6,"name":{"lexeme":"add","line":4,"start":43,"end":46},"depth":null},"paren":{"lexeme":")","line":4,"start":51,"end":52},"arguments":[{"type":"Literal","span":{"line":4,"start":47,"end":48},"id":7,"token":{"lexeme":"1","line":4,"start":47,"end":48},"value":1},{"type":"Literal","span":{"line":4,"start":50,"end":51},"id":8,"token":{"lexeme":"2","line":4,"start":50,"end":51},"value":2}]}],"closing_bracket":{"lexeme":"]","line":4,"start":52,"end":53}},"semicolon":{"lexeme":";","line":4,"start":53,"end":54}}]}"#;
+    assert_eq!(export("json", source, "json"), format!("{expected}\n"));
+}
+
+#[test]
+fn sexp_export_is_stable() {
+    let source = "class A < B {\n  init(x) { this.x = x; }\n}\n{\n  var a = {\"k\": 1};\n  try { throw a; } catch (e) {}\n}\n";
+    let expected = [
+        r#"(Class (span 1 0 41) (id 6) (keyword "class") (name "A") (superclass (Variable (span 1 10 11) (id 0) (name "B") (depth nil))) (methods ((Function (span 2 16 39) (id 5) (keyword nil) (name "init") (params ("x")) (body ((Expression (span 2 26 37) (id 4) (expression (Set (span 2 26 36) (id 2) (object (This (span 2 26 30) (id 1) (keyword "this") (depth 1))) (name "x") (value (Variable (span 2 35 36) (id 3) (name "x") (depth 0))))) (semicolon ";")))) (closing_brace "}")))) (closing_brace "}"))"#,
+        r#"(Block (span 4 42 97) (id 14) (brace "{") (statements ((Var (span 5 46 63) (id 10) (keyword "var") (name "a") (initializer (Map (span 5 54 62) (id 9) (brace "{") (keys ((Literal (span 5 55 58) (id 7) (token "\"k\"") (value "k")))) (values ((Literal (span 5 60 61) (id 8) (token "1") (value 1)))) (closing_brace "}"))) (semicolon ";")) (Try (span 6 66 95) (id 13) (keyword "try") (body ((Throw (span 6 72 80) (id 12) (keyword "throw") (value (Variable (span 6 78 79) (id 11) (name "a") (depth 1))) (semicolon ";")))) (catch_name "e") (catch_body ()) (finally_body nil) (closing_brace "}")))) (closing_brace "}"))"#,
+    ];
+    assert_eq!(
+        export("sexp", source, "sexp"),
+        expected.map(|line| line.to_string() + "\n").concat()
+    );
+}
+
+// statements span from their first token to their semicolon or closing brace, and desugared loops
+// span the loop they're made of
+#[test]
+fn spans_cover_whole_statements() {
+    let source = "for (var i = 0; i < 2; i = i + 1) print i;\nfor (x in [1]) { print x; }\n";
+    let spans: Vec<String> = export("spans", source, "sexp")
+        .lines()
+        .map(|line| line.split(") (id").next().unwrap().to_string())
+        .collect();
+    assert_eq!(spans, ["(Block (span 1 0 42", "(Block (span 2 43 70"]);
+}
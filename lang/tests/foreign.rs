@@ -0,0 +1,139 @@
+use std::rc::Rc;
+
+use lang::symbol::Symbol;
+use lang::{Foreign, Lox, NativeClass, Value};
+
+struct Account {
+    owner: String,
+    balance: f64,
+}
+
+impl NativeClass for Account {
+    fn class_name(&self) -> &'static str {
+        "Account"
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "owner" => Some(Value::String(Symbol::from(self.owner.as_str()))),
+            "balance" => Some(Value::Number(self.balance)),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
+        match (name, value) {
+            ("owner", Value::String(owner)) => {
+                self.owner = owner.to_string();
+                Ok(())
+            }
+            ("owner", _) => Err("Owner must be a string.".to_string()),
+            _ => Err(format!("Undefined property '{name}'.")),
+        }
+    }
+
+    fn arity(&self, name: &str) -> Option<usize> {
+        match name {
+            "deposit" => Some(1),
+            "close" => Some(0),
+            _ => None,
+        }
+    }
+
+    fn call(&mut self, name: &str, arguments: &[Value]) -> Result<Value, String> {
+        match (name, arguments) {
+            ("deposit", [Value::Number(amount)]) if *amount > 0.0 => {
+                self.balance += amount;
+                Ok(Value::Number(self.balance))
+            }
+            ("deposit", _) => Err("Deposit must be a positive number.".to_string()),
+            _ => {
+                self.balance = 0.0;
+                Ok(Value::Nil)
+            }
+        }
+    }
+}
+
+// an interpreter of each backend with an account as the global `account`
+fn backends() -> [(Lox, Rc<Foreign>); 2] {
+    [Lox::new(), Lox::with_vm()].map(|mut lox| {
+        let account = Rc::new(Foreign::new(Account {
+            owner: "Ada".to_string(),
+            balance: 10.0,
+        }));
+        lox.set_global("account", Value::Foreign(account.clone()));
+        (lox, account)
+    })
+}
+
+fn number(value: Value) -> f64 {
+    match value {
+        Value::Number(number) => number,
+        value => panic!("expected a number, got {value:?}"),
+    }
+}
+
+#[test]
+fn scripts_use_native_objects_like_instances() {
+    for (mut lox, account) in backends() {
+        assert_eq!(number(lox.eval("account.balance;").unwrap()), 10.0);
+        assert_eq!(number(lox.eval("account.deposit(5);").unwrap()), 15.0);
+        // a method read from the object stays bound to it
+        let source = "var deposit = account.deposit; deposit(2.5); account.balance;";
+        assert_eq!(number(lox.eval(source).unwrap()), 17.5);
+
+        let owner = lox.eval("account.owner = \"Grace\"; account.owner;");
+        assert!(matches!(owner, Ok(Value::String(owner)) if &*owner == "Grace"));
+        let kind = lox.eval("type_of(account);");
+        assert!(matches!(kind, Ok(Value::String(kind)) if &*kind == "Account"));
+
+        let state = account.with(|account: &mut Account| (account.owner.clone(), account.balance));
+        assert_eq!(state, Some(("Grace".to_string(), 17.5)));
+
+        assert!(matches!(lox.eval("account.close();"), Ok(Value::Nil)));
+        assert_eq!(
+            account.with(|account: &mut Account| account.balance),
+            Some(0.0)
+        );
+    }
+}
+
+#[test]
+fn with_hands_back_only_the_wrapped_type() {
+    struct Other;
+    impl NativeClass for Other {
+        fn class_name(&self) -> &'static str {
+            "Other"
+        }
+
+        fn call(&mut self, _name: &str, _arguments: &[Value]) -> Result<Value, String> {
+            Ok(Value::Nil)
+        }
+    }
+
+    let (_, account) = backends().into_iter().next().unwrap();
+    assert_eq!(account.with(|_: &mut Other| ()), None);
+}
+
+#[test]
+fn missing_members_and_refusals_are_runtime_errors() {
+    let cases = [
+        ("account.missing;", "Undefined property 'missing'."),
+        ("account.missing();", "Undefined property 'missing'."),
+        ("account.balance = 1;", "Undefined property 'balance'."),
+        ("account.owner = 1;", "Owner must be a string."),
+        ("account.deposit(-1);", "Deposit must be a positive number."),
+        ("account.deposit();", "Expected 1 arguments but got 0."),
+    ];
+    for (mut lox, _) in backends() {
+        for (source, message) in cases {
+            let error = lox.eval(&format!("\n{source}")).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("[line: 2] Error: {message}"),
+                "{source}"
+            );
+        }
+    }
+}
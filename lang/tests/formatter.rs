@@ -0,0 +1,90 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use lang::ast::formatter::format;
+
+const CORPUS: &[&str] = &[
+    "var a=1;var b =  a+2 ;print a*(b-1);",
+    "// header\n\nfun add(a, b) { // adds\n  return a + b;\n}\n\n\n// footer\n",
+    "for (var i = 0; i < 10; i = i + 1) print i;\nfor (;;) { break; }\nfor (i = 0; i < 3;) { i = i + 1; }",
+    "var sum = 0;\nfor (x in [1, 2, 3]) {\n  // add it\n  sum = sum + x;\n}\nfor (k in {\"a\": 1}) print k;",
+    "print some_function_with_a_long_name(first_argument, second_argument, third_argument, 42);",
+    "var nested = outer(inner(first_argument_of_inner, second_argument_of_inner), [1, 2, 3, 4, 5, 6, 7]);",
+    "var map = {\"first key\": \"first value\", \"second key\": \"second value\", \"third\": 3};",
+    "call(1, // one\n  2, // two\n  3);",
+    "call(\n  // first\n  1,\n  [2, // two\n  3]\n  // last\n);",
+    "if (a) {\n  print 1;\n}\n// otherwise\nelse {\n  print 2;\n}",
+    "if (a) {\n  print 1;\n} // done\nelse if (b) print 2; // two\n// three\nelse print 3;",
+    "class A < B { init(x) { this.x = x; } // init\n  get() { return super.get() + this.x; } }",
+    "try { throw \"boom\"; } catch (e) { print e; } finally {\n  // cleanup\n}",
+    "import \"lib\" as lib;\nfrom \"lib\" import a, b;\nwhile (true) { if (lib.done()) break; else continue; }",
+];
+
+#[test]
+fn formatting_is_idempotent() {
+    for source in CORPUS {
+        let once = format(source).unwrap_or_else(|| panic!("can't format:\n{source}"));
+        let twice = format(&once).unwrap();
+        assert_eq!(once, twice, "formatting again changed:\n{source}");
+
+        let comments = |text: &str| text.matches("//").count();
+        assert_eq!(
+            comments(source),
+            comments(&once),
+            "lost a comment:\n{source}"
+        );
+    }
+}
+
+#[test]
+fn comments_stay_by_their_arguments() {
+    let formatted = format("call(1, // one\n  // two\n  2);").unwrap();
+    assert_eq!(formatted, "call(\n  1, // one\n  // two\n  2\n);\n");
+}
+
+#[test]
+fn comments_before_else_stay_above_it() {
+    let formatted = format("if (a) {\n  print 1;\n}\n// otherwise\nelse {\n  print 2;\n}").unwrap();
+    assert_eq!(
+        formatted,
+        "if (a) {\n  print 1;\n}\n// otherwise\nelse {\n  print 2;\n}\n"
+    );
+}
+
+// script in a directory of its own for the test
+fn script(name: &str, source: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("lox-fmt-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn check(path: &PathBuf) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_0x6b73746b"))
+        .args(["fmt", "--check"])
+        .arg(path)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn check_reports_unformatted_scripts() {
+    let formatted = script("formatted.lox", "print 1;\n");
+    assert_eq!(check(&formatted), (Some(0), String::new()));
+
+    let unformatted = script("unformatted.lox", "print   1 ;");
+    let (code, printed) = check(&unformatted);
+    assert_eq!(code, Some(1));
+    assert_eq!(printed.trim(), unformatted.display().to_string());
+    assert_eq!(fs::read_to_string(&unformatted).unwrap(), "print   1 ;");
+
+    let invalid = script("invalid.lox", "print ;");
+    assert_eq!(check(&invalid).0, Some(65));
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use lang::ast::generate_ast;
+
+// the checked-in modules are what `gen-ast src/ast` writes from the grammar
+#[test]
+fn ast_matches_grammar() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/ast");
+    let modules = generate_ast::generate_modules().unwrap();
+
+    let outdated: Vec<&str> = generate_ast::outdated_modules(directory.to_str().unwrap(), &modules)
+        .iter()
+        .map(|module| module.file_name.as_str())
+        .collect();
+    assert!(
+        outdated.is_empty(),
+        "{outdated:?} differ from nodes.grammar, run `0x6b73746b gen-ast src/ast`"
+    );
+}
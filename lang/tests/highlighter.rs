@@ -0,0 +1,125 @@
+use lang::ast::highlighter::{highlight, html, json, Highlight};
+use lang::json::Json;
+
+#[test]
+fn html_escapes_text() {
+    let source = "print \"<a & b>\" < 1; // <&>\n";
+    assert_eq!(
+        html(source),
+        concat!(
+            "<pre class=\"lox\"><code>",
+            "<span class=\"keyword\">print</span> ",
+            "<span class=\"string\">&quot;&lt;a &amp; b&gt;&quot;</span> ",
+            "<span class=\"operator\">&lt;</span> ",
+            "<span class=\"number\">1</span><span class=\"punctuation\">;</span> ",
+            "<span class=\"comment\">// &lt;&amp;&gt;</span>\n",
+            "</code></pre>\n"
+        )
+    );
+}
+
+// names are highlighted by what they refer to, with the names of declarations marked
+#[test]
+fn names_are_told_apart_by_their_declarations() {
+    let source = "var total = 0;
+class Box {
+  get(item) {
+    var copy = item;
+    return copy.size;
+  }
+}
+fun add(x) {
+  total = total + x;
+  return Box;
+}
+import \"lib.lox\" as lib;
+print clock;
+";
+    let chars: Vec<char> = source.chars().collect();
+    let names: Vec<(String, &str, bool)> = highlight(source)
+        .into_iter()
+        .filter(|span| {
+            !matches!(
+                span.highlight,
+                Highlight::Keyword
+                    | Highlight::Constant
+                    | Highlight::Number
+                    | Highlight::String
+                    | Highlight::Comment
+                    | Highlight::Operator
+                    | Highlight::Punctuation
+            )
+        })
+        .map(|span| {
+            (
+                chars[span.range].iter().collect(),
+                span.highlight.name(),
+                span.declaration,
+            )
+        })
+        .collect();
+
+    let expected = [
+        ("total", "global", true),
+        ("Box", "class", true),
+        ("get", "method", true),
+        ("item", "parameter", true),
+        ("copy", "local", true),
+        ("item", "parameter", false),
+        ("copy", "local", false),
+        ("size", "property", false),
+        ("add", "function", true),
+        ("x", "parameter", true),
+        ("total", "global", false),
+        ("total", "global", false),
+        ("x", "parameter", false),
+        ("Box", "class", false),
+        ("lib", "module", true),
+        ("clock", "global", false),
+    ];
+    let expected: Vec<(String, &str, bool)> = expected
+        .into_iter()
+        .map(|(name, class, declaration)| (name.to_string(), class, declaration))
+        .collect();
+    assert_eq!(names, expected);
+}
+
+// lines and columns count from one and, like offsets and lengths, count characters rather than bytes
+#[test]
+fn json_positions_count_characters() {
+    let source = "var s = \"ünï\";\n// ç ≠ c\n  print s;\n";
+    let tokens = json(source);
+    let positions: Vec<(usize, usize, usize, usize, &str)> = tokens
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|token| {
+            let number = |key: &str| token.get(key).as_usize().unwrap();
+            (
+                number("line"),
+                number("column"),
+                number("offset"),
+                number("length"),
+                token.get("type").as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            (1, 1, 0, 3, "keyword"),
+            (1, 5, 4, 1, "global"),
+            (1, 7, 6, 1, "operator"),
+            (1, 9, 8, 5, "string"),
+            (1, 14, 13, 1, "punctuation"),
+            (2, 1, 15, 8, "comment"),
+            (3, 3, 26, 5, "keyword"),
+            (3, 9, 32, 1, "global"),
+            (3, 10, 33, 1, "punctuation"),
+        ]
+    );
+
+    let modifiers = |index: usize| tokens.as_array().unwrap()[index].get("modifiers").clone();
+    assert_eq!(modifiers(1), Json::from(vec![Json::from("declaration")]));
+    assert_eq!(modifiers(7), Json::from(Vec::<Json>::new()));
+}
@@ -0,0 +1,91 @@
+use lang::ast::linter::lint;
+
+// every lint of the source as it's printed, with the rule which found it
+fn lints(source: &str) -> Vec<String> {
+    lint(source)
+        .unwrap()
+        .iter()
+        .map(|lint| lint.to_string())
+        .collect()
+}
+
+#[test]
+fn each_rule_reports_its_message_and_line() {
+    let source = "fun f(a, b) {
+  var c = 1;
+  var d = 2;
+  {
+    var d = 3;
+    print d;
+  }
+  return d;
+  print a;
+}
+var x = 1;
+if (x == x) print 1;
+if (1 < 2) print 2;
+while (x = 2) print 3;
+print f;
+";
+    assert_eq!(
+        lints(source),
+        [
+            "[line: 1] Error at 'b': Parameter 'b' is never used. [unused-parameter]",
+            "[line: 2] Error at 'c': Variable 'c' is never used. [unused-variable]",
+            "[line: 5] Error at 'd': 'd' shadows the declaration on line 3. [shadowing]",
+            "[line: 9] Error: Unreachable code. [unreachable-code]",
+            "[line: 12] Error at '==': 'x' is compared with itself. [self-comparison]",
+            "[line: 13] Error at 'if': Condition is always the same. [constant-condition]",
+            "[line: 14] Error at 'x': Assignment in a condition, use '==' to compare. [assignment-in-condition]",
+        ]
+    );
+}
+
+// a directive on its own line lasts until the next one, after code it applies to its line only
+#[test]
+fn directives_disable_and_enable_rules() {
+    let source = "fun f() {
+  // lint: disable unused-variable
+  var a = 1;
+  var b = 2;
+  // lint: enable unused-variable
+  var c = 3;
+  var d = 4; // lint: disable unused-variable
+  var e = 5;
+  var g = 6; // lint: disable
+  // lint: disable
+  var h = 7;
+  var i = 8; // lint: enable unused-variable
+}
+";
+    assert_eq!(
+        lints(source),
+        [
+            "[line: 6] Error at 'c': Variable 'c' is never used. [unused-variable]",
+            "[line: 8] Error at 'e': Variable 'e' is never used. [unused-variable]",
+            "[line: 12] Error at 'i': Variable 'i' is never used. [unused-variable]",
+        ]
+    );
+}
+
+#[test]
+fn unknown_directives_are_errors() {
+    assert!(lint("var a = 1; // lint: ignore\n").is_none());
+    assert!(lint("// lint: disable unused-variable, unused-everything\n").is_none());
+}
+
+// names starting with `_`, globals, `while (true)` and assignments in extra parentheses are
+// written that way on purpose
+#[test]
+fn exemptions_are_not_reported() {
+    let source = "var unused_global = 1;
+fun g(_ignored) {
+  var _scratch = 2;
+  while (true) {
+    if ((unused_global = 3)) break;
+  }
+  for (;;) break;
+}
+";
+    assert_eq!(lints(source), Vec::<String>::new());
+}
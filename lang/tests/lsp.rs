@@ -0,0 +1,240 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use lang::json::Json;
+
+const URI: &str = "file:///counter.lox";
+const BROKEN: &str = "var count = 1;\nprint count +;\n";
+const FIXED: &str = "var count = 1;\nprint count;\ncount = count + 1;\n";
+
+fn frame(message: Json) -> String {
+    let content = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{content}", content.len())
+}
+
+fn request(id: usize, method: &str, params: Json) -> String {
+    frame(Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ]))
+}
+
+fn notification(method: &str, params: Json) -> String {
+    frame(Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ]))
+}
+
+// parameters of a request about the name at the position
+fn at(line: usize, character: usize) -> Json {
+    Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+        (
+            "position",
+            Json::object([("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+fn rename(new_name: &str) -> Json {
+    let Json::Object(mut params) = at(2, 8) else {
+        unreachable!();
+    };
+    params.push(("newName".to_string(), new_name.into()));
+    Json::Object(params)
+}
+
+// messages the server wrote, split at their Content-Length headers
+fn messages(mut output: &str) -> Vec<Json> {
+    let mut messages = Vec::new();
+    while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+        let length: usize = header["Content-Length: ".len()..].parse().unwrap();
+        messages.push(Json::parse(&rest[..length]).unwrap());
+        output = &rest[length..];
+    }
+    messages
+}
+
+// runs a session with the server over pipes, returns its messages and its exit code
+fn session(input: &[String]) -> (Vec<Json>, Option<i32>) {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_0x6b73746b"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    server
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.concat().as_bytes())
+        .unwrap();
+
+    let output = server.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (messages(&stdout), output.status.code())
+}
+
+fn response(messages: &[Json], id: usize) -> &Json {
+    messages
+        .iter()
+        .find(|message| message.get("id").as_usize() == Some(id))
+        .unwrap_or_else(|| panic!("no response to request {id}"))
+}
+
+// start line and character of a range
+fn start(range: &Json) -> (usize, usize) {
+    bounds(range).0
+}
+
+// start and end line and character of a range
+fn bounds(range: &Json) -> ((usize, usize), (usize, usize)) {
+    let position = |position: &Json| {
+        (
+            position.get("line").as_usize().unwrap(),
+            position.get("character").as_usize().unwrap(),
+        )
+    };
+    (position(range.get("start")), position(range.get("end")))
+}
+
+fn document(text: &str) -> Json {
+    Json::object([
+        ("uri", URI.into()),
+        ("languageId", "lox".into()),
+        ("version", 1.0.into()),
+        ("text", text.into()),
+    ])
+}
+
+#[test]
+fn session_over_pipes() {
+    let input = [
+        request(1, "initialize", Json::object([])),
+        notification("initialized", Json::object([])),
+        notification(
+            "textDocument/didOpen",
+            Json::object([("textDocument", document(BROKEN))]),
+        ),
+        notification(
+            "textDocument/didChange",
+            Json::object([
+                ("textDocument", Json::object([("uri", URI.into())])),
+                (
+                    "contentChanges",
+                    vec![Json::object([("text", FIXED.into())])].into(),
+                ),
+            ]),
+        ),
+        request(2, "textDocument/definition", at(2, 8)),
+        request(3, "textDocument/references", at(1, 6)),
+        request(4, "textDocument/rename", rename("total")),
+        request(5, "textDocument/rename", rename("1x")),
+        request(6, "shutdown", Json::Null),
+        notification("exit", Json::Null),
+    ];
+    let (messages, code) = session(&input);
+    assert_eq!(code, Some(0));
+
+    let capabilities = response(&messages, 1).get("result").get("capabilities");
+    for capability in ["definitionProvider", "referencesProvider", "renameProvider"] {
+        assert_eq!(capabilities.get(capability).as_bool(), Some(true));
+    }
+
+    // the broken document has an error on its second line, the fixed one none
+    let diagnostics: Vec<&[Json]> = messages
+        .iter()
+        .filter(|message| message.get("method").as_str() == Some("textDocument/publishDiagnostics"))
+        .map(|message| message.get("params").get("diagnostics").as_array().unwrap())
+        .collect();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].len(), 1);
+    assert_eq!(start(diagnostics[0][0].get("range")).0, 1);
+    assert!(diagnostics[1].is_empty());
+
+    let definition = response(&messages, 2).get("result");
+    assert_eq!(definition.get("uri").as_str(), Some(URI));
+    assert_eq!(start(definition.get("range")), (0, 4));
+
+    let references = response(&messages, 3).get("result").as_array().unwrap();
+    let mut starts: Vec<(usize, usize)> = references
+        .iter()
+        .map(|reference| start(reference.get("range")))
+        .collect();
+    starts.sort();
+    assert_eq!(starts, [(0, 4), (1, 6), (2, 0), (2, 8)]);
+
+    let edits = response(&messages, 4)
+        .get("result")
+        .get("changes")
+        .get(URI)
+        .as_array()
+        .unwrap();
+    assert_eq!(edits.len(), 4);
+    assert!(edits
+        .iter()
+        .all(|edit| edit.get("newText").as_str() == Some("total")));
+
+    let error = response(&messages, 5).get("error");
+    assert_eq!(error.get("code").as_f64(), Some(-32602.0));
+}
+
+#[test]
+fn exit_without_shutdown_fails() {
+    let input = [
+        request(1, "initialize", Json::object([])),
+        notification("exit", Json::Null),
+    ];
+    let (messages, code) = session(&input);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(code, Some(1));
+}
+
+// symbols span their whole declaration and select its name
+#[test]
+fn document_symbols_span_their_declarations() {
+    let text =
+        "var count = 0;\nfun add(a, b) {\n  return a + b;\n}\nclass Counter {\n  add() {}\n}\n";
+    let input = [
+        request(1, "initialize", Json::object([])),
+        notification(
+            "textDocument/didOpen",
+            Json::object([("textDocument", document(text))]),
+        ),
+        request(
+            2,
+            "textDocument/documentSymbol",
+            Json::object([("textDocument", Json::object([("uri", URI.into())]))]),
+        ),
+        request(3, "shutdown", Json::Null),
+        notification("exit", Json::Null),
+    ];
+    let (messages, _) = session(&input);
+
+    let symbols = response(&messages, 2).get("result").as_array().unwrap();
+    let ranges: Vec<(&str, _, _)> = symbols
+        .iter()
+        .map(|symbol| {
+            (
+                symbol.get("name").as_str().unwrap(),
+                bounds(symbol.get("range")),
+                bounds(symbol.get("selectionRange")),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            ("count", ((0, 0), (0, 14)), ((0, 4), (0, 9))),
+            ("add", ((1, 0), (3, 1)), ((1, 4), (1, 7))),
+            ("Counter", ((4, 0), (6, 1)), ((4, 6), (4, 13))),
+        ]
+    );
+
+    let method = &symbols[2].get("children").as_array().unwrap()[0];
+    assert_eq!(bounds(method.get("range")), ((5, 2), (5, 10)));
+}
@@ -0,0 +1,166 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const BACKENDS: [&str; 2] = ["--backend=tree", "--backend=vm"];
+
+// files of a test in a directory of their own
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = env::temp_dir().join(format!("lox-modules-{}-{name}", std::process::id()));
+    for (path, source) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    directory
+}
+
+// what the script printed and its exit code
+fn run(backend: &str, script: &Path, flags: &[String]) -> (String, String, Option<i32>) {
+    let output = Command::new(env!("CARGO_BIN_EXE_0x6b73746b"))
+        .arg(backend)
+        .args(flags)
+        .arg(script)
+        .output()
+        .unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn imported_globals_are_used_through_the_module_or_by_name() {
+    let directory = project(
+        "globals",
+        &[
+            (
+                "main.lox",
+                "import \"lib/shapes.lox\" as shapes;\nfrom \"lib/shapes.lox\" import area, Square;\nprint shapes.area(2);\nprint area(3);\nprint Square(4).side;\nprint shapes;",
+            ),
+            (
+                "lib/shapes.lox",
+                "var scale = 10;\nfun area(side) { return side * side * scale; }\nclass Square { init(side) { this.side = side; } }",
+            ),
+        ],
+    );
+    for backend in BACKENDS {
+        let (printed, errors, code) = run(backend, &directory.join("main.lox"), &[]);
+        assert_eq!(
+            printed, "40\n90\n4\n<module shapes>\n",
+            "{backend}: {errors}"
+        );
+        assert_eq!(code, Some(0));
+    }
+}
+
+// every importer gets the same module, which ran on the first import only
+#[test]
+fn modules_run_once() {
+    let directory = project(
+        "once",
+        &[
+            (
+                "main.lox",
+                "import \"counter.lox\" as counter;\nimport \"user.lox\" as user;\ncounter.increment();\nprint counter.count;",
+            ),
+            ("user.lox", "import \"counter.lox\" as counter;\ncounter.increment();"),
+            (
+                "counter.lox",
+                "print \"counter runs\";\nvar count = 0;\nfun increment() { count = count + 1; }",
+            ),
+        ],
+    );
+    for backend in BACKENDS {
+        let (printed, errors, code) = run(backend, &directory.join("main.lox"), &[]);
+        assert_eq!(printed, "counter runs\n2\n", "{backend}: {errors}");
+        assert_eq!(code, Some(0));
+    }
+}
+
+#[test]
+fn circular_imports_are_runtime_errors() {
+    let directory = project(
+        "cycle",
+        &[
+            ("main.lox", "import \"first.lox\" as first;"),
+            ("first.lox", "import \"second.lox\" as second;"),
+            ("second.lox", "import \"first.lox\" as first;"),
+        ],
+    );
+    for backend in BACKENDS {
+        let (_, errors, code) = run(backend, &directory.join("main.lox"), &[]);
+        assert_eq!(
+            errors.lines().next(),
+            Some("[line: 1] Error: Circular import of module 'first.lox'.")
+        );
+        assert_eq!(code, Some(70));
+    }
+}
+
+// the main script is running as long as the program does, so it can't be imported either
+#[test]
+fn importing_the_main_script_is_circular() {
+    let directory = project(
+        "main-cycle",
+        &[
+            (
+                "main.lox",
+                "print \"main runs\";\nimport \"helper.lox\" as helper;",
+            ),
+            ("helper.lox", "import \"main.lox\" as main;"),
+        ],
+    );
+    for backend in BACKENDS {
+        let (printed, errors, code) = run(backend, &directory.join("main.lox"), &[]);
+        assert_eq!(printed, "main runs\n");
+        assert_eq!(
+            errors.lines().next(),
+            Some("[line: 1] Error: Circular import of module 'main.lox'.")
+        );
+        assert_eq!(code, Some(70));
+    }
+}
+
+#[test]
+fn module_path_is_searched_after_the_importing_directory() {
+    let directory = project(
+        "path",
+        &[
+            (
+                "app/main.lox",
+                "import \"greeting.lox\" as greeting;\nprint greeting.text;",
+            ),
+            ("app/greeting.lox", "var text = \"next to the script\";"),
+            (
+                "app/other.lox",
+                "import \"shared.lox\" as shared;\nprint shared.text;",
+            ),
+            (
+                "library/greeting.lox",
+                "var text = \"from the module path\";",
+            ),
+            ("library/shared.lox", "var text = \"from the module path\";"),
+        ],
+    );
+    let module_path = [format!(
+        "--module-path={}",
+        directory.join("library").display()
+    )];
+    for backend in BACKENDS {
+        let (_, errors, code) = run(backend, &directory.join("app/other.lox"), &[]);
+        assert_eq!(
+            errors.lines().next(),
+            Some("[line: 1] Error: Can't find module 'shared.lox'.")
+        );
+        assert_eq!(code, Some(70));
+
+        let (printed, _, _) = run(backend, &directory.join("app/other.lox"), &module_path);
+        assert_eq!(printed, "from the module path\n");
+
+        let (printed, _, _) = run(backend, &directory.join("app/main.lox"), &module_path);
+        assert_eq!(printed, "next to the script\n");
+    }
+}
@@ -0,0 +1,75 @@
+use lang::ast::expression::{self, Expr};
+use lang::ast::node_id::NodeId;
+use lang::ast::statement::{self, Stmt};
+
+const SOURCE: &str = "
+class A < B {
+    m(x) {
+        if (x) print this.y; else return super.m(-x);
+    }
+}
+var xs = [1, {\"k\": 2}];
+for (var i = 0; i < 3; i = i + 1) xs[0] = xs[0] + i;
+for (x in xs) { try { throw x; } catch (e) { print e; } finally { break; } }
+";
+
+// ids of every node of a script, in the order they're walked
+#[derive(Default)]
+struct Ids(Vec<NodeId>);
+
+impl expression::Walker for Ids {
+    fn walk_expr(&mut self, expr: &Expr) {
+        self.0.push(expr.id());
+        expression::walk_expr(self, expr);
+    }
+}
+
+impl statement::Walker for Ids {
+    fn walk_stmt(&mut self, stmt: &Stmt) {
+        self.0.push(stmt.id());
+        statement::walk_stmt(self, stmt);
+    }
+
+    // superclasses aren't walked as `Expr`s, nor methods as `Stmt`s
+    fn walk_class_stmt(&mut self, stmt: &statement::Class) {
+        self.0
+            .extend(stmt.superclass.as_ref().map(|superclass| superclass.id));
+        statement::walk_class_stmt(self, stmt);
+    }
+
+    fn walk_function_stmt(&mut self, stmt: &statement::Function) {
+        self.0.push(stmt.id);
+        statement::walk_function_stmt(self, stmt);
+    }
+}
+
+fn ids(source: &str) -> Vec<NodeId> {
+    let statements = lang::ast::parse(source.to_string()).unwrap().statements;
+    let mut ids = Ids::default();
+    for statement in &statements {
+        statement::Walker::walk_stmt(&mut ids, statement);
+    }
+    ids.0
+}
+
+fn distinct(mut ids: Vec<NodeId>) -> bool {
+    let count = ids.len();
+    ids.sort();
+    ids.dedup();
+    ids.len() == count
+}
+
+// including the nodes the parser desugars loops into
+#[test]
+fn nodes_have_distinct_ids() {
+    assert!(distinct(ids(SOURCE)));
+}
+
+// i.e. the lines of a REPL session or a script and the modules it imports
+#[test]
+fn scripts_parsed_in_a_session_have_distinct_ids() {
+    let first = ids(SOURCE);
+    let second = ids(SOURCE);
+    assert_eq!(first.len(), second.len());
+    assert!(distinct([first, second].concat()));
+}
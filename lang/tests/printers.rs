@@ -0,0 +1,201 @@
+use lang::ast::expression::Expr;
+use lang::ast::node_id::NodeId;
+use lang::ast::rpn_printer::RpnPrinter;
+use lang::ast::source_printer::SourcePrinter;
+use lang::ast::statement::Stmt;
+use lang::lexical_analysis::Token;
+
+fn parse(expr: &str) -> Expr {
+    let script = lang::ast::parse(format!("print {expr};"))
+        .unwrap_or_else(|| panic!("`{expr}` doesn't parse"));
+    match script.statements.into_iter().next() {
+        Some(Stmt::Print(stmt)) => stmt.expression,
+        _ => panic!("`{expr}` isn't an expression"),
+    }
+}
+
+fn rpn(expr: &str) -> String {
+    RpnPrinter {}.print(&parse(expr))
+}
+
+fn source(expr: &str) -> String {
+    SourcePrinter {}.print(&parse(expr))
+}
+
+const EXPRESSIONS: &[&str] = &[
+    "1 + 2 * 3",
+    "(1 + 2) * 3",
+    "1 - (2 - 3)",
+    "(1 - 2) - 3",
+    "((1)) / (2 / (3 * 4))",
+    "-(-1)",
+    "!(a == b) != !c",
+    "-(a + b) * -c",
+    "a or b and c",
+    "(a or b) and c",
+    "a == (b == c)",
+    "a = b = c",
+    "(a = 1) + 2",
+    "a or (b = c)",
+    "(f)(1, 2 + 3)(g(h))",
+    "(a + b).c.d(e)",
+    "(a.b = c).d",
+    "a.b = c.d = e",
+    "(a = b)[c + d] = [1, (2), [3]]",
+    "(-a)[0]",
+    "{\"k\": (1 + 2), 3: {}}[\"k\"]",
+    "\"a string\" + nil + true + false + 1.5",
+];
+
+// tokens are compared without their position in the source
+fn strip_token(token: &mut Token) {
+    token.line = 0;
+    token.offset = 0;
+}
+
+// the tree without its groupings, which printing the source drops, nor ids and positions, which
+// differ between the parses
+fn strip(expr: &mut Expr) {
+    if let Expr::Grouping(grouping) = expr {
+        *expr = (*grouping.expression).clone();
+        return strip(expr);
+    }
+
+    let id = NodeId::new(0);
+    match expr {
+        Expr::Assign(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.name);
+            strip(&mut expr.value);
+        }
+        Expr::Binary(expr) => {
+            expr.id = id;
+            strip(&mut expr.left);
+            strip_token(&mut expr.operator);
+            strip(&mut expr.right);
+        }
+        Expr::Call(expr) => {
+            expr.id = id;
+            strip(&mut expr.callee);
+            strip_token(&mut expr.paren);
+            expr.arguments.iter_mut().for_each(strip);
+        }
+        Expr::Get(expr) => {
+            expr.id = id;
+            strip(&mut expr.object);
+            strip_token(&mut expr.name);
+        }
+        Expr::Grouping(_) => unreachable!(),
+        Expr::Index(expr) => {
+            expr.id = id;
+            strip(&mut expr.object);
+            strip_token(&mut expr.bracket);
+            strip(&mut expr.index);
+        }
+        Expr::IndexSet(expr) => {
+            expr.id = id;
+            strip(&mut expr.object);
+            strip_token(&mut expr.bracket);
+            strip(&mut expr.index);
+            strip(&mut expr.value);
+        }
+        Expr::List(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.bracket);
+            expr.elements.iter_mut().for_each(strip);
+            strip_token(&mut expr.closing_bracket);
+        }
+        Expr::Literal(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.token);
+        }
+        Expr::Logical(expr) => {
+            expr.id = id;
+            strip(&mut expr.left);
+            strip_token(&mut expr.operator);
+            strip(&mut expr.right);
+        }
+        Expr::Map(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.brace);
+            expr.keys.iter_mut().for_each(strip);
+            expr.values.iter_mut().for_each(strip);
+            strip_token(&mut expr.closing_brace);
+        }
+        Expr::Set(expr) => {
+            expr.id = id;
+            strip(&mut expr.object);
+            strip_token(&mut expr.name);
+            strip(&mut expr.value);
+        }
+        Expr::Super(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.keyword);
+            strip_token(&mut expr.method);
+        }
+        Expr::This(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.keyword);
+        }
+        Expr::Unary(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.operator);
+            strip(&mut expr.right);
+        }
+        Expr::Variable(expr) => {
+            expr.id = id;
+            strip_token(&mut expr.name);
+        }
+    }
+}
+
+fn stripped(expr: &str) -> Expr {
+    let mut expr = parse(expr);
+    strip(&mut expr);
+    expr
+}
+
+#[test]
+fn source_round_trips() {
+    for expr in EXPRESSIONS {
+        let printed = source(expr);
+        assert_eq!(
+            stripped(expr),
+            stripped(&printed),
+            "`{expr}` printed as `{printed}`"
+        );
+        assert_eq!(printed, source(&printed), "`{expr}` printed as `{printed}`");
+    }
+}
+
+// the comparison sees through groupings but not through a different shape of the tree
+#[test]
+fn stripped_trees_differ_by_shape() {
+    assert_eq!(stripped("((a)) + (b)"), stripped("a + b"));
+    assert_ne!(stripped("(a - b) - c"), stripped("a - (b - c)"));
+    assert_ne!(stripped("a + b"), stripped("a - b"));
+}
+
+#[test]
+fn source_keeps_needed_parentheses() {
+    assert_eq!(source("((1 + 2)) * 3"), "(1 + 2) * 3");
+    assert_eq!(source("1 + (2 * 3)"), "1 + 2 * 3");
+    assert_eq!(source("(1 - 2) - 3"), "1 - 2 - 3");
+    assert_eq!(source("1 - (2 - 3)"), "1 - (2 - 3)");
+    assert_eq!(source("-(-a)"), "--a");
+    assert_eq!(source("!(a and b)"), "!(a and b)");
+    assert_eq!(source("(a or b) and (c and d)"), "(a or b) and (c and d)");
+    assert_eq!(source("(a + b).c"), "(a + b).c");
+    assert_eq!(source("a.b = (c = d)"), "a.b = c = d");
+    assert_eq!(source("f((a), [(b)], {(c): (d)})"), "f(a, [b], {c: d})");
+}
+
+#[test]
+fn rpn_puts_operators_last() {
+    assert_eq!(rpn("(1 + 2) * (4 - 3)"), "1 2 + 4 3 - *");
+    assert_eq!(rpn("-a - !b"), "a neg b ! -");
+    assert_eq!(rpn("a = b or c and d"), "b c d and or =a");
+    assert_eq!(rpn("f(1, g())"), "f 1 g call/0 call/2");
+    assert_eq!(rpn("a.b.c = [1, 2][0]"), "a .b 1 2 list/2 0 [] .c=");
+    assert_eq!(rpn("m[\"k\"] = {1: 2}"), "m \"k\" 1 2 map/1 []=");
+}
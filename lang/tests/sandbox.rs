@@ -0,0 +1,183 @@
+use std::thread;
+use std::time::Duration;
+
+use lang::{Error, ErrorKind, Limits, Lox, Value};
+
+fn backends() -> [Lox; 2] {
+    [Lox::new(), Lox::with_vm()]
+}
+
+// kind and line of the error which stopped the script
+fn stopped(mut lox: Lox, limits: Limits, source: &str) -> (ErrorKind, usize) {
+    lox.set_limits(limits);
+    match lox.eval(source) {
+        Err(Error::Runtime(error)) => (error.kind, error.line),
+        result => panic!("expected a runtime error, got {result:?}"),
+    }
+}
+
+fn heap(max: usize) -> Limits {
+    Limits {
+        max_heap: Some(max),
+        ..Limits::default()
+    }
+}
+
+#[test]
+fn growing_strings_hit_the_heap_limit() {
+    for lox in backends() {
+        let source = "var s = \"a\";\nwhile (true)\n  s = s + s;";
+        assert_eq!(stopped(lox, heap(1000), source), (ErrorKind::HeapLimit, 3));
+    }
+}
+
+#[test]
+fn growing_collections_hit_the_heap_limit() {
+    let scripts = [
+        "var l = [1];\nwhile (true) {\n  var n = len(l);\n  for (var i = 0; i < n; i = i + 1) l.push(l[i]);\n}",
+        "var m = {};\nvar i = 0;\nwhile (true) {\n  m[i] = i;\n  i = i + 1;\n}",
+        "var l = [];\nwhile (true) l.push(split(\"aaaaaaaa\", \"\"));",
+    ];
+    for source in scripts {
+        for lox in backends() {
+            let (kind, _) = stopped(lox, heap(1000), source);
+            assert_eq!(kind, ErrorKind::HeapLimit, "{source}");
+        }
+    }
+}
+
+// objects which die young are collected instead of counting against the limit
+#[test]
+fn garbage_does_not_count_against_the_heap_limit() {
+    let limits = Limits {
+        max_heap: Some(100_000),
+        max_steps: Some(100_000),
+        ..Limits::default()
+    };
+    let source = "while (true) {\n  var s = \"abc\" + to_string(clock());\n  var l = [s, s];\n}";
+    for lox in backends() {
+        assert_eq!(stopped(lox, limits, source).0, ErrorKind::StepLimit);
+    }
+}
+
+#[test]
+fn budget_errors_have_the_line_of_the_running_code() {
+    let limits = Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    };
+    for lox in backends() {
+        let (kind, line) = stopped(lox, limits, "var i = 0;\nwhile (true) {\n}");
+        assert_eq!(kind, ErrorKind::StepLimit);
+        assert_ne!(line, 0);
+    }
+}
+
+fn kind(result: Result<Value, Error>) -> ErrorKind {
+    match result {
+        Err(Error::Runtime(error)) => error.kind,
+        result => panic!("expected a runtime error, got {result:?}"),
+    }
+}
+
+// enough steps for the cancellation to be checked many times, so a lost one fails the test instead
+// of looping forever
+fn bounded() -> Limits {
+    Limits {
+        max_steps: Some(10_000_000),
+        ..Limits::default()
+    }
+}
+
+#[test]
+fn cancelling_before_the_run_stops_it() {
+    for mut lox in backends() {
+        lox.set_limits(bounded());
+        lox.cancel_handle().cancel();
+        assert_eq!(kind(lox.eval("while (true) {}")), ErrorKind::Cancelled);
+
+        // the cancellation was for that run only
+        let source = "var i = 0;\nwhile (i < 10000) i = i + 1;\ni;";
+        assert!(matches!(lox.eval(source), Ok(Value::Number(n)) if n == 10000.0));
+    }
+}
+
+#[test]
+fn cancelling_from_another_thread_stops_the_run() {
+    for mut lox in backends() {
+        lox.set_limits(bounded());
+        let handle = lox.cancel_handle();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.cancel();
+        });
+        assert_eq!(kind(lox.eval("while (true) {}")), ErrorKind::Cancelled);
+        canceller.join().unwrap();
+    }
+}
+
+// the 2 MiB stack of a spawned thread is far smaller than the one the CLI runs scripts on
+#[test]
+fn deep_recursion_is_reported_on_a_small_stack() {
+    let source = "fun f(n) { if (n == 0) return 0; return f(n - 1) + 1; }\nf(100000);";
+    let runner = thread::Builder::new().stack_size(2 * 1024 * 1024);
+    let result = runner
+        .spawn(move || backends().map(|lox| stopped(lox, Limits::default(), source).0))
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(result, [ErrorKind::StackOverflow; 2]);
+}
+
+// too deep a tree would overflow the stack of the parser or of a pass walking it, on the 2 MiB stack
+// of a test thread as much as in the host
+#[test]
+fn deep_nesting_is_a_compile_error() {
+    let limits = Limits {
+        max_steps: Some(1_000_000),
+        max_heap: Some(1_000_000),
+        ..Limits::default()
+    };
+    let scripts = [
+        (
+            format!("print {}1{};", "(".repeat(20_000), ")".repeat(20_000)),
+            "Expression",
+        ),
+        (format!("print {}1;", "-".repeat(100_000)), "Expression"),
+        (format!("print 1{};", " + 1".repeat(100_000)), "Expression"),
+        (
+            format!("var a;\na{} = 1;", " = a".repeat(50_000)),
+            "Expression",
+        ),
+        (
+            format!("{}{}", "{".repeat(50_000), "}".repeat(50_000)),
+            "Statement",
+        ),
+    ];
+    for (source, kind) in &scripts {
+        for mut lox in backends() {
+            lox.set_limits(limits);
+            match lox.eval(source) {
+                Err(Error::Compile(errors)) => {
+                    let messages: Vec<&str> =
+                        errors.iter().map(|error| error.message.as_str()).collect();
+                    assert_eq!(messages, [format!("{kind} nested too deeply.")]);
+                }
+                result => panic!("expected a compile error, got {result:?}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn shallow_nesting_runs() {
+    let source = format!(
+        "{}1{} + 1{};",
+        "(".repeat(10),
+        ")".repeat(10),
+        " + 1".repeat(200)
+    );
+    for mut lox in backends() {
+        assert!(matches!(lox.eval(&source), Ok(Value::Number(n)) if n == 202.0));
+    }
+}
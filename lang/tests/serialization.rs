@@ -0,0 +1,105 @@
+use lang::ast;
+use lang::bytecode::chunk::OpCode;
+use lang::bytecode::compiler::Compiler;
+use lang::bytecode::object::Prototype;
+use lang::bytecode::serialization::{deserialize, serialize, LoadError, FORMAT_VERSION};
+
+fn compile(source: &str) -> Vec<u8> {
+    let statements = ast::parse(source.to_string()).unwrap().statements;
+    serialize(&Compiler::new().compile(&statements).unwrap())
+}
+
+// a script made of the given bytes, as if it had been written by hand
+fn assemble(code: &[u8]) -> Vec<u8> {
+    let mut function = Prototype::new(String::new());
+    for byte in code {
+        function.chunk.write(*byte, 1);
+    }
+    serialize(&function)
+}
+
+fn corruption(bytes: &[u8]) -> String {
+    match deserialize(bytes) {
+        Err(LoadError::Corrupted(reason)) => reason,
+        Err(error) => panic!("expected a corrupted script, got: {error}"),
+        Ok(_) => panic!("expected a corrupted script, but it loaded"),
+    }
+}
+
+#[test]
+fn compiled_scripts_load() {
+    let bytes = compile(
+        "fun f(a) { var b = a; fun g() { return b; } try { return g(); } finally { print 1; } }
+         class A { m() { return 1; } } class B < A { m() { return super.m(); } }
+         for (x in [1, 2]) print f(x) + B().m();",
+    );
+    assert!(deserialize(&bytes).is_ok());
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let mut bytes = compile("print 1;");
+    bytes[4..6].copy_from_slice(&(FORMAT_VERSION - 1).to_le_bytes());
+
+    match deserialize(&bytes) {
+        Err(LoadError::VersionMismatch { found }) => assert_eq!(found, FORMAT_VERSION - 1),
+        _ => panic!("expected a version mismatch"),
+    }
+}
+
+#[test]
+fn truncated_scripts_are_rejected() {
+    let bytes = compile("print 1;");
+    assert!(matches!(deserialize(b"lox"), Err(LoadError::NotBytecode)));
+    assert_eq!(corruption(&bytes[..8]), "unexpected end of file");
+    assert_eq!(corruption(&bytes[..bytes.len() - 1]), "checksum mismatch");
+}
+
+#[test]
+fn damaged_scripts_are_rejected() {
+    let mut bytes = compile("print 1;");
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0xff;
+    assert_eq!(corruption(&bytes), "checksum mismatch");
+}
+
+#[test]
+fn stack_underflow_is_rejected() {
+    let code = [
+        OpCode::Pop as u8,
+        OpCode::Pop as u8,
+        OpCode::Pop as u8,
+        OpCode::Nil as u8,
+        OpCode::Return as u8,
+    ];
+    assert_eq!(
+        corruption(&assemble(&code)),
+        "stack underflow at <script>:0001"
+    );
+}
+
+#[test]
+fn locals_outside_the_frame_are_rejected() {
+    let code = [OpCode::GetLocal as u8, 0, 200, OpCode::Return as u8];
+    assert_eq!(
+        corruption(&assemble(&code)),
+        "local slot out of range at <script>:0000"
+    );
+}
+
+#[test]
+fn paths_with_different_heights_are_rejected() {
+    // the jump skips pushing a value which the other path leaves on the stack
+    let code = [
+        OpCode::True as u8,
+        OpCode::JumpIfFalse as u8,
+        0,
+        1,
+        OpCode::Nil as u8,
+        OpCode::Return as u8,
+    ];
+    assert_eq!(
+        corruption(&assemble(&code)),
+        "paths disagree on the stack height at <script>:0004"
+    );
+}